@@ -0,0 +1,319 @@
+//! Calls found in the abstract code kept by `debug_info`: Erlang's
+//! `erl_abstract_code` forms, the older Abst chunk, and the expanded
+//! definitions of Elixir's `elixir_erl` backend. Unlike the disassembled
+//! code, it has the line of every call.
+
+use std::convert::TryFrom;
+
+use eetf::Term;
+use fxhash::FxHashSet;
+
+use crate::etf;
+
+/// A function of the module, with the calls its body makes, including the
+/// ones in the funs it creates
+#[derive(Debug)]
+pub struct Function<'a> {
+    pub name: String,
+    pub arity: u32,
+    pub calls: Vec<Call<'a>>,
+}
+
+#[derive(Debug)]
+pub struct Call<'a> {
+    /// `None` for a local call, or for an auto-imported BIF in Erlang code
+    pub module: Option<&'a str>,
+    pub function: &'a str,
+    pub arity: u32,
+    pub line: Option<u32>,
+}
+
+/// The functions of the Dbgi chunk, `{debug_info_v1, Backend, Metadata}`,
+/// or of the Abst chunk, `{raw_abstract_v1, Forms}`. `None` for other
+/// backends, or if the module was compiled without `debug_info`.
+pub fn functions(debug_info: &Term) -> Option<Vec<Function<'_>>> {
+    match etf::tuple(debug_info)? {
+        [tag, backend, metadata] if etf::atom(tag) == Some("debug_info_v1") => {
+            match etf::atom(backend)? {
+                "erl_abstract_code" => match etf::tuple(metadata)? {
+                    [forms, _options] => erlang_functions(etf::list(forms)?),
+                    _ => None,
+                },
+                "elixir_erl" => match etf::tuple(metadata)? {
+                    [tag, map, ..] if etf::atom(tag) == Some("elixir_v1") => elixir_functions(map),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        [tag, forms] if etf::atom(tag) == Some("raw_abstract_v1") => {
+            erlang_functions(etf::list(forms)?)
+        }
+        _ => None,
+    }
+}
+
+/// `{function, Anno, Name, Arity, Clauses}` forms
+fn erlang_functions(forms: &[Term]) -> Option<Vec<Function<'_>>> {
+    let functions = forms
+        .iter()
+        .filter_map(|form| match etf::tuple(form)? {
+            [tag, _, name, arity, clauses] if etf::atom(tag) == Some("function") => {
+                let mut calls = vec![];
+                erlang_calls(clauses, &mut calls);
+                Some(Function {
+                    name: etf::atom(name)?.to_string(),
+                    arity: etf::int(arity)? as u32,
+                    calls,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    Some(functions)
+}
+
+/// Collects the calls and fun references nested anywhere in an Erlang
+/// expression
+fn erlang_calls<'a>(expr: &'a Term, out: &mut Vec<Call<'a>>) {
+    let elements = match expr {
+        Term::List(list) => {
+            return list
+                .elements
+                .iter()
+                .for_each(|elem| erlang_calls(elem, out))
+        }
+        Term::Tuple(tuple) => &tuple.elements[..],
+        _ => return,
+    };
+    match elements {
+        // {call, Anno, {remote, _, {atom, _, M}, {atom, _, F}}, Args}
+        // {call, Anno, {atom, _, F}, Args}
+        [tag, anno, callee, args] if etf::atom(tag) == Some("call") => {
+            let arity = etf::list(args).map_or(0, <[Term]>::len) as u32;
+            let callee = match etf::tuple(callee) {
+                Some([tag, _, module, function]) if etf::atom(tag) == Some("remote") => {
+                    erlang_atom(module)
+                        .zip(erlang_atom(function))
+                        .map(|(module, function)| (Some(module), function))
+                }
+                Some(_) => erlang_atom(callee).map(|function| (None, function)),
+                None => None,
+            };
+            if let Some((module, function)) = callee {
+                out.push(Call {
+                    module,
+                    function,
+                    arity,
+                    line: erlang_line(anno),
+                });
+            }
+        }
+        // {'fun', Anno, {function, F, A}}
+        // {'fun', Anno, {function, {atom, _, M}, {atom, _, F}, {integer, _, A}}}
+        [tag, anno, fun] if etf::atom(tag) == Some("fun") => {
+            let reference = match etf::tuple(fun) {
+                Some([tag, function, arity]) if etf::atom(tag) == Some("function") => {
+                    Some((None, etf::atom(function), etf::int(arity)))
+                }
+                Some([tag, module, function, arity]) if etf::atom(tag) == Some("function") => {
+                    // Before OTP 20 the names were bare atoms
+                    Some((
+                        erlang_atom(module).or(etf::atom(module)),
+                        erlang_atom(function).or(etf::atom(function)),
+                        erlang_int(arity).or(etf::int(arity)),
+                    ))
+                }
+                _ => None,
+            };
+            match reference {
+                Some((module, Some(function), Some(arity))) => out.push(Call {
+                    module,
+                    function,
+                    arity: arity as u32,
+                    line: erlang_line(anno),
+                }),
+                _ => erlang_calls(fun, out),
+            }
+            return;
+        }
+        _ => {}
+    }
+    elements.iter().for_each(|elem| erlang_calls(elem, out))
+}
+
+/// The name of an `{atom, Anno, Name}` expression
+fn erlang_atom(expr: &Term) -> Option<&str> {
+    match etf::tuple(expr)? {
+        [tag, _, name] if etf::atom(tag) == Some("atom") => etf::atom(name),
+        _ => None,
+    }
+}
+
+fn erlang_int(expr: &Term) -> Option<i64> {
+    match etf::tuple(expr)? {
+        [tag, _, value] if etf::atom(tag) == Some("integer") => etf::int(value),
+        _ => None,
+    }
+}
+
+/// The line of an annotation: `Line`, `{Line, Column}`, or a list with
+/// `{location, Location}`
+fn erlang_line(anno: &Term) -> Option<u32> {
+    let line = match anno {
+        Term::FixInteger(_) => etf::int(anno),
+        Term::Tuple(_) => etf::int(etf::tuple(anno)?.first()?),
+        Term::List(_) => return erlang_line(etf::proplist_get(anno, "location")?),
+        _ => None,
+    };
+    line.and_then(|line| u32::try_from(line).ok())
+        .filter(|&line| line > 0)
+}
+
+/// The `definitions` of the Elixir module map, as
+/// `{{Name, Arity}, Kind, Meta, Clauses}`, where every clause is
+/// `{Meta, Args, Guards, Body}`. Macros get the name and arity they're
+/// compiled to.
+fn elixir_functions(map: &Term) -> Option<Vec<Function<'_>>> {
+    let definitions = etf::list(etf::map_get(map, "definitions")?)?;
+    let definitions: Vec<_> = definitions
+        .iter()
+        .filter_map(|definition| match etf::tuple(definition)? {
+            [name_arity, kind, _, clauses] => match etf::tuple(name_arity)? {
+                [name, arity] => Some((
+                    etf::atom(name)?,
+                    etf::int(arity)? as u32,
+                    etf::atom(kind)?,
+                    clauses,
+                )),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    // Local macros are expanded, only functions are called
+    let defined: FxHashSet<_> = definitions
+        .iter()
+        .filter(|(_, _, kind, _)| matches!(*kind, "def" | "defp"))
+        .map(|&(name, arity, _, _)| (name, arity))
+        .collect();
+
+    let functions = definitions
+        .iter()
+        .map(|&(name, arity, kind, clauses)| {
+            let mut calls = vec![];
+            elixir_calls(clauses, &defined, &mut calls);
+            let (name, arity) = match kind {
+                "defmacro" | "defmacrop" => (format!("MACRO-{}", name), arity + 1),
+                _ => (name.to_string(), arity),
+            };
+            Function { name, arity, calls }
+        })
+        .collect();
+    Some(functions)
+}
+
+/// Collects the calls and captures nested anywhere in expanded Elixir AST,
+/// where imported functions are already remote calls
+fn elixir_calls<'a>(expr: &'a Term, defined: &FxHashSet<(&str, u32)>, out: &mut Vec<Call<'a>>) {
+    let elements = match expr {
+        Term::List(list) => {
+            return list
+                .elements
+                .iter()
+                .for_each(|elem| elixir_calls(elem, defined, out))
+        }
+        Term::Tuple(tuple) => &tuple.elements[..],
+        _ => return,
+    };
+    if let [callee, meta, Term::List(args)] = elements {
+        let arity = args.elements.len() as u32;
+        let line = || {
+            etf::proplist_get(meta, "line")
+                .and_then(etf::int)
+                .and_then(|line| u32::try_from(line).ok())
+        };
+        // {{:., _, [Module, Function]}, Meta, Args}
+        if let Some((module, function)) = elixir_remote(callee) {
+            out.push(Call {
+                module: Some(module),
+                function,
+                arity,
+                line: line(),
+            });
+            return args
+                .elements
+                .iter()
+                .for_each(|elem| elixir_calls(elem, defined, out));
+        }
+        // {:&, _, [{:/, _, [Function, Arity]}]}, with a remote call of no
+        // arguments or a local call as the function
+        if etf::atom(callee) == Some("&") {
+            if let Some((module, function, arity)) = elixir_capture(&args.elements, defined) {
+                out.push(Call {
+                    module,
+                    function,
+                    arity,
+                    line: line(),
+                });
+                return;
+            }
+        }
+        if let Some(function) = etf::atom(callee) {
+            if defined.contains(&(function, arity)) {
+                out.push(Call {
+                    module: None,
+                    function,
+                    arity,
+                    line: line(),
+                });
+            }
+        }
+    }
+    elements
+        .iter()
+        .for_each(|elem| elixir_calls(elem, defined, out))
+}
+
+/// The module and function of a remote call's `{:., Meta, [Module,
+/// Function]}`, when both are atoms
+fn elixir_remote(callee: &Term) -> Option<(&str, &str)> {
+    match etf::tuple(callee)? {
+        [dot, _, target] if etf::atom(dot) == Some(".") => match etf::list(target)? {
+            [module, function] => Some((etf::atom(module)?, etf::atom(function)?)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The function of a `&Module.function/arity` or `&function/arity` capture,
+/// from the arguments of `:&`
+fn elixir_capture<'a>(
+    args: &'a [Term],
+    defined: &FxHashSet<(&str, u32)>,
+) -> Option<(Option<&'a str>, &'a str, u32)> {
+    let (function, arity) = match args {
+        [slash] => match etf::tuple(slash)? {
+            [op, _, operands] if etf::atom(op) == Some("/") => match etf::list(operands)? {
+                [function, arity] => (function, etf::int(arity)? as u32),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match etf::tuple(function)? {
+        [callee, _, args] if etf::list(args).is_some_and(<[Term]>::is_empty) => {
+            let (module, function) = elixir_remote(callee)?;
+            Some((Some(module), function, arity))
+        }
+        [name, _, context] if etf::atom(context).is_some() => {
+            let name = etf::atom(name)?;
+            defined
+                .contains(&(name, arity))
+                .then_some((None, name, arity))
+        }
+        _ => None,
+    }
+}
@@ -1,105 +1,3179 @@
-use fxhash::FxHashMap;
-use petgraph::algo;
+use std::{
+    cmp::Reverse,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use anyhow::Result;
+use fxhash::{FxHashMap, FxHashSet};
+use petgraph::{algo, graphmap::DiGraphMap};
 use rayon::prelude::*;
+use serde::Deserialize;
 
-use crate::types::{AppDeps, AppModules, Atom, Interner, Modules};
+use crate::{
+    otp::{self, Obsolete},
+    pattern::{Excludes, FunctionPattern, Pattern},
+    progress::{Callback, Event},
+    rules::{self, ForbiddenCalls, Group, Layer, RiskyCalls, TestCode},
+    suggest,
+    types::{
+        AppCallbacks, AppDeps, AppModules, AppPaths, AppVersions, Atom, DeclaredDeps, Interner,
+        Mfa, Module, Modules, RegisteredNames, ShadowedModules, Workspace,
+    },
+};
 
+/// A loaded workspace ready for queries. Nothing is mutated after
+/// construction, all queries take `&self`, so a single analyzer can be
+/// shared between threads, e.g. behind an `Arc`, and serve them
+/// concurrently.
 pub struct Analyzer {
+    interner: Interner,
     modules: Modules,
     modules_rev: FxHashMap<Atom, Atom>,
+    exports_by_name: FxHashMap<Atom, Vec<(Atom, u32)>>,
+    /// Modules calling every function, by `(module, function, arity)`
+    callers: FxHashMap<(Atom, Atom, u32), Vec<Atom>>,
+    /// Other modules calling into every module
+    importers: FxHashMap<Atom, Vec<Atom>>,
+    /// Applications depending on each other, sorted by name
+    app_cycles: Vec<Vec<Atom>>,
     app_modules: AppModules,
     app_deps: AppDeps,
+    declared_deps: DeclaredDeps,
+    included_apps: DeclaredDeps,
+    optional_deps: DeclaredDeps,
+    runtime_deps: DeclaredDeps,
+    registered: RegisteredNames,
+    app_paths: AppPaths,
+    app_versions: AppVersions,
+    app_callbacks: AppCallbacks,
+    shadowed: ShadowedModules,
+    shadowed_builds: FxHashMap<PathBuf, u64>,
+    listed_modules: AppModules,
+    progress: Option<Callback>,
+    source_roots: Vec<SourceRoot>,
+    sample: Option<Sample>,
+    only_modules: Option<FxHashSet<Atom>>,
+    checks: Option<FxHashSet<String>>,
+    severities: FxHashMap<String, Severity>,
+    excluded: FxHashSet<Atom>,
+    excludes: Excludes,
+    entry_points: Vec<FunctionPattern>,
+    min_cycle_size: usize,
+    start_only_deps: Vec<Pattern>,
+    otp_version: Option<u32>,
+    forbidden_calls: Vec<ForbiddenCalls>,
+    /// Patterns of calls the risky_call check reports, with the risk
+    risky_calls: Vec<(FunctionPattern, String)>,
+    layers: Vec<Layer>,
+    groups: Vec<Group>,
+    /// Indexes into `groups` of every loaded application in a group
+    app_groups: FxHashMap<Atom, Vec<usize>>,
+    /// Modules private to their application, see `internal`
+    internal_modules: Vec<Pattern>,
+    /// Index into `layers` of every loaded application in a layer
+    app_layers: FxHashMap<Atom, usize>,
+    test_code: TestCode,
+    /// Applications loaded only from test lib paths
+    test_apps: FxHashSet<Atom>,
+    reachability: bool,
+    /// Computed on first use, see `reached`
+    reached: OnceLock<Reached>,
+}
+
+/// Functions and modules reached from the roots, see `with_reachability`
+#[derive(Default)]
+struct Reached {
+    functions: FxHashSet<Mfa>,
+    modules: FxHashSet<Atom>,
+}
+
+/// Names of all the checks, as returned by `AnalysisResult::check_name`
+pub const CHECKS: &[&str] = &[
+    "undefined_module",
+    "undefined_function",
+    "undefined_dynamic_call",
+    "undefined_capture",
+    "undefined_behaviour",
+    "undefined_parse_transform",
+    "deprecated_call",
+    "otp_upgrade",
+    "forbidden_call",
+    "risky_call",
+    "layer_violation",
+    "group_boundary",
+    "internal_module_call",
+    "test_code_call",
+    "missing_dependency",
+    "dependency_not_found",
+    "unused_dependency",
+    "redundant_dependency",
+    "self_dependency",
+    "undeclared_runtime_dependency",
+    "unused_runtime_dependency",
+    "missing_source",
+    "missing_priv_file",
+    "missing_on_load",
+    "nif_library",
+    "unused_export",
+    "locals_not_used",
+    "dead_module",
+    "duplicate_module",
+    "different_build",
+    "otp_module_clash",
+    "registered_name_clash",
+    "unlisted_module",
+    "missing_beam",
+    "app_callback",
+    "app_cycle",
+    "module_cycle",
+];
+
+/// Checks that only run when enabled explicitly, as they report a lot on
+/// most code bases
+pub const OPT_IN_CHECKS: &[&str] = &[
+    "unused_export",
+    "dead_module",
+    "module_cycle",
+    "nif_library",
+    "redundant_dependency",
+    "risky_call",
+    "internal_module_call",
+];
+
+/// Applications every `.app` file has to list, whether it calls them or not
+const REQUIRED_DEPS: &[&str] = &["kernel", "stdlib"];
+
+/// Functions the `mod` callback of an application has to export
+const APP_CALLBACKS: &[(&str, u32)] = &[("start", 2), ("stop", 1)];
+
+/// Smallest module cycle reported by default
+pub const MIN_CYCLE_SIZE: usize = 2;
+
+/// Analyze only a random subset of modules. The choice depends only on the
+/// seed and the module name, so the same modules are picked on every run.
+#[derive(Debug, Copy, Clone)]
+pub struct Sample {
+    pub fraction: f64,
+    pub seed: u64,
+}
+
+impl Sample {
+    fn contains(&self, name: &str) -> bool {
+        let hash = fxhash::hash64(&(self.seed, name));
+        (hash as f64) < self.fraction * u64::MAX as f64
+    }
+}
+
+/// Checkout the recorded source paths are verified against. Written either
+/// as a single directory or as `RECORDED=CHECKOUT` when the beams were
+/// compiled elsewhere, for example in a CI container.
+#[derive(Debug, Clone)]
+pub struct SourceRoot {
+    pub recorded: PathBuf,
+    pub checkout: PathBuf,
 }
 
+impl FromStr for SourceRoot {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<SourceRoot> {
+        let (recorded, checkout) = s.split_once('=').unwrap_or((s, s));
+        if recorded.is_empty() || checkout.is_empty() {
+            anyhow::bail!("expected DIR or RECORDED=CHECKOUT, got: {}", s);
+        }
+        Ok(SourceRoot {
+            recorded: PathBuf::from(recorded),
+            checkout: PathBuf::from(checkout),
+        })
+    }
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone)]
 pub enum AnalysisResult {
     MissingModule(Atom),
-    MissingFunction(Atom, Atom, u32),
+    /// An undefined module that by its name belongs to an optional
+    /// dependency of the application, which isn't present
+    MissingOptionalModule {
+        module: Atom,
+        dep: Atom,
+    },
+    /// An undefined module that every calling function checks is loaded
+    /// first, with `check` like `code:ensure_loaded/1`
+    CheckedMissingModule {
+        module: Atom,
+        check: Mfa,
+    },
+    /// With exports of the module with a similar name that could have been
+    /// meant
+    MissingFunction(Atom, Atom, u32, Vec<(Atom, u32)>),
+    /// A call to a function only exported with other arities, reported by
+    /// the same check as an undefined function
+    ArityMismatch {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        /// Exported arities of the function
+        arities: Vec<u32>,
+    },
+    /// A remote call to an Elixir macro, which only exists at compile
+    /// time as the `MACRO-` export taking the caller's environment first
+    MacroCall {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+    },
+    /// A call to a function the module defines without exporting it, found
+    /// in its local function table
+    UnexportedFunction {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+    },
+    /// A `{Module, Function, Args}` tuple in the literals naming a function
+    /// that doesn't exist. Tuples are only taken for calls when the module
+    /// is loaded.
+    UndefinedDynamicCall {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+    },
+    /// A `fun Module:Function/Arity` capture of a function that doesn't
+    /// exist. Creating it succeeds, only calling it fails, so it's often a
+    /// deliberate late-bound reference.
+    UndefinedCapture {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        /// The module isn't loaded
+        module_missing: bool,
+    },
+    /// A parse transform the module was compiled with that isn't loaded
+    UndefinedParseTransform {
+        transform: Atom,
+    },
+    /// A `-behaviour` that's neither loaded nor a known OTP behaviour
+    UndefinedBehaviour {
+        behaviour: Atom,
+    },
+    /// Call to a function its module declares `-deprecated`, with the
+    /// description from the declaration
+    DeprecatedCall {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        hint: Option<String>,
+    },
+    /// Call to an OTP function deprecated or removed by the OTP release
+    /// targeted with `with_otp_version`
+    ObsoleteCall {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        obsolete: Obsolete,
+    },
+    /// Call matching a configured `forbidden_calls` rule, with the pattern
+    /// it matched and the reason of the rule
+    ForbiddenCall {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        pattern: String,
+        reason: Option<String>,
+    },
+    /// Call of a built-in or configured risky function, reported for a
+    /// security audit, with the risk
+    RiskyCall {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        reason: String,
+    },
+    /// Production code calling into test code
+    TestCodeCall {
+        module: Atom,
+    },
+    /// Call into an application of a layer the caller's layer may not call
+    LayerViolation {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        from_layer: String,
+        to_layer: String,
+    },
+    /// Call into an application of groups the caller's groups may not call,
+    /// with the names of both
+    GroupBoundary {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        from_groups: Vec<String>,
+        to_groups: Vec<String>,
+    },
+    /// Call from another application into a module private to `owner`, or
+    /// into a function hidden from the docs of a public one
+    InternalModuleCall {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+        owner: Atom,
+        /// Only the function is private
+        hidden_function: bool,
+    },
     MissingDependency {
         module: Atom,
         app_from: Atom,
         app_to: Atom,
     },
+    /// Listed in `applications` or `included_applications`, but not loaded
+    DependencyNotFound {
+        app: Atom,
+        dep: Atom,
+        /// Whether `dep` is an OTP application, so the OTP lib directory is
+        /// likely missing from the lib paths
+        otp: bool,
+        /// Listed in `optional_applications`, so it may be missing
+        optional: bool,
+    },
+    /// Declared in the `.app` file, but no module of the application calls
+    /// into it
+    UnusedDependency {
+        app: Atom,
+        dep: Atom,
+    },
+    /// An unused dependency that another dependency of `app` already
+    /// depends on, directly or not, so it's started anyway
+    RedundantDependency {
+        app: Atom,
+        dep: Atom,
+        /// The dependency it's implied by
+        via: Atom,
+    },
+    /// `app` lists itself in the `applications` of its `.app` file
+    SelfDependency {
+        app: Atom,
+    },
+    /// An application called by `app`, missing from the
+    /// `runtime_dependencies` of its `.app` file
+    UndeclaredRuntimeDependency {
+        app: Atom,
+        dep: Atom,
+    },
+    /// In the `runtime_dependencies` of the `.app` file of `app`, but no
+    /// module of the application calls into it
+    UnusedRuntimeDependency {
+        app: Atom,
+        dep: Atom,
+    },
+    MissingSource {
+        source: PathBuf,
+    },
+    /// Relative to the application directory, so it starts with `priv/`
+    MissingPrivFile {
+        path: PathBuf,
+    },
+    /// The function of the `-on_load` attribute isn't defined
+    MissingOnLoad {
+        module: Atom,
+        function: Atom,
+    },
+    /// A call to a NIF stub, a function calling `erlang:nif_error`, of an
+    /// application shipping no shared library in `priv/`
+    NifWithoutLibrary {
+        module: Atom,
+        function: Atom,
+        arity: u32,
+    },
+    /// Exported, but not called by any loaded module
+    UnusedExport(Atom, Atom, u32),
+    /// Local function not reachable from the exported ones
+    UnusedLocal(Atom, Atom, u32),
+    /// Not called by any other loaded module, nor an application callback
+    /// or entry point
+    DeadModule(Atom),
+    /// A module of `app` missing from the `modules` of its `.app` file
+    UnlistedModule {
+        app: Atom,
+        module: Atom,
+    },
+    /// A module in the `modules` of the `.app` file without a beam file
+    MissingBeam {
+        app: Atom,
+        module: Atom,
+    },
+    /// The `mod` callback of the `.app` file of `app` isn't loaded, or is
+    /// missing some of the `application` callbacks
+    AppCallback {
+        app: Atom,
+        callback: Atom,
+        /// `None` when the module isn't loaded at all
+        missing: Option<Vec<(&'static str, u32)>>,
+    },
+    /// A module of a project application with the name of a module of an
+    /// OTP application, loaded or known to ship with OTP
+    OtpModuleClash {
+        module: Atom,
+        otp_app: String,
+    },
+    /// A module of `app` that another application also has. It either
+    /// shadows the one of `other`, coming first on the lib paths, or is
+    /// shadowed by it.
+    DuplicateModule {
+        module: Atom,
+        app: Atom,
+        other: Atom,
+        shadows: bool,
+    },
+    /// A module of the application that's also on another lib path, as a
+    /// different build of it: a stale or conflicting artifact. `loaded` is
+    /// the beam file that comes first on the lib paths, `other` the one it
+    /// shadows.
+    DifferentBuild {
+        module: Atom,
+        loaded: PathBuf,
+        other: PathBuf,
+    },
+    /// A process name in the `registered` list of `app` that `other` has
+    /// too, so they can't run on the same node
+    RegisteredNameClash {
+        app: Atom,
+        name: Atom,
+        other: Atom,
+    },
+    /// Applications depending on each other through their `.app` files,
+    /// sorted by name, with the dependencies between them that make
+    /// starting them in order impossible
+    AppCycle {
+        apps: Vec<Atom>,
+        edges: Vec<(Atom, Atom)>,
+    },
+    /// Modules of one application calling each other, directly or through
+    /// other modules of the cycle, sorted by name
+    ModuleCycle {
+        modules: Vec<Atom>,
+    },
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Expected in some setups, like an optional dependency that isn't
+    /// installed
+    Info,
+    Warning,
+    Error,
 }
 
 impl AnalysisResult {
+    /// Stable identifier of the check that produced the result
+    pub fn check_name(&self) -> &'static str {
+        match self {
+            AnalysisResult::MissingModule(_)
+            | AnalysisResult::MissingOptionalModule { .. }
+            | AnalysisResult::CheckedMissingModule { .. } => "undefined_module",
+            AnalysisResult::MissingFunction(..)
+            | AnalysisResult::ArityMismatch { .. }
+            | AnalysisResult::MacroCall { .. }
+            | AnalysisResult::UnexportedFunction { .. } => "undefined_function",
+            AnalysisResult::UndefinedDynamicCall { .. } => "undefined_dynamic_call",
+            AnalysisResult::UndefinedCapture { .. } => "undefined_capture",
+            AnalysisResult::UndefinedBehaviour { .. } => "undefined_behaviour",
+            AnalysisResult::UndefinedParseTransform { .. } => "undefined_parse_transform",
+            AnalysisResult::DeprecatedCall { .. } => "deprecated_call",
+            AnalysisResult::TestCodeCall { .. } => "test_code_call",
+            AnalysisResult::ObsoleteCall { .. } => "otp_upgrade",
+            AnalysisResult::ForbiddenCall { .. } => "forbidden_call",
+            AnalysisResult::RiskyCall { .. } => "risky_call",
+            AnalysisResult::LayerViolation { .. } => "layer_violation",
+            AnalysisResult::GroupBoundary { .. } => "group_boundary",
+            AnalysisResult::InternalModuleCall { .. } => "internal_module_call",
+            AnalysisResult::MissingDependency { .. } => "missing_dependency",
+            AnalysisResult::DependencyNotFound { .. } => "dependency_not_found",
+            AnalysisResult::UnusedDependency { .. } => "unused_dependency",
+            AnalysisResult::RedundantDependency { .. } => "redundant_dependency",
+            AnalysisResult::SelfDependency { .. } => "self_dependency",
+            AnalysisResult::UndeclaredRuntimeDependency { .. } => "undeclared_runtime_dependency",
+            AnalysisResult::UnusedRuntimeDependency { .. } => "unused_runtime_dependency",
+            AnalysisResult::MissingSource { .. } => "missing_source",
+            AnalysisResult::MissingPrivFile { .. } => "missing_priv_file",
+            AnalysisResult::MissingOnLoad { .. } => "missing_on_load",
+            AnalysisResult::NifWithoutLibrary { .. } => "nif_library",
+            AnalysisResult::UnusedExport(..) => "unused_export",
+            AnalysisResult::UnusedLocal(..) => "locals_not_used",
+            AnalysisResult::DeadModule(_) => "dead_module",
+            AnalysisResult::DuplicateModule { .. } => "duplicate_module",
+            AnalysisResult::RegisteredNameClash { .. } => "registered_name_clash",
+            AnalysisResult::DifferentBuild { .. } => "different_build",
+            AnalysisResult::OtpModuleClash { .. } => "otp_module_clash",
+            AnalysisResult::UnlistedModule { .. } => "unlisted_module",
+            AnalysisResult::MissingBeam { .. } => "missing_beam",
+            AnalysisResult::AppCallback { .. } => "app_callback",
+            AnalysisResult::AppCycle { .. } => "app_cycle",
+            AnalysisResult::ModuleCycle { .. } => "module_cycle",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            AnalysisResult::MissingModule(_)
+            | AnalysisResult::MissingFunction(..)
+            | AnalysisResult::ArityMismatch { .. }
+            | AnalysisResult::MacroCall { .. }
+            | AnalysisResult::UnexportedFunction { .. } => Severity::Error,
+            AnalysisResult::UndefinedBehaviour { .. } => Severity::Warning,
+            AnalysisResult::UndefinedParseTransform { .. } => Severity::Warning,
+            AnalysisResult::DeprecatedCall { .. } => Severity::Warning,
+            AnalysisResult::ObsoleteCall { obsolete, .. } => match obsolete {
+                Obsolete::Deprecated { .. } => Severity::Warning,
+                Obsolete::Removed { .. } => Severity::Error,
+            },
+            AnalysisResult::ForbiddenCall { .. } => Severity::Error,
+            AnalysisResult::RiskyCall { .. } => Severity::Info,
+            AnalysisResult::LayerViolation { .. } => Severity::Error,
+            AnalysisResult::GroupBoundary { .. } => Severity::Error,
+            AnalysisResult::InternalModuleCall { .. } => Severity::Warning,
+            AnalysisResult::MissingDependency { .. } => Severity::Warning,
+            AnalysisResult::MissingOptionalModule { .. }
+            | AnalysisResult::CheckedMissingModule { .. } => Severity::Info,
+            AnalysisResult::DependencyNotFound { optional: true, .. } => Severity::Info,
+            AnalysisResult::DependencyNotFound { .. } => Severity::Warning,
+            AnalysisResult::UnusedDependency { .. } => Severity::Warning,
+            AnalysisResult::RedundantDependency { .. } | AnalysisResult::SelfDependency { .. } => {
+                Severity::Warning
+            }
+            AnalysisResult::UndeclaredRuntimeDependency { .. }
+            | AnalysisResult::UnusedRuntimeDependency { .. } => Severity::Warning,
+            AnalysisResult::MissingSource { .. } => Severity::Warning,
+            AnalysisResult::MissingPrivFile { .. } => Severity::Warning,
+            AnalysisResult::MissingOnLoad { .. } => Severity::Error,
+            AnalysisResult::NifWithoutLibrary { .. } => Severity::Warning,
+            AnalysisResult::UnusedExport(..) => Severity::Warning,
+            AnalysisResult::UnusedLocal(..) => Severity::Warning,
+            AnalysisResult::DeadModule(_) => Severity::Warning,
+            AnalysisResult::DuplicateModule { .. } => Severity::Warning,
+            AnalysisResult::RegisteredNameClash { .. } => Severity::Warning,
+            AnalysisResult::DifferentBuild { .. } => Severity::Warning,
+            AnalysisResult::OtpModuleClash { .. } => Severity::Warning,
+            AnalysisResult::UnlistedModule { .. } => Severity::Warning,
+            AnalysisResult::MissingBeam { .. } => Severity::Warning,
+            AnalysisResult::AppCallback { .. } => Severity::Error,
+            AnalysisResult::TestCodeCall { .. } => Severity::Error,
+            AnalysisResult::UndefinedDynamicCall { .. } => Severity::Warning,
+            AnalysisResult::UndefinedCapture { .. } => Severity::Info,
+            AnalysisResult::AppCycle { .. } => Severity::Error,
+            AnalysisResult::ModuleCycle { .. } => Severity::Warning,
+        }
+    }
+
+    /// What the finding points at: the missing module or function, the
+    /// dependency or the source file
+    pub fn target(&self, interner: &Interner) -> String {
+        match self {
+            AnalysisResult::MissingModule(module)
+            | AnalysisResult::MissingOptionalModule { module, .. }
+            | AnalysisResult::CheckedMissingModule { module, .. }
+            | AnalysisResult::DeadModule(module)
+            | AnalysisResult::UndefinedBehaviour { behaviour: module }
+            | AnalysisResult::UndefinedParseTransform { transform: module }
+            | AnalysisResult::TestCodeCall { module } => {
+                module.resolve(interner).unwrap().to_string()
+            }
+            AnalysisResult::DuplicateModule { other, .. } => {
+                other.resolve(interner).unwrap().to_string()
+            }
+            AnalysisResult::RegisteredNameClash { name, .. } => {
+                name.resolve(interner).unwrap().to_string()
+            }
+            AnalysisResult::OtpModuleClash { otp_app, .. } => otp_app.clone(),
+            AnalysisResult::UnlistedModule { module, .. }
+            | AnalysisResult::MissingBeam { module, .. }
+            | AnalysisResult::AppCallback {
+                callback: module, ..
+            } => module.resolve(interner).unwrap().to_string(),
+            AnalysisResult::MissingFunction(module, fun, arity, _)
+            | AnalysisResult::ArityMismatch {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::MacroCall {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UnexportedFunction {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UnusedExport(module, fun, arity)
+            | AnalysisResult::UnusedLocal(module, fun, arity)
+            | AnalysisResult::NifWithoutLibrary {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UndefinedDynamicCall {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UndefinedCapture {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::DeprecatedCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::ObsoleteCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::ForbiddenCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::RiskyCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::LayerViolation {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::GroupBoundary {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::InternalModuleCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            } => format!(
+                "{}:{}/{}",
+                module.resolve(interner).unwrap(),
+                fun.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::MissingDependency { app_to, .. } => {
+                app_to.resolve(interner).unwrap().to_string()
+            }
+            AnalysisResult::DependencyNotFound { dep, .. }
+            | AnalysisResult::UnusedDependency { dep, .. }
+            | AnalysisResult::RedundantDependency { dep, .. }
+            | AnalysisResult::SelfDependency { app: dep }
+            | AnalysisResult::UndeclaredRuntimeDependency { dep, .. }
+            | AnalysisResult::UnusedRuntimeDependency { dep, .. } => {
+                dep.resolve(interner).unwrap().to_string()
+            }
+            AnalysisResult::MissingSource { source } => source.display().to_string(),
+            AnalysisResult::DifferentBuild { other, .. } => other.display().to_string(),
+            AnalysisResult::MissingPrivFile { path } => path.display().to_string(),
+            AnalysisResult::MissingOnLoad { module, function } => format!(
+                "{}:{}/0",
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap()
+            ),
+            AnalysisResult::AppCycle { apps: atoms, .. }
+            | AnalysisResult::ModuleCycle { modules: atoms } => atoms
+                .iter()
+                .map(|atom| atom.resolve(interner).unwrap())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
     pub fn fmt(&self, interner: &Interner) -> String {
         match self {
             AnalysisResult::MissingModule(module) => {
                 format!("undefined module: {}", module.resolve(interner).unwrap())
             }
-            AnalysisResult::MissingFunction(module, fun, arity) => format!(
-                "undefined function: {}:{}/{}",
+            AnalysisResult::MissingFunction(module, fun, arity, suggestions) => {
+                let mut message = format!(
+                    "undefined function: {}:{}/{}",
+                    module.resolve(interner).unwrap(),
+                    fun.resolve(interner).unwrap(),
+                    arity
+                );
+                if !suggestions.is_empty() {
+                    let suggestions: Vec<_> = suggestions
+                        .iter()
+                        .map(|(fun, arity)| format!("{}/{}", fun.resolve(interner).unwrap(), arity))
+                        .collect();
+                    message.push_str(&format!(" (did you mean: {}?)", suggestions.join(", ")));
+                }
+                message
+            }
+            AnalysisResult::UnexportedFunction {
+                module,
+                function,
+                arity,
+            } => format!(
+                "function not exported: {}:{}/{} exists but is not exported",
                 module.resolve(interner).unwrap(),
-                fun.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::MacroCall {
+                module,
+                function,
+                arity,
+            } => format!(
+                "macro called at runtime: {}:{}/{} is a macro, it has to be required and expanded at compile time",
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::ArityMismatch {
+                module,
+                function,
+                arity,
+                arities,
+            } => {
+                let function = function.resolve(interner).unwrap();
+                let arities: Vec<_> = arities
+                    .iter()
+                    .map(|arity| format!("{}/{}", function, arity))
+                    .collect();
+                format!(
+                    "arity mismatch: {}:{}/{} is not exported, only {}",
+                    module.resolve(interner).unwrap(),
+                    function,
+                    arity,
+                    arities.join(", ")
+                )
+            }
+            AnalysisResult::UndefinedDynamicCall {
+                module,
+                function,
+                arity,
+            } => format!(
+                "undefined function in dynamic call: {}:{}/{}",
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::UndefinedCapture {
+                module,
+                function,
+                arity,
+                module_missing,
+            } => format!(
+                "undefined {} in fun capture: fun {}:{}/{}",
+                if *module_missing { "module" } else { "function" },
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::TestCodeCall { module } => format!(
+                "call into test code: {} is test-only",
+                module.resolve(interner).unwrap()
+            ),
+            AnalysisResult::UndefinedBehaviour { behaviour } => format!(
+                "undefined behaviour: {}",
+                behaviour.resolve(interner).unwrap()
+            ),
+            AnalysisResult::UndefinedParseTransform { transform } => format!(
+                "undefined parse transform: {} is needed to compile the module",
+                transform.resolve(interner).unwrap()
+            ),
+            AnalysisResult::DeprecatedCall { module, function, arity, hint } => {
+                let call = format!(
+                    "{}:{}/{}",
+                    module.resolve(interner).unwrap(),
+                    function.resolve(interner).unwrap(),
+                    arity
+                );
+                match hint {
+                    Some(hint) => format!("deprecated function: {} ({})", call, hint),
+                    None => format!("deprecated function: {}", call),
+                }
+            }
+            AnalysisResult::ObsoleteCall { module, function, arity, obsolete } => {
+                let call = format!(
+                    "{}:{}/{}",
+                    module.resolve(interner).unwrap(),
+                    function.resolve(interner).unwrap(),
+                    arity
+                );
+                match obsolete {
+                    Obsolete::Deprecated { release, hint } => {
+                        format!("deprecated in OTP {}: {} ({})", release, call, hint)
+                    }
+                    Obsolete::Removed { release, hint } => {
+                        format!("removed in OTP {}: {} ({})", release, call, hint)
+                    }
+                }
+            }
+            AnalysisResult::ForbiddenCall { module, function, arity, pattern, reason } => {
+                let call = format!(
+                    "{}:{}/{}",
+                    module.resolve(interner).unwrap(),
+                    function.resolve(interner).unwrap(),
+                    arity
+                );
+                match reason {
+                    Some(reason) => format!("forbidden call: {} ({})", call, reason),
+                    None => format!("forbidden call: {} matches {}", call, pattern),
+                }
+            }
+            AnalysisResult::RiskyCall {
+                module,
+                function,
+                arity,
+                reason,
+            } => format!(
+                "risky call: {}:{}/{} ({})",
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity,
+                reason
+            ),
+            AnalysisResult::LayerViolation { module, function, arity, from_layer, to_layer } => format!(
+                "layer violation: layer {} may not call layer {}, but calls {}:{}/{}",
+                from_layer,
+                to_layer,
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::GroupBoundary {
+                module,
+                function,
+                arity,
+                from_groups,
+                to_groups,
+            } => format!(
+                "group boundary violation: group {} may not call group {}, but calls {}:{}/{}",
+                from_groups.join(", "),
+                to_groups.join(", "),
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
                 arity
             ),
+            AnalysisResult::InternalModuleCall {
+                module,
+                function,
+                arity,
+                owner,
+                hidden_function,
+            } => format!(
+                "call into internal {}: {}:{}/{} is private to {}",
+                if *hidden_function {
+                    "function"
+                } else {
+                    "module"
+                },
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity,
+                owner.resolve(interner).unwrap()
+            ),
             AnalysisResult::MissingDependency { module, app_from, app_to } => format!(
                 "missing dependency between applications: application {} uses module {} from {} without depending on it",
                 app_from.resolve(interner).unwrap(),
                 module.resolve(interner).unwrap(),
                 app_to.resolve(interner).unwrap()
             ),
+            AnalysisResult::MissingOptionalModule { module, dep } => format!(
+                "optional dependency not present: module {} of {} is not loaded",
+                module.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap()
+            ),
+            AnalysisResult::CheckedMissingModule {
+                module,
+                check: (check_module, check_fun, check_arity),
+            } => format!(
+                "undefined module: {}, called only after checking it with {}:{}/{}",
+                module.resolve(interner).unwrap(),
+                check_module.resolve(interner).unwrap(),
+                check_fun.resolve(interner).unwrap(),
+                check_arity
+            ),
+            AnalysisResult::DependencyNotFound {
+                app,
+                dep,
+                optional: true,
+                ..
+            } => format!(
+                "optional dependency not present: application {} optionally depends on {}, which is not on any lib path",
+                app.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap()
+            ),
+            AnalysisResult::DependencyNotFound {
+                app,
+                dep,
+                otp: true,
+                ..
+            } => format!(
+                "dependency not found on path: application {} depends on {}, an OTP application missing from the lib paths",
+                app.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap()
+            ),
+            AnalysisResult::DependencyNotFound {
+                app,
+                dep,
+                otp: false,
+                ..
+            } => format!(
+                "dependency not found on path: application {} depends on {}, which is not on any lib path and not an OTP application",
+                app.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap()
+            ),
+            AnalysisResult::RedundantDependency { app, dep, via } => format!(
+                "redundant dependency: application {} depends on {}, which none of its modules call and {} already depends on",
+                app.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap(),
+                via.resolve(interner).unwrap()
+            ),
+            AnalysisResult::SelfDependency { app } => format!(
+                "self dependency: application {} lists itself in its applications",
+                app.resolve(interner).unwrap()
+            ),
+            AnalysisResult::UndeclaredRuntimeDependency { app, dep } => format!(
+                "undeclared runtime dependency: application {} calls {}, which is missing from its runtime_dependencies",
+                app.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap()
+            ),
+            AnalysisResult::UnusedRuntimeDependency { app, dep } => format!(
+                "unused runtime dependency: application {} lists {} in its runtime_dependencies, but none of its modules call it",
+                app.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap()
+            ),
+            AnalysisResult::UnusedDependency { app, dep } => format!(
+                "unused dependency: application {} depends on {}, but none of its modules call it",
+                app.resolve(interner).unwrap(),
+                dep.resolve(interner).unwrap()
+            ),
+            AnalysisResult::MissingSource { source } => format!(
+                "source file not found: compiled from {}, which does not exist",
+                source.display()
+            ),
+            AnalysisResult::MissingPrivFile { path } => format!(
+                "priv file not found: {} is executed, but the application doesn't ship it",
+                path.display()
+            ),
+            AnalysisResult::MissingOnLoad { module, function } => format!(
+                "undefined on_load function: {}:{}/0 is not defined",
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap()
+            ),
+            AnalysisResult::NifWithoutLibrary {
+                module,
+                function,
+                arity,
+            } => format!(
+                "NIF without library: {}:{}/{} is a NIF stub, but the application ships no shared library in priv",
+                module.resolve(interner).unwrap(),
+                function.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::UnusedExport(module, fun, arity) => format!(
+                "unused export: {}:{}/{} is not called by any loaded module",
+                module.resolve(interner).unwrap(),
+                fun.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::UnusedLocal(module, fun, arity) => format!(
+                "unused local function: {}:{}/{} is not called from any exported function",
+                module.resolve(interner).unwrap(),
+                fun.resolve(interner).unwrap(),
+                arity
+            ),
+            AnalysisResult::DeadModule(module) => format!(
+                "dead module: {} is not called by any other loaded module, nor an application callback or entry point",
+                module.resolve(interner).unwrap()
+            ),
+            AnalysisResult::DuplicateModule { module, app, other, shadows } => format!(
+                "duplicate module: {} of application {} {} the one of {}",
+                module.resolve(interner).unwrap(),
+                app.resolve(interner).unwrap(),
+                if *shadows { "shadows" } else { "is shadowed by" },
+                other.resolve(interner).unwrap()
+            ),
+            AnalysisResult::DifferentBuild {
+                module,
+                loaded,
+                other,
+            } => format!(
+                "different build: {} is loaded from {}, which differs from the one it shadows in {}",
+                module.resolve(interner).unwrap(),
+                loaded.display(),
+                other.display()
+            ),
+            AnalysisResult::RegisteredNameClash { app, name, other } => format!(
+                "registered name clash: applications {} and {} both register {}",
+                app.resolve(interner).unwrap(),
+                other.resolve(interner).unwrap(),
+                name.resolve(interner).unwrap()
+            ),
+            AnalysisResult::UnlistedModule { app, module } => format!(
+                "module not listed in .app file: {} of application {} is missing from its modules",
+                module.resolve(interner).unwrap(),
+                app.resolve(interner).unwrap()
+            ),
+            AnalysisResult::MissingBeam { app, module } => format!(
+                "beam file not found: {} is listed in the modules of application {}, but not in its ebin",
+                module.resolve(interner).unwrap(),
+                app.resolve(interner).unwrap()
+            ),
+            AnalysisResult::AppCallback {
+                app,
+                callback,
+                missing: None,
+            } => format!(
+                "undefined application callback: {} of application {}",
+                callback.resolve(interner).unwrap(),
+                app.resolve(interner).unwrap()
+            ),
+            AnalysisResult::AppCallback {
+                app,
+                callback,
+                missing: Some(missing),
+            } => {
+                let missing: Vec<_> = missing
+                    .iter()
+                    .map(|(fun, arity)| format!("{}/{}", fun, arity))
+                    .collect();
+                format!(
+                    "invalid application callback: {} of application {} doesn't export {}",
+                    callback.resolve(interner).unwrap(),
+                    app.resolve(interner).unwrap(),
+                    missing.join(", ")
+                )
+            }
+            AnalysisResult::OtpModuleClash { module, otp_app } => format!(
+                "module name clash with OTP: {} is also a module of {}, which one is loaded depends on the code path",
+                module.resolve(interner).unwrap(),
+                otp_app
+            ),
+            AnalysisResult::AppCycle { apps, edges } => {
+                let names: Vec<_> = apps.iter().map(|app| app.resolve(interner).unwrap()).collect();
+                let edges: Vec<_> = edges
+                    .iter()
+                    .map(|(from, to)| {
+                        format!(
+                            "{} -> {}",
+                            from.resolve(interner).unwrap(),
+                            to.resolve(interner).unwrap()
+                        )
+                    })
+                    .collect();
+                format!(
+                    "dependency cycle between applications: {} can't be started in any order, because of {}",
+                    names.join(", "),
+                    edges.join(", ")
+                )
+            }
+            AnalysisResult::ModuleCycle { modules } => {
+                let names: Vec<_> = modules
+                    .iter()
+                    .map(|module| module.resolve(interner).unwrap())
+                    .collect();
+                format!(
+                    "module cycle: {} modules depend on each other: {}",
+                    names.len(),
+                    names.join(", ")
+                )
+            }
         }
     }
 }
 
 impl Analyzer {
-    pub fn new(modules: Modules, app_modules: AppModules, app_deps: AppDeps) -> Analyzer {
-        let modules_rev = app_modules
+    pub fn new(workspace: Workspace) -> Analyzer {
+        let _span = tracing::info_span!("index").entered();
+        let Workspace {
+            interner,
+            modules,
+            app_modules,
+            app_deps,
+            declared_deps,
+            included_apps,
+            optional_deps,
+            runtime_deps,
+            registered,
+            app_paths,
+            app_versions,
+            app_callbacks,
+            shadowed,
+            shadowed_builds,
+            listed_modules,
+            test_apps,
+            excluded,
+        } = workspace;
+
+        let mut modules_rev: FxHashMap<_, _> = app_modules
             .iter()
             .flat_map(|(&app, modules)| modules.iter().map(move |&module| (module, app)))
             .collect();
+        // Modules in more than one application belong to the one whose
+        // module was loaded
+        for module in shadowed.keys() {
+            let path = &modules[module].path;
+            if let Some(app) = app_of_path(&app_paths, path) {
+                modules_rev.insert(*module, app);
+            }
+        }
+
+        let mut callers = FxHashMap::<_, Vec<_>>::default();
+        let mut importers = FxHashMap::<_, Vec<_>>::default();
+        for (&caller, module) in &modules {
+            let calls = module.imports.iter().flat_map(|(&module, functions)| {
+                functions
+                    .iter()
+                    .map(move |&(fun, arity)| (module, fun, arity))
+            });
+            // Dynamic calls count as calls too, so what they reach isn't
+            // unused or dead
+            let dynamic_calls = module
+                .dynamic_calls
+                .iter()
+                .map(|call| (call.module, call.function, call.arity));
+            for (module, fun, arity) in calls.chain(dynamic_calls) {
+                let module_importers = importers.entry(module).or_default();
+                if module != caller && module_importers.last() != Some(&caller) {
+                    module_importers.push(caller);
+                }
+                let fun_callers = callers.entry((module, fun, arity)).or_default();
+                if fun_callers.last() != Some(&caller) {
+                    fun_callers.push(caller);
+                }
+            }
+        }
+
+        let mut exports_by_name = FxHashMap::<_, Vec<_>>::default();
+        for (&module, Module { exports, .. }) in &modules {
+            for &(fun, arity) in exports {
+                exports_by_name
+                    .entry(fun)
+                    .or_default()
+                    .push((module, arity));
+            }
+        }
+
+        // A cycle is a strongly connected component of more than one
+        // application, or one depending on itself
+        let mut app_cycles: Vec<Vec<Atom>> = algo::tarjan_scc(&app_deps)
+            .into_iter()
+            .filter(|apps| apps.len() > 1 || app_deps.contains_edge(apps[0], apps[0]))
+            .map(|mut apps| {
+                apps.sort_by_key(|app| app.resolve(&interner));
+                apps
+            })
+            .collect();
+        app_cycles.sort_by_key(|apps| apps[0].resolve(&interner));
 
         Analyzer {
+            interner,
             modules,
             modules_rev,
+            exports_by_name,
+            callers,
+            importers,
+            app_cycles,
             app_modules,
             app_deps,
+            declared_deps,
+            included_apps,
+            optional_deps,
+            runtime_deps,
+            registered,
+            app_paths,
+            app_versions,
+            app_callbacks,
+            shadowed,
+            shadowed_builds,
+            listed_modules,
+            progress: None,
+            source_roots: vec![],
+            sample: None,
+            only_modules: None,
+            checks: None,
+            severities: FxHashMap::default(),
+            excluded,
+            excludes: Excludes::default(),
+            entry_points: vec![],
+            min_cycle_size: MIN_CYCLE_SIZE,
+            start_only_deps: vec![],
+            otp_version: None,
+            forbidden_calls: vec![],
+            risky_calls: rules::RISKY_CALLS
+                .iter()
+                .map(|(pattern, reason)| (pattern.parse().unwrap(), reason.to_string()))
+                .collect(),
+            layers: vec![],
+            groups: vec![],
+            app_groups: FxHashMap::default(),
+            internal_modules: vec![],
+            app_layers: FxHashMap::default(),
+            test_code: TestCode::default(),
+            test_apps,
+            reachability: false,
+            reached: OnceLock::new(),
         }
     }
 
-    pub fn run(&self, apps: &[Atom]) -> Vec<(Atom, AnalysisResult)> {
-        apps.par_iter()
-            .flat_map(|app| self.app_modules[app].par_iter())
-            .flat_map(|&module| {
-                let (imports, _) = self.modules.get(&module).unwrap();
-                imports.par_iter().flat_map(move |(&imported, functions)| {
-                    let mut results = vec![];
-                    results.append(&mut self.check_missing_module(module, imported, functions));
-                    results.append(&mut self.check_missing_dep(module, imported));
-                    results
+    /// Report the exports and modules the roots don't reach, instead of
+    /// the ones nothing calls. Roots are the entry points, application
+    /// callbacks and the callbacks of behaviours. Calls are followed
+    /// function by function, or for the whole module when its code
+    /// couldn't be disassembled.
+    pub fn with_reachability(self, reachability: bool) -> Analyzer {
+        Analyzer {
+            reachability,
+            ..self
+        }
+    }
+
+    fn reached(&self) -> &Reached {
+        self.reached.get_or_init(|| {
+            let _span = tracing::info_span!("reachability").entered();
+            let mut queue: Vec<Mfa> = self
+                .modules
+                .iter()
+                .flat_map(|(&module, Module { exports, .. })| {
+                    exports
+                        .iter()
+                        .filter(move |&&(fun, arity)| self.is_root(module, fun, arity))
+                        .map(move |&(fun, arity)| (module, fun, arity))
                 })
-            })
-            .collect()
+                .collect();
+
+            let mut reached = Reached::default();
+            while let Some(mfa) = queue.pop() {
+                if !reached.functions.insert(mfa) {
+                    continue;
+                }
+                let (module, fun, arity) = mfa;
+                let loaded = match self.modules.get(&module) {
+                    Some(loaded) => loaded,
+                    None => continue,
+                };
+                if reached.modules.insert(module) {
+                    queue.extend(loaded.on_load.map(|fun| (module, fun, 0)));
+                    queue.extend(
+                        loaded
+                            .dynamic_calls
+                            .iter()
+                            .map(|call| (call.module, call.function, call.arity)),
+                    );
+                }
+                match &loaded.function_calls {
+                    Some(function_calls) => {
+                        queue.extend(function_calls.get(&(fun, arity)).into_iter().flatten())
+                    }
+                    None => queue.extend(loaded.imports.iter().flat_map(|(&module, functions)| {
+                        functions
+                            .iter()
+                            .map(move |&(fun, arity)| (module, fun, arity))
+                    })),
+                }
+            }
+            reached
+        })
     }
 
-    fn check_missing_module(
-        &self,
-        module: Atom,
-        imported: Atom,
-        functions: &[(Atom, u32)],
-    ) -> Vec<(Atom, AnalysisResult)> {
-        match self.modules.get(&imported) {
-            Some((_, exports)) => functions
+    /// Exports called from outside of the loaded code
+    fn is_root(&self, module: Atom, fun: Atom, arity: u32) -> bool {
+        let name = self.name(module);
+        let fun_name = self.name(fun);
+        let callback = |behaviour: &str| {
+            otp::behaviour_callbacks(behaviour)
+                .is_none_or(|callbacks| callbacks.contains(&(fun_name, arity)))
+        };
+        self.entry_points
+            .iter()
+            .any(|entry| entry.matches(name, fun_name, arity))
+            || self.dispatched(module, fun, arity)
+            || (self.owner(module).and_then(|app| self.app_callback(app)) == Some(module)
+                && callback("application"))
+            || self.modules[&module]
+                .behaviours
                 .iter()
-                .filter(|fa| !exports.contains(fa))
-                .map(|(f, a)| (module, AnalysisResult::MissingFunction(imported, *f, *a)))
-                .collect(),
-            None => vec![(module, AnalysisResult::MissingModule(imported))],
+                .any(|&behaviour| callback(self.name(behaviour)))
+    }
+
+    /// Report production code calling the test code
+    pub fn with_test_code(self, test_code: TestCode) -> Analyzer {
+        Analyzer { test_code, ..self }
+    }
+
+    /// Whether the module is test code, by name or by its application
+    pub fn is_test_module(&self, module: Atom) -> bool {
+        let app = self.owner(module);
+        app.is_some_and(|app| self.test_apps.contains(&app))
+            || self
+                .test_code
+                .contains(app.map(|app| self.name(app)), self.name(module))
+    }
+
+    /// Report calls between applications that break the layering. An
+    /// application is in the first layer it matches.
+    pub fn with_layers(self, layers: Vec<Layer>) -> Analyzer {
+        let app_layers = self
+            .app_modules
+            .keys()
+            .filter_map(|&app| {
+                let name = self.name(app);
+                let layer = layers.iter().position(|layer| layer.contains(name))?;
+                Some((app, layer))
+            })
+            .collect();
+        Analyzer {
+            layers,
+            app_layers,
+            ..self
         }
     }
 
-    fn check_missing_dep(&self, module: Atom, imported: Atom) -> Vec<(Atom, AnalysisResult)> {
-        let app_from = self.modules_rev[&module];
+    /// Report calls between applications crossing the boundaries of their
+    /// groups
+    pub fn with_groups(self, groups: Vec<Group>) -> Analyzer {
+        let app_groups = self
+            .app_modules
+            .keys()
+            .filter_map(|&app| {
+                let name = self.name(app);
+                let in_groups: Vec<_> = (0..groups.len())
+                    .filter(|&group| groups[group].contains(name))
+                    .collect();
+                (!in_groups.is_empty()).then_some((app, in_groups))
+            })
+            .collect();
+        Analyzer {
+            groups,
+            app_groups,
+            ..self
+        }
+    }
 
-        if let Some(&app_to) = self.modules_rev.get(&imported) {
-            if algo::has_path_connecting(&self.app_deps, app_from, app_to, None) {
-                vec![]
-            } else {
-                vec![(module, AnalysisResult::MissingDependency { module: imported, app_from, app_to })]
+    /// Modules only their own application may call, besides the ones
+    /// hidden from the docs
+    pub fn with_internal_modules(self, internal_modules: Vec<Pattern>) -> Analyzer {
+        Analyzer {
+            internal_modules,
+            ..self
+        }
+    }
+
+    /// Report calls matching these besides the built-in risky calls
+    pub fn with_risky_calls(mut self, risky_calls: Vec<RiskyCalls>) -> Analyzer {
+        for rule in risky_calls {
+            for pattern in rule.calls {
+                self.risky_calls.push((pattern, rule.reason.clone()));
             }
-        } else {
-            vec![]
+        }
+        self
+    }
+
+    /// Report calls matching the rules
+    pub fn with_forbidden_calls(self, forbidden_calls: Vec<ForbiddenCalls>) -> Analyzer {
+        Analyzer {
+            forbidden_calls,
+            ..self
         }
     }
+
+    /// Report calls to OTP functions deprecated or removed by the given
+    /// OTP release, to prepare an upgrade
+    pub fn with_otp_version(self, version: u32) -> Analyzer {
+        Analyzer {
+            otp_version: Some(version),
+            ..self
+        }
+    }
+
+    /// Dependencies declared only to be started before the application,
+    /// like `sasl`, which are never reported as unused
+    pub fn with_start_only_deps(self, start_only_deps: Vec<Pattern>) -> Analyzer {
+        Analyzer {
+            start_only_deps,
+            ..self
+        }
+    }
+
+    /// Report only module cycles of at least `size` modules, cycles are
+    /// never smaller than two
+    pub fn with_min_cycle_size(self, size: usize) -> Analyzer {
+        Analyzer {
+            min_cycle_size: size.max(MIN_CYCLE_SIZE),
+            ..self
+        }
+    }
+
+    /// Functions called from outside of the loaded code, which aren't
+    /// reported as unused exports, and whose modules aren't dead
+    pub fn with_entry_points(self, entry_points: Vec<FunctionPattern>) -> Analyzer {
+        Analyzer {
+            entry_points,
+            ..self
+        }
+    }
+
+    /// Run only the named checks instead of all of them
+    pub fn with_checks(self, checks: Vec<String>) -> Analyzer {
+        Analyzer {
+            checks: Some(checks.into_iter().collect()),
+            ..self
+        }
+    }
+
+    /// Don't report references to the matching applications and modules,
+    /// in addition to the ones the loader skipped
+    pub fn with_excludes(self, excludes: Excludes) -> Analyzer {
+        Analyzer { excludes, ..self }
+    }
+
+    /// Override the default severity of checks, keyed by check name
+    pub fn with_severities(self, severities: FxHashMap<String, Severity>) -> Analyzer {
+        Analyzer { severities, ..self }
+    }
+
+    pub fn severity(&self, result: &AnalysisResult) -> Severity {
+        self.severities
+            .get(result.check_name())
+            .copied()
+            .unwrap_or_else(|| result.severity())
+    }
+
+    /// Report modules compiled from sources under `roots` which no longer
+    /// exist. Modules built from sources elsewhere, like dependencies or OTP
+    /// itself, are not checked.
+    pub fn with_source_roots(self, roots: Vec<SourceRoot>) -> Analyzer {
+        Analyzer {
+            source_roots: roots,
+            ..self
+        }
+    }
+
+    /// Report progress of the analysis through `callback`
+    pub fn with_progress(self, callback: Callback) -> Analyzer {
+        Analyzer {
+            progress: Some(callback),
+            ..self
+        }
+    }
+
+    pub fn run(&self, apps: &[Atom]) -> Vec<(Atom, AnalysisResult)> {
+        let _span = tracing::info_span!("analysis", apps = apps.len()).entered();
+        let results: Vec<_> = apps
+            .par_iter()
+            .flat_map(|&app| {
+                let name = app.resolve(&self.interner).unwrap();
+                let _span = tracing::debug_span!("check_app", app = name).entered();
+                let results = self.run_app(app);
+                self.emit(Event::AppChecked {
+                    name: name.to_string(),
+                    findings: results.len(),
+                });
+                results
+            })
+            .chain(self.check_app_cycles(apps))
+            .filter(|(_, result)| self.enabled(result.check_name()))
+            .filter(|(_, result)| !self.points_to_excluded(result))
+            .filter(|(subject, result)| !self.ignored_in_source(*subject, result))
+            .collect();
+
+        self.emit(Event::AnalysisFinished {
+            findings: results.len(),
+        });
+        results
+    }
+
+    /// Restrict module level checks to a sample of the modules
+    pub fn with_sample(self, sample: Sample) -> Analyzer {
+        Analyzer {
+            sample: Some(sample),
+            ..self
+        }
+    }
+
+    /// Check only the named modules, application level checks are skipped
+    pub fn with_modules(self, modules: FxHashSet<Atom>) -> Analyzer {
+        Analyzer {
+            only_modules: Some(modules),
+            ..self
+        }
+    }
+
+    fn run_app(&self, app: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let modules: Vec<Atom> = self
+            .app_modules
+            .get(&app)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|module| {
+                self.sample
+                    .is_none_or(|sample| sample.contains(module.resolve(&self.interner).unwrap()))
+            })
+            .filter(|module| {
+                self.only_modules
+                    .as_ref()
+                    .is_none_or(|only| only.contains(module))
+            })
+            .collect();
+
+        let module_results = modules.par_iter().flat_map(|&module| {
+            let imports = &self.modules.get(&module).unwrap().imports;
+            imports.par_iter().flat_map(move |(&imported, functions)| {
+                let mut results = vec![];
+                results.append(&mut self.check_missing_module(module, imported, functions));
+                results.append(&mut self.check_missing_dep(module, imported));
+                results.append(&mut self.check_deprecated(module, imported, functions));
+                results.append(&mut self.check_obsolete(module, imported, functions));
+                results.append(&mut self.check_forbidden(module, imported, functions));
+                results.append(&mut self.check_layers(module, imported, functions));
+                results.append(&mut self.check_groups(module, imported, functions));
+                results.extend(self.check_test_code(module, imported));
+                if self.enabled("nif_library") {
+                    results.append(&mut self.check_nif_library(module, imported, functions));
+                }
+                if self.enabled("risky_call") {
+                    results.append(&mut self.check_risky(module, imported, functions));
+                }
+                if self.enabled("internal_module_call") {
+                    results.append(&mut self.check_internal(module, imported, functions));
+                }
+                results
+            })
+        });
+
+        let duplicate_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_duplicate(app, module));
+        let build_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_builds(app, module));
+        let clash_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_otp_clash(app, module));
+        let behaviour_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_behaviours(module));
+        let transform_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_parse_transforms(module));
+        let dynamic_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_dynamic_calls(module));
+        let source_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_source(module));
+        let priv_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_priv_files(app, module));
+        let on_load_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_on_load(module));
+        let unused_results = modules
+            .par_iter()
+            .filter(|_| self.enabled("unused_export"))
+            .flat_map(|&module| self.check_unused_exports(module));
+        let locals_results = modules
+            .par_iter()
+            .flat_map(|&module| self.check_unused_locals(module));
+        let dead_results = modules
+            .par_iter()
+            .filter(|_| self.enabled("dead_module"))
+            .flat_map(|&module| self.check_dead_module(module));
+
+        let app_results = match self.only_modules {
+            Some(_) => vec![],
+            None => {
+                let mut results = self.check_deps_found(app);
+                results.append(&mut self.check_deps_used(app));
+                results.append(&mut self.check_runtime_deps(app));
+                results.append(&mut self.check_listed_modules(app));
+                results.append(&mut self.check_registered(app));
+                results.extend(self.check_app_callback(app));
+                if self.enabled("module_cycle") {
+                    results.append(&mut self.check_module_cycles(app));
+                }
+                results
+            }
+        };
+
+        app_results
+            .into_par_iter()
+            .chain(duplicate_results)
+            .chain(build_results)
+            .chain(clash_results)
+            .chain(behaviour_results)
+            .chain(transform_results)
+            .chain(dynamic_results)
+            .chain(source_results)
+            .chain(priv_results)
+            .chain(on_load_results)
+            .chain(unused_results)
+            .chain(locals_results)
+            .chain(dead_results)
+            .chain(module_results)
+            .collect()
+    }
+
+    /// Whether the check runs, either named in `with_checks` or on by default
+    pub fn enabled(&self, check: &str) -> bool {
+        match &self.checks {
+            Some(checks) => checks.contains(check),
+            None => !OPT_IN_CHECKS.contains(&check),
+        }
+    }
+
+    /// Modules calling the function
+    pub fn callers(&self, module: Atom, fun: Atom, arity: u32) -> &[Atom] {
+        self.callers
+            .get(&(module, fun, arity))
+            .map_or(&[][..], |callers| callers.as_slice())
+    }
+
+    fn check_unused_exports(&self, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let name = self.name(module);
+        let mut exports: Vec<_> = self.modules[&module]
+            .exports
+            .iter()
+            .copied()
+            .filter(|&(fun, arity)| {
+                let fun_name = self.name(fun);
+                // Elixir macros are expanded by the compiler, never called
+                if fun_name == "module_info" || fun_name.starts_with("MACRO-") {
+                    return false;
+                }
+                if self.reachability {
+                    return !self.reached().functions.contains(&(module, fun, arity));
+                }
+                self.callers(module, fun, arity).is_empty()
+                    && !self.dispatched(module, fun, arity)
+                    && !self
+                        .entry_points
+                        .iter()
+                        .any(|entry| entry.matches(name, fun_name, arity))
+            })
+            .collect();
+        exports.sort_by_key(|&(fun, arity)| (self.name(fun), arity));
+        exports
+            .into_iter()
+            .map(|(fun, arity)| (module, AnalysisResult::UnusedExport(module, fun, arity)))
+            .collect()
+    }
+
+    fn check_duplicate(&self, app: Atom, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let shadowed = match self.shadowed.get(&module) {
+            Some(shadowed) => shadowed,
+            None => return vec![],
+        };
+        let owner = self.owner(module);
+        let others: Vec<_> = if owner == Some(app) {
+            shadowed
+                .iter()
+                .filter_map(|path| app_of_path(&self.app_paths, path))
+                .collect()
+        } else {
+            owner.into_iter().collect()
+        };
+        others
+            .into_iter()
+            .filter(|&other| other != app)
+            .map(|other| {
+                let result = AnalysisResult::DuplicateModule {
+                    module,
+                    app,
+                    other,
+                    shadows: owner == Some(app),
+                };
+                (module, result)
+            })
+            .collect()
+    }
+
+    /// Names in `registered` of the `.app` file of `app` that other loaded
+    /// applications register too
+    fn check_registered(&self, app: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let mut results = vec![];
+        for &name in self.registered.get(&app).into_iter().flatten() {
+            let mut others: Vec<_> = self
+                .registered
+                .iter()
+                .filter(|&(&other, names)| other != app && names.contains(&name))
+                .map(|(&other, _)| other)
+                .collect();
+            others.sort_by_key(|&other| self.name(other));
+            results.extend(others.into_iter().map(|other| {
+                let result = AnalysisResult::RegisteredNameClash { app, name, other };
+                (app, result)
+            }));
+        }
+        results
+    }
+
+    /// Beam files of the module that it shadows and that aren't of another
+    /// loaded application, like the same application on two lib paths, but
+    /// were built from different code
+    fn check_builds(&self, app: Atom, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let loaded = &self.modules[&module];
+        if self.owner(module) != Some(app) {
+            return vec![];
+        }
+        self.shadowed
+            .get(&module)
+            .into_iter()
+            .flatten()
+            .filter(|path| app_of_path(&self.app_paths, path).is_none_or(|other| other == app))
+            .filter(|path| self.shadowed_builds.get(*path) != Some(&loaded.build))
+            .map(|path| {
+                let result = AnalysisResult::DifferentBuild {
+                    module,
+                    loaded: loaded.path.clone(),
+                    other: path.clone(),
+                };
+                (module, result)
+            })
+            .collect()
+    }
+
+    fn check_otp_clash(&self, app: Atom, module: Atom) -> Option<(Atom, AnalysisResult)> {
+        if otp::is_otp_app(self.name(app)) {
+            return None;
+        }
+        let shadowed = self.shadowed.get(&module).into_iter().flatten();
+        let loaded = self
+            .owner(module)
+            .into_iter()
+            .chain(shadowed.filter_map(|path| app_of_path(&self.app_paths, path)))
+            .map(|other| self.name(other))
+            .find(|&other| otp::is_otp_app(other));
+        let otp_app = loaded.or_else(|| otp::module_app(self.name(module)))?;
+        let result = AnalysisResult::OtpModuleClash {
+            module,
+            otp_app: otp_app.to_string(),
+        };
+        Some((module, result))
+    }
+
+    fn check_behaviours(&self, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        self.modules[&module]
+            .behaviours
+            .iter()
+            .filter(|behaviour| {
+                !self.modules.contains_key(behaviour)
+                    && !otp::is_otp_behaviour(self.name(**behaviour))
+            })
+            .map(|&behaviour| (module, AnalysisResult::UndefinedBehaviour { behaviour }))
+            .collect()
+    }
+
+    fn check_deprecated(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        // Calls within a deprecated module are fine
+        let deprecated = match self.modules.get(&imported) {
+            Some(imported_module) if imported != module => &imported_module.deprecated,
+            _ => return vec![],
+        };
+        functions
+            .iter()
+            .filter_map(|&(function, arity)| {
+                let deprecation = deprecated
+                    .iter()
+                    .find(|deprecation| deprecation.matches(function, arity))?;
+                let result = AnalysisResult::DeprecatedCall {
+                    module: imported,
+                    function,
+                    arity,
+                    hint: deprecation.hint.clone(),
+                };
+                Some((module, result))
+            })
+            .collect()
+    }
+
+    fn check_obsolete(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        let target = match self.otp_version {
+            Some(target) => target,
+            None => return vec![],
+        };
+        let name = self.name(imported);
+        functions
+            .iter()
+            .filter_map(|&(function, arity)| {
+                let obsolete = otp::obsolete(name, self.name(function), arity, target)?;
+                let result = AnalysisResult::ObsoleteCall {
+                    module: imported,
+                    function,
+                    arity,
+                    obsolete,
+                };
+                Some((module, result))
+            })
+            .collect()
+    }
+
+    fn check_forbidden(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        let app = self.owner(module).map(|app| self.name(app));
+        let rules: Vec<_> = self
+            .forbidden_calls
+            .iter()
+            .filter(|rule| rule.applies_to(app, self.name(module)))
+            .collect();
+        if rules.is_empty() {
+            return vec![];
+        }
+
+        let name = self.name(imported);
+        functions
+            .iter()
+            .filter_map(|&(function, arity)| {
+                let (rule, pattern) = rules.iter().find_map(|rule| {
+                    let pattern = rule.forbids(name, self.name(function), arity)?;
+                    Some((rule, pattern))
+                })?;
+                let result = AnalysisResult::ForbiddenCall {
+                    module: imported,
+                    function,
+                    arity,
+                    pattern: pattern.to_string(),
+                    reason: rule.reason.clone(),
+                };
+                Some((module, result))
+            })
+            .collect()
+    }
+
+    /// Calls matching a risky call pattern, and calls to `crypto` from
+    /// functions naming a weak algorithm
+    fn check_risky(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        let name = self.name(imported);
+        let crypto_atoms = &self.modules[&module].crypto_atoms;
+        functions
+            .iter()
+            .flat_map(|&(function, arity)| {
+                let matched = self.risky_calls.iter().find_map(|(pattern, reason)| {
+                    pattern
+                        .matches(name, self.name(function), arity)
+                        .then(|| reason.clone())
+                });
+                let weak = crypto_atoms
+                    .get(&(imported, function, arity))
+                    .into_iter()
+                    .flatten()
+                    .map(|&atom| self.name(atom))
+                    .filter(|algorithm| rules::WEAK_ALGORITHMS.contains(algorithm))
+                    .map(|algorithm| format!("{} is a broken algorithm", algorithm));
+                matched.into_iter().chain(weak).map(move |reason| {
+                    let result = AnalysisResult::RiskyCall {
+                        module: imported,
+                        function,
+                        arity,
+                        reason,
+                    };
+                    (module, result)
+                })
+            })
+            .collect()
+    }
+
+    /// Calls into an internal module of another application
+    fn check_internal(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        let owner = match (self.owner(module), self.owner(imported)) {
+            (Some(app), Some(owner)) if app != owner => owner,
+            _ => return vec![],
+        };
+        let internal = self.internal(imported);
+        functions
+            .iter()
+            .filter_map(|&(function, arity)| {
+                let hidden_function = !internal && self.hidden_function(imported, function, arity);
+                if !internal && !hidden_function {
+                    return None;
+                }
+                let result = AnalysisResult::InternalModuleCall {
+                    module: imported,
+                    function,
+                    arity,
+                    owner,
+                    hidden_function,
+                };
+                Some((module, result))
+            })
+            .collect()
+    }
+
+    /// Whether the function is hidden from the docs, with `@doc false` or
+    /// `-doc false`. Elixir's `__name__` functions are hidden too, but meant
+    /// for the code macros generate in other applications.
+    fn hidden_function(&self, module: Atom, function: Atom, arity: u32) -> bool {
+        let name = self.name(function);
+        !(name.starts_with("__") && name.ends_with("__"))
+            && self.modules[&module]
+                .docs
+                .get(&(function, arity))
+                .is_some_and(|doc| doc.hidden)
+    }
+
+    /// Whether the module is private to its application: hidden from the
+    /// docs or matching the internal modules
+    pub fn internal(&self, module: Atom) -> bool {
+        let name = self.name(module);
+        self.modules
+            .get(&module)
+            .is_some_and(|loaded| loaded.hidden)
+            || self
+                .internal_modules
+                .iter()
+                .any(|pattern| pattern.matches(name))
+    }
+
+    fn check_test_code(&self, module: Atom, imported: Atom) -> Option<(Atom, AnalysisResult)> {
+        if self.is_test_module(imported) && !self.is_test_module(module) {
+            Some((module, AnalysisResult::TestCodeCall { module: imported }))
+        } else {
+            None
+        }
+    }
+
+    fn check_layers(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        let layer = |module| {
+            let app = self.owner(module)?;
+            self.app_layers.get(&app).copied()
+        };
+        let (from, to) = match (layer(module), layer(imported)) {
+            (Some(from), Some(to)) if !rules::layer_allows(&self.layers, from, to) => (from, to),
+            _ => return vec![],
+        };
+        functions
+            .iter()
+            .map(|&(function, arity)| {
+                let result = AnalysisResult::LayerViolation {
+                    module: imported,
+                    function,
+                    arity,
+                    from_layer: self.layers[from].name.clone(),
+                    to_layer: self.layers[to].name.clone(),
+                };
+                (module, result)
+            })
+            .collect()
+    }
+
+    fn check_groups(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        let groups = |module| {
+            let app = self.owner(module)?;
+            self.app_groups.get(&app)
+        };
+        let (from, to) = match (groups(module), groups(imported)) {
+            (Some(from), Some(to)) if !rules::groups_allow(&self.groups, from, to) => (from, to),
+            _ => return vec![],
+        };
+        let names = |groups: &[usize]| -> Vec<String> {
+            groups
+                .iter()
+                .map(|&group| self.groups[group].name.clone())
+                .collect()
+        };
+        functions
+            .iter()
+            .map(|&(function, arity)| {
+                let result = AnalysisResult::GroupBoundary {
+                    module: imported,
+                    function,
+                    arity,
+                    from_groups: names(from),
+                    to_groups: names(to),
+                };
+                (module, result)
+            })
+            .collect()
+    }
+
+    /// Other modules calling into `module`
+    pub fn importers(&self, module: Atom) -> &[Atom] {
+        self.importers
+            .get(&module)
+            .map_or(&[][..], |importers| importers.as_slice())
+    }
+
+    /// Local functions the exported ones and the `-on_load` function
+    /// don't reach. Functions the compiler generates, like the ones of
+    /// funs, are reported through the function defining them.
+    fn check_unused_locals(&self, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let Module {
+            exports,
+            on_load,
+            function_calls,
+            ..
+        } = &self.modules[&module];
+        let function_calls = match function_calls {
+            Some(function_calls) => function_calls,
+            None => return vec![],
+        };
+
+        let mut reached: FxHashSet<_> = exports
+            .iter()
+            .copied()
+            .chain(on_load.map(|fun| (fun, 0)))
+            .collect();
+        let mut queue: Vec<_> = reached.iter().copied().collect();
+        while let Some(function) = queue.pop() {
+            let calls = function_calls.get(&function).into_iter().flatten();
+            for &(called_module, fun, arity) in calls {
+                if called_module == module && reached.insert((fun, arity)) {
+                    queue.push((fun, arity));
+                }
+            }
+        }
+
+        let mut unused: Vec<_> = function_calls
+            .keys()
+            .filter(|function| !reached.contains(function))
+            .filter(|(fun, _)| !self.name(*fun).starts_with('-'))
+            .copied()
+            .collect();
+        unused.sort_by_key(|&(fun, arity)| (self.name(fun), arity));
+        unused
+            .into_iter()
+            .map(|(fun, arity)| (module, AnalysisResult::UnusedLocal(module, fun, arity)))
+            .collect()
+    }
+
+    /// Whether the function is part of Elixir protocol dispatch, which
+    /// calls it in ways the imports don't show
+    fn dispatched(&self, module: Atom, fun: Atom, arity: u32) -> bool {
+        if self.modules[&module].protocol {
+            return matches!(self.name(fun), "__protocol__" | "impl_for" | "impl_for!");
+        }
+        let protocol = match self.modules[&module].protocol_impl {
+            Some(protocol) => protocol,
+            None => return false,
+        };
+        self.name(fun) == "__impl__"
+            || self
+                .modules
+                .get(&protocol)
+                .is_none_or(|protocol| protocol.exports.contains(&(fun, arity)))
+    }
+
+    fn check_dead_module(&self, module: Atom) -> Option<(Atom, AnalysisResult)> {
+        if self.reachability {
+            return Some((module, AnalysisResult::DeadModule(module)))
+                .filter(|_| !self.reached().modules.contains(&module));
+        }
+        let name = self.name(module);
+        let rooted = !self.importers(module).is_empty()
+            || self.modules[&module].protocol_impl.is_some()
+            || self.owner(module).and_then(|app| self.app_callback(app)) == Some(module)
+            || self
+                .entry_points
+                .iter()
+                .any(|entry| entry.matches_module(name));
+        if rooted {
+            None
+        } else {
+            Some((module, AnalysisResult::DeadModule(module)))
+        }
+    }
+
+    /// Whether the module the finding is reported for suppresses what it
+    /// points at with an `-ignore_xref` attribute
+    fn ignored_in_source(&self, subject: Atom, result: &AnalysisResult) -> bool {
+        let ignores = match self.modules.get(&subject) {
+            Some(module) if !module.ignore_xref.is_empty() => &module.ignore_xref,
+            _ => return false,
+        };
+        let target = result.target(&self.interner);
+        ignores.iter().any(|ignore| {
+            let module = self.name(ignore.module);
+            match ignore.function {
+                Some((function, arity)) => {
+                    target == format!("{}:{}/{}", module, self.name(function), arity)
+                }
+                None => {
+                    target == module
+                        || target
+                            .strip_prefix(module)
+                            .is_some_and(|rest| rest.starts_with(':'))
+                }
+            }
+        })
+    }
+
+    fn points_to_excluded(&self, result: &AnalysisResult) -> bool {
+        let (target, is_app) = match result {
+            AnalysisResult::MissingModule(module)
+            | AnalysisResult::MissingOptionalModule { module, .. }
+            | AnalysisResult::CheckedMissingModule { module, .. }
+            | AnalysisResult::MissingFunction(module, ..)
+            | AnalysisResult::ArityMismatch { module, .. }
+            | AnalysisResult::MacroCall { module, .. }
+            | AnalysisResult::UnexportedFunction { module, .. }
+            | AnalysisResult::RiskyCall { module, .. }
+            | AnalysisResult::GroupBoundary { module, .. }
+            | AnalysisResult::InternalModuleCall { module, .. }
+            | AnalysisResult::NifWithoutLibrary { module, .. }
+            | AnalysisResult::UndefinedDynamicCall { module, .. }
+            | AnalysisResult::UndefinedCapture { module, .. }
+            | AnalysisResult::UndefinedBehaviour { behaviour: module }
+            | AnalysisResult::UndefinedParseTransform { transform: module } => (*module, false),
+            AnalysisResult::DependencyNotFound { dep, .. }
+            | AnalysisResult::UnusedDependency { dep, .. }
+            | AnalysisResult::RedundantDependency { dep, .. }
+            | AnalysisResult::UndeclaredRuntimeDependency { dep, .. }
+            | AnalysisResult::UnusedRuntimeDependency { dep, .. }
+            | AnalysisResult::RegisteredNameClash { other: dep, .. } => (*dep, true),
+            _ => return false,
+        };
+        let name = target.resolve(&self.interner).unwrap();
+
+        self.excluded.contains(&target)
+            || if is_app {
+                self.excludes.app(name)
+            } else {
+                self.excludes.module(name)
+            }
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(progress) = &self.progress {
+            progress(event)
+        }
+    }
+
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    /// Looks up an atom without interning it, names that never appeared in
+    /// any loaded module are not known
+    pub fn atom(&self, name: &str) -> Option<Atom> {
+        self.interner.get(name).map(Atom)
+    }
+
+    pub fn name(&self, atom: Atom) -> &str {
+        atom.resolve(&self.interner).unwrap()
+    }
+
+    /// The application named `name`, if it was loaded
+    pub fn app(&self, name: &str) -> Option<Atom> {
+        self.atom(name)
+            .filter(|app| self.app_modules.contains_key(app))
+    }
+
+    /// Like `app`, with an error suggesting similar names of loaded
+    /// applications, or listing all of them when there are few
+    pub fn find_app(&self, name: &str) -> Result<Atom> {
+        if let Some(app) = self.app(name) {
+            return Ok(app);
+        }
+
+        let mut apps: Vec<_> = self.app_modules.keys().map(|&app| self.name(app)).collect();
+        apps.sort_unstable();
+        let close = suggest::closest(name, apps.iter().copied());
+        if !close.is_empty() {
+            anyhow::bail!(
+                "unknown application: {} (did you mean: {}?)",
+                name,
+                close.join(", ")
+            );
+        }
+        match apps.len() {
+            0 => anyhow::bail!(
+                "unknown application: {} (no applications were loaded)",
+                name
+            ),
+            1..=20 => anyhow::bail!(
+                "unknown application: {} (loaded applications: {})",
+                name,
+                apps.join(", ")
+            ),
+            count => anyhow::bail!(
+                "unknown application: {} ({} applications loaded, see `xref list apps`)",
+                name,
+                count
+            ),
+        }
+    }
+
+    pub fn app_modules(&self) -> &AppModules {
+        &self.app_modules
+    }
+
+    pub fn app_deps(&self) -> &AppDeps {
+        &self.app_deps
+    }
+
+    /// Applications in the `optional_applications` of the `.app` file of
+    /// `app`
+    pub fn optional_deps(&self, app: Atom) -> &[Atom] {
+        self.optional_deps
+            .get(&app)
+            .map_or(&[][..], |deps| deps.as_slice())
+    }
+
+    /// Applications listed in the `.app` file of `app`
+    pub fn declared_deps(&self, app: Atom) -> &[Atom] {
+        self.declared_deps
+            .get(&app)
+            .map_or(&[][..], |deps| deps.as_slice())
+    }
+
+    /// Applications in the `included_applications` of the `.app` file of
+    /// `app`, which it starts as part of its own supervision tree
+    pub fn included_apps(&self, app: Atom) -> &[Atom] {
+        self.included_apps
+            .get(&app)
+            .map_or(&[][..], |deps| deps.as_slice())
+    }
+
+    /// Applications including `app`, sorted by name
+    pub fn includers(&self, app: Atom) -> Vec<Atom> {
+        let mut includers: Vec<_> = self
+            .included_apps
+            .iter()
+            .filter(|(_, included)| included.contains(&app))
+            .map(|(&includer, _)| includer)
+            .collect();
+        includers.sort_by_key(|&includer| self.name(includer));
+        includers
+    }
+
+    /// Applications whose modules are called from modules of `app`, or
+    /// compile them as parse transforms, sorted by name. Calls to modules
+    /// that are not loaded can't be attributed.
+    pub fn used_apps(&self, app: Atom) -> Vec<Atom> {
+        let mut used: Vec<_> = self
+            .app_modules
+            .get(&app)
+            .into_iter()
+            .flatten()
+            .flat_map(|module| {
+                let module = &self.modules[module];
+                module.imports.keys().chain(&module.parse_transforms)
+            })
+            .filter_map(|&imported| self.owner(imported))
+            .filter(|&owner| owner != app)
+            .collect();
+        used.sort_by_key(|app| app.resolve(&self.interner));
+        used.dedup();
+        used
+    }
+
+    /// Remote calls made from modules of other applications into modules
+    /// of `app`, as `(caller, module, function, arity)`
+    pub fn calls_into(&self, app: Atom) -> Vec<(Atom, Atom, Atom, u32)> {
+        let mut calls: Vec<_> = self
+            .modules
+            .iter()
+            .filter(|(caller, _)| self.owner(**caller) != Some(app))
+            .flat_map(|(&caller, module)| {
+                module
+                    .imports
+                    .iter()
+                    .filter(|(imported, _)| self.owner(**imported) == Some(app))
+                    .flat_map(move |(&imported, functions)| {
+                        functions
+                            .iter()
+                            .map(move |&(fun, arity)| (caller, imported, fun, arity))
+                    })
+            })
+            .collect();
+        calls.sort_unstable();
+        calls
+    }
+
+    /// Modules exporting a function named `fun`, with any arity unless one
+    /// is given, as `(module, arity)` sorted by module name
+    pub fn implementors(&self, fun: Atom, arity: Option<u32>) -> Vec<(Atom, u32)> {
+        let mut implementors: Vec<_> = self
+            .exports_by_name
+            .get(&fun)
+            .into_iter()
+            .flatten()
+            .filter(|(_, exported)| arity.is_none_or(|arity| arity == *exported))
+            .copied()
+            .collect();
+        implementors.sort_by_key(|&(module, arity)| (module.resolve(&self.interner), arity));
+        implementors
+    }
+
+    /// Collapses missing dependency findings into one suggestion per pair of
+    /// applications, as `(app, dep, modules of app needing dep)`, the ones
+    /// needed by most modules first
+    pub fn dependency_suggestions(
+        &self,
+        results: &[(Atom, AnalysisResult)],
+    ) -> Vec<(Atom, Atom, Vec<Atom>)> {
+        let mut suggestions = FxHashMap::<(Atom, Atom), Vec<Atom>>::default();
+        for (module, result) in results {
+            if let AnalysisResult::MissingDependency {
+                app_from, app_to, ..
+            } = result
+            {
+                suggestions
+                    .entry((*app_from, *app_to))
+                    .or_default()
+                    .push(*module);
+            }
+        }
+
+        let mut suggestions: Vec<_> = suggestions
+            .into_iter()
+            .map(|((app, dep), mut modules)| {
+                modules.sort_by_key(|module| module.resolve(&self.interner));
+                modules.dedup();
+                (app, dep, modules)
+            })
+            .collect();
+        suggestions.sort_by_key(|(app, dep, modules)| {
+            (
+                Reverse(modules.len()),
+                app.resolve(&self.interner),
+                dep.resolve(&self.interner),
+            )
+        });
+        suggestions
+    }
+
+    /// Application a finding belongs to - for module-level results that's
+    /// the owner of the module the finding is reported for
+    pub fn result_app(&self, subject: Atom, result: &AnalysisResult) -> Option<Atom> {
+        match result {
+            AnalysisResult::DependencyNotFound { app, .. }
+            | AnalysisResult::UnusedDependency { app, .. }
+            | AnalysisResult::RedundantDependency { app, .. }
+            | AnalysisResult::SelfDependency { app }
+            | AnalysisResult::UndeclaredRuntimeDependency { app, .. }
+            | AnalysisResult::UnusedRuntimeDependency { app, .. } => Some(*app),
+            AnalysisResult::AppCycle { .. } => Some(subject),
+            AnalysisResult::DuplicateModule { app, .. }
+            | AnalysisResult::RegisteredNameClash { app, .. }
+            | AnalysisResult::UnlistedModule { app, .. }
+            | AnalysisResult::MissingBeam { app, .. }
+            | AnalysisResult::AppCallback { app, .. } => Some(*app),
+            _ => self.owner(subject),
+        }
+    }
+
+    /// Source file and line of the call a finding is about, for modules
+    /// compiled with debug info: the first call of the function, or of any
+    /// function of the module for an undefined one
+    pub fn location(&self, subject: Atom, result: &AnalysisResult) -> Option<(PathBuf, u32)> {
+        let (called, function) = match result {
+            AnalysisResult::MissingModule(module)
+            | AnalysisResult::MissingOptionalModule { module, .. }
+            | AnalysisResult::CheckedMissingModule { module, .. }
+            | AnalysisResult::TestCodeCall { module } => (*module, None),
+            AnalysisResult::MissingFunction(module, function, arity, _)
+            | AnalysisResult::ArityMismatch {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::MacroCall {
+                module,
+                function,
+                arity,
+            }
+            | AnalysisResult::UnexportedFunction {
+                module,
+                function,
+                arity,
+            }
+            | AnalysisResult::UndefinedCapture {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::DeprecatedCall {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::ObsoleteCall {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::ForbiddenCall {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::RiskyCall {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::LayerViolation {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::GroupBoundary {
+                module,
+                function,
+                arity,
+                ..
+            }
+            | AnalysisResult::InternalModuleCall {
+                module,
+                function,
+                arity,
+                ..
+            } => (*module, Some((*function, *arity))),
+            _ => return None,
+        };
+        let loaded = self.modules.get(&subject)?;
+        let line = loaded
+            .call_lines
+            .iter()
+            .filter(|((module, fun, arity), _)| {
+                *module == called && function.is_none_or(|function| function == (*fun, *arity))
+            })
+            .map(|(_, &line)| line)
+            .min()?;
+        let recorded = loaded.source.as_ref()?;
+        let source = self
+            .checkout_source(recorded)
+            .unwrap_or_else(|| recorded.clone());
+        Some((source, line))
+    }
+
+    /// Application providing `module`
+    pub fn owner(&self, module: Atom) -> Option<Atom> {
+        self.modules_rev.get(&module).copied()
+    }
+
+    /// Reverse index from every loaded module to its application
+    pub fn modules_rev(&self) -> &FxHashMap<Atom, Atom> {
+        &self.modules_rev
+    }
+
+    /// Directory the application was loaded from
+    pub fn app_path(&self, app: Atom) -> Option<&Path> {
+        self.app_paths.get(&app).map(|path| path.as_path())
+    }
+
+    /// Version from the .app file of the application
+    pub fn app_version(&self, app: Atom) -> Option<&str> {
+        self.app_versions.get(&app).map(|vsn| vsn.as_str())
+    }
+
+    /// Application callback module, from `mod` in the .app file
+    pub fn app_callback(&self, app: Atom) -> Option<Atom> {
+        self.app_callbacks.get(&app).copied()
+    }
+
+    /// Functions the function calls, in the order of the code, when its
+    /// module could be disassembled
+    pub fn function_calls(&self, module: Atom, fun: Atom, arity: u32) -> Option<&[Mfa]> {
+        let calls = self.modules.get(&module)?.function_calls.as_ref()?;
+        calls.get(&(fun, arity)).map(Vec::as_slice)
+    }
+
+    pub fn module(&self, module: Atom) -> Option<&Module> {
+        self.modules.get(&module)
+    }
+
+    /// Counts, for every pair of distinct applications, how many distinct
+    /// (caller module, callee module) pairs connect them, most coupled first
+    /// and then by name.
+    pub fn app_couplings(&self) -> Vec<(Atom, Atom, usize)> {
+        let mut couplings = FxHashMap::<(Atom, Atom), usize>::default();
+
+        for (module, Module { imports, .. }) in &self.modules {
+            let app_from = match self.modules_rev.get(module) {
+                Some(&app) => app,
+                None => continue,
+            };
+            for imported in imports.keys() {
+                match self.modules_rev.get(imported) {
+                    Some(&app_to) if app_to != app_from => {
+                        *couplings.entry((app_from, app_to)).or_default() += 1
+                    }
+                    _ => continue,
+                }
+            }
+        }
+
+        let mut couplings: Vec<_> = couplings
+            .into_iter()
+            .map(|((app_from, app_to), count)| (app_from, app_to, count))
+            .collect();
+        couplings.sort_by_key(|&(app_from, app_to, count)| {
+            (Reverse(count), self.name(app_from), self.name(app_to))
+        });
+        couplings
+    }
+
+    /// Every cycle with an analyzed application, reported once for the
+    /// first of them by name
+    fn check_app_cycles(&self, apps: &[Atom]) -> Vec<(Atom, AnalysisResult)> {
+        if self.only_modules.is_some() {
+            return vec![];
+        }
+        self.app_cycles
+            .iter()
+            // Applications depending on themselves are reported by the
+            // self_dependency check
+            .filter(|cycle| cycle.len() > 1)
+            .filter_map(|cycle| {
+                let app = cycle.iter().find(|app| apps.contains(app))?;
+                let apps = cycle.clone();
+                let edges = self.cycle_edges(&self.app_deps, &apps);
+                Some((*app, AnalysisResult::AppCycle { apps, edges }))
+            })
+            .collect()
+    }
+
+    /// Edges of `graph` between the applications of a cycle, sorted by name
+    pub fn cycle_edges(&self, graph: &AppDeps, apps: &[Atom]) -> Vec<(Atom, Atom)> {
+        let mut edges: Vec<_> = apps
+            .iter()
+            .flat_map(|&from| {
+                graph
+                    .neighbors(from)
+                    .filter(|to| apps.contains(to))
+                    .map(move |to| (from, to))
+            })
+            .collect();
+        edges.sort_by_key(|&(from, to)| (self.name(from), self.name(to)));
+        edges
+    }
+
+    /// Calls between distinct modules of `app`, as edges from the caller
+    pub fn module_graph(&self, app: Atom) -> DiGraphMap<Atom, ()> {
+        let mut graph = DiGraphMap::new();
+        for &module in self.app_modules.get(&app).into_iter().flatten() {
+            graph.add_node(module);
+            for &imported in self.modules[&module].imports.keys() {
+                if imported != module && self.owner(imported) == Some(app) {
+                    graph.add_edge(module, imported, ());
+                }
+            }
+        }
+        graph
+    }
+
+    /// Strongly connected components of the module graph, reported for
+    /// their first module by name
+    fn check_module_cycles(&self, app: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let graph = self.module_graph(app);
+        let mut cycles: Vec<_> = algo::tarjan_scc(&graph)
+            .into_iter()
+            .filter(|modules| modules.len() >= self.min_cycle_size)
+            .map(|mut modules| {
+                modules.sort_by_key(|&module| self.name(module));
+                modules
+            })
+            .collect();
+        cycles.sort_by_key(|modules| self.name(modules[0]));
+        cycles
+            .into_iter()
+            .map(|modules| (modules[0], AnalysisResult::ModuleCycle { modules }))
+            .collect()
+    }
+
+    fn check_deps_found(&self, app: Atom) -> Vec<(Atom, AnalysisResult)> {
+        self.declared_deps(app)
+            .iter()
+            .chain(self.included_apps(app))
+            .filter(|dep| !self.app_modules.contains_key(dep))
+            .map(|&dep| {
+                let otp = otp::is_otp_app(self.name(dep));
+                let optional = self.optional_deps(app).contains(&dep);
+                let result = AnalysisResult::DependencyNotFound {
+                    app,
+                    dep,
+                    otp,
+                    optional,
+                };
+                (app, result)
+            })
+            .collect()
+    }
+
+    /// Declared dependencies that were loaded, but aren't called. Ones
+    /// that weren't loaded are reported as not found instead, and with the
+    /// redundant_dependency check, ones implied by another dependency as
+    /// redundant. Included applications are started by `app` itself, so
+    /// they are never unused.
+    fn check_deps_used(&self, app: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let used = self.used_apps(app);
+        let declared = self.declared_deps(app);
+        let self_dependency = declared
+            .contains(&app)
+            .then_some((app, AnalysisResult::SelfDependency { app }));
+        let unused = declared
+            .iter()
+            .filter(|&&dep| dep != app)
+            .filter(|dep| self.app_modules.contains_key(dep) && !used.contains(dep))
+            .filter(|&&dep| {
+                let name = self.name(dep);
+                !REQUIRED_DEPS.contains(&name)
+                    && !self
+                        .start_only_deps
+                        .iter()
+                        .any(|pattern| pattern.matches(name))
+            })
+            .map(|&dep| {
+                let via = declared
+                    .iter()
+                    .filter(|_| self.enabled("redundant_dependency"))
+                    .filter(|&&other| other != dep && other != app)
+                    .filter(|&&other| algo::has_path_connecting(&self.app_deps, other, dep, None))
+                    .min_by_key(|&&other| self.name(other));
+                let result = match via {
+                    Some(&via) => AnalysisResult::RedundantDependency { app, dep, via },
+                    None => AnalysisResult::UnusedDependency { app, dep },
+                };
+                (app, result)
+            });
+        self_dependency.into_iter().chain(unused).collect()
+    }
+
+    /// Compares the `runtime_dependencies` of the `.app` file to the
+    /// applications called, when it has the key. erts, kernel and stdlib
+    /// are listed by all OTP applications, so never unused.
+    fn check_runtime_deps(&self, app: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let declared = match self.runtime_deps.get(&app) {
+            Some(declared) => declared,
+            None => return vec![],
+        };
+        let used = self.used_apps(app);
+
+        let undeclared = used
+            .iter()
+            .filter(|dep| !declared.contains(dep))
+            .map(|&dep| {
+                (
+                    app,
+                    AnalysisResult::UndeclaredRuntimeDependency { app, dep },
+                )
+            });
+        let unused = declared
+            .iter()
+            .filter(|dep| self.app_modules.contains_key(dep) && !used.contains(dep))
+            .filter(|&&dep| {
+                let name = self.name(dep);
+                name != "erts" && !REQUIRED_DEPS.contains(&name)
+            })
+            .map(|&dep| (app, AnalysisResult::UnusedRuntimeDependency { app, dep }));
+        undeclared.chain(unused).collect()
+    }
+
+    /// Compares the modules of the `.app` file to the loaded beam files.
+    /// Excluded modules are neither loaded nor missing.
+    fn check_listed_modules(&self, app: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let listed = match self.listed_modules.get(&app) {
+            Some(listed) => listed,
+            None => return vec![],
+        };
+        let loaded = self.app_modules.get(&app).map_or(&[][..], Vec::as_slice);
+
+        let mut unlisted: Vec<_> = loaded
+            .iter()
+            .filter(|module| !listed.contains(module))
+            .map(|&module| (module, AnalysisResult::UnlistedModule { app, module }))
+            .collect();
+        unlisted.sort_by_key(|&(module, _)| self.name(module));
+        let missing = listed
+            .iter()
+            .filter(|module| !loaded.contains(module) && !self.excluded.contains(module))
+            .map(|&module| (app, AnalysisResult::MissingBeam { app, module }));
+        unlisted.into_iter().chain(missing).collect()
+    }
+
+    /// The `mod` callback has to implement the `application` behaviour
+    fn check_app_callback(&self, app: Atom) -> Option<(Atom, AnalysisResult)> {
+        let callback = self.app_callback(app)?;
+        if self.excluded.contains(&callback) {
+            return None;
+        }
+        let missing = self.modules.get(&callback).map(|module| {
+            APP_CALLBACKS
+                .iter()
+                .filter(|&&(fun, arity)| {
+                    !module.exports.iter().any(|&(exported, exported_arity)| {
+                        exported_arity == arity && self.name(exported) == fun
+                    })
+                })
+                .copied()
+                .collect::<Vec<_>>()
+        });
+        if missing.as_ref().is_some_and(Vec::is_empty) {
+            return None;
+        }
+        Some((
+            app,
+            AnalysisResult::AppCallback {
+                app,
+                callback,
+                missing,
+            },
+        ))
+    }
+
+    /// Where a source path recorded by the compiler is in the checkout,
+    /// if it's under one of the source roots
+    fn checkout_source(&self, recorded: &Path) -> Option<PathBuf> {
+        self.source_roots.iter().find_map(|root| {
+            let relative = recorded.strip_prefix(&root.recorded).ok()?;
+            Some(root.checkout.join(relative))
+        })
+    }
+
+    fn check_source(&self, module: Atom) -> Option<(Atom, AnalysisResult)> {
+        let recorded = self.modules[&module].source.as_ref()?;
+        let source = self.checkout_source(recorded)?;
+
+        if source.exists() {
+            None
+        } else {
+            Some((module, AnalysisResult::MissingSource { source }))
+        }
+    }
+
+    /// Literal paths into `priv/` in modules that run external programs
+    fn check_priv_files(&self, app: Atom, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        const RUNNERS: &[(&str, &str)] = &[
+            ("os", "cmd"),
+            ("erlang", "open_port"),
+            ("Elixir.System", "cmd"),
+            ("Elixir.Port", "open"),
+        ];
+
+        let Module {
+            imports,
+            literal_strings,
+            ..
+        } = &self.modules[&module];
+        let runs_programs = imports.iter().any(|(imported, functions)| {
+            functions.iter().any(|(fun, _)| {
+                let call = (
+                    imported.resolve(&self.interner).unwrap(),
+                    fun.resolve(&self.interner).unwrap(),
+                );
+                RUNNERS.contains(&call)
+            })
+        });
+        let app_path = match self.app_paths.get(&app) {
+            Some(app_path) if runs_programs => app_path,
+            _ => return vec![],
+        };
+
+        let mut paths: Vec<_> = literal_strings
+            .iter()
+            .filter_map(|string| priv_path(string))
+            .collect();
+        paths.sort_unstable();
+        paths.dedup();
+
+        paths
+            .into_iter()
+            .filter(|path| !app_path.join(path).exists())
+            .map(|path| (module, AnalysisResult::MissingPrivFile { path }))
+            .collect()
+    }
+
+    /// The `-on_load` function has to be defined, or loading the module
+    /// fails. Without the disassembled code only exports are known.
+    fn check_on_load(&self, module: Atom) -> Option<(Atom, AnalysisResult)> {
+        let Module {
+            exports,
+            on_load,
+            function_calls,
+            ..
+        } = &self.modules[&module];
+        let function = (*on_load)?;
+        let defined = exports.contains(&(function, 0))
+            || function_calls
+                .as_ref()
+                .is_none_or(|calls| calls.contains_key(&(function, 0)));
+        if defined {
+            None
+        } else {
+            Some((module, AnalysisResult::MissingOnLoad { module, function }))
+        }
+    }
+
+    /// Whether the function is a NIF stub, replaced when the NIF library is
+    /// loaded and raising an error otherwise
+    pub fn is_nif_stub(&self, module: Atom, fun: Atom, arity: u32) -> bool {
+        self.function_calls(module, fun, arity)
+            .into_iter()
+            .flatten()
+            .any(|&(called, called_fun, _)| {
+                self.name(called) == "erlang" && self.name(called_fun) == "nif_error"
+            })
+    }
+
+    /// Calls to NIF stubs of applications without a shared library in
+    /// `priv/`, which fail when the stubs are never replaced
+    fn check_nif_library(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        let stubs: Vec<_> = functions
+            .iter()
+            .filter(|&&(fun, arity)| self.is_nif_stub(imported, fun, arity))
+            .collect();
+        let ships_library = self
+            .owner(imported)
+            .and_then(|app| self.app_paths.get(&app))
+            .is_none_or(|app_path| has_shared_library(&app_path.join("priv")));
+        if stubs.is_empty() || ships_library {
+            return vec![];
+        }
+        stubs
+            .into_iter()
+            .map(|&(function, arity)| {
+                let result = AnalysisResult::NifWithoutLibrary {
+                    module: imported,
+                    function,
+                    arity,
+                };
+                (module, result)
+            })
+            .collect()
+    }
+
+    fn check_missing_module(
+        &self,
+        module: Atom,
+        imported: Atom,
+        functions: &[(Atom, u32)],
+    ) -> Vec<(Atom, AnalysisResult)> {
+        // Protocols call their implementations for every type, whether
+        // there is one or not
+        if self.modules[&module].protocol {
+            let implementation = self
+                .name(imported)
+                .strip_prefix(self.name(module))
+                .is_some_and(|rest| rest.starts_with('.'));
+            if implementation {
+                return vec![];
+            }
+        }
+        match self.modules.get(&imported) {
+            Some(Module {
+                exports, locals, ..
+            }) => functions
+                .iter()
+                .filter(|fa| !exports.contains(fa))
+                .map(|&(f, a)| {
+                    let mut arities: Vec<_> = exports
+                        .iter()
+                        .filter(|&&(export, _)| export == f)
+                        .map(|&(_, arity)| arity)
+                        .collect();
+                    let macro_name = format!("MACRO-{}", self.name(f));
+                    let is_macro = self
+                        .atom(&macro_name)
+                        .is_some_and(|name| exports.contains(&(name, a + 1)));
+                    let result = if is_macro {
+                        AnalysisResult::MacroCall {
+                            module: imported,
+                            function: f,
+                            arity: a,
+                        }
+                    } else if locals.contains(&(f, a)) {
+                        AnalysisResult::UnexportedFunction {
+                            module: imported,
+                            function: f,
+                            arity: a,
+                        }
+                    } else if arities.is_empty() {
+                        let suggestions = self.similar_exports(exports, f, a);
+                        AnalysisResult::MissingFunction(imported, f, a, suggestions)
+                    } else {
+                        arities.sort_unstable();
+                        AnalysisResult::ArityMismatch {
+                            module: imported,
+                            function: f,
+                            arity: a,
+                            arities,
+                        }
+                    };
+                    (module, result)
+                })
+                .collect(),
+            None => match self.optional_owner(module, imported) {
+                Some(dep) => {
+                    let result = AnalysisResult::MissingOptionalModule {
+                        module: imported,
+                        dep,
+                    };
+                    vec![(module, result)]
+                }
+                None => match self.load_check(module, imported) {
+                    Some(check) => {
+                        let result = AnalysisResult::CheckedMissingModule {
+                            module: imported,
+                            check,
+                        };
+                        vec![(module, result)]
+                    }
+                    None => vec![(module, AnalysisResult::MissingModule(imported))],
+                },
+            },
+        }
+    }
+
+    /// The optional dependency of the caller's application that isn't
+    /// loaded and the module belongs to, going by the usual naming:
+    /// `dep`, `dep_*` or the Elixir `Dep` and `Dep.*`
+    fn optional_owner(&self, caller: Atom, module: Atom) -> Option<Atom> {
+        let app = self.owner(caller)?;
+        let name = self.name(module);
+        self.optional_deps(app).iter().copied().find(|dep| {
+            let dep_name = self.name(*dep);
+            let elixir: String = dep_name
+                .split('_')
+                .map(|part| {
+                    let mut chars = part.chars();
+                    chars.next().map_or(String::new(), |first| {
+                        first.to_ascii_uppercase().to_string() + chars.as_str()
+                    })
+                })
+                .collect();
+            let elixir = format!("Elixir.{}", elixir);
+            !self.app_modules.contains_key(dep)
+                && (name == dep_name
+                    || name
+                        .strip_prefix(dep_name)
+                        .is_some_and(|rest| rest.starts_with('_'))
+                    || name == elixir
+                    || name
+                        .strip_prefix(&elixir)
+                        .is_some_and(|rest| rest.starts_with('.')))
+        })
+    }
+
+    /// The check every function of `caller` calling `module` does first,
+    /// whether it's loaded, if they all do
+    fn load_check(&self, caller: Atom, module: Atom) -> Option<Mfa> {
+        let loaded = &self.modules[&caller];
+        let mut checks = loaded
+            .function_calls
+            .as_ref()?
+            .iter()
+            .filter(|(_, calls)| calls.iter().any(|&(called, _, _)| called == module))
+            .map(|(function, _)| {
+                loaded
+                    .load_checks
+                    .get(function)?
+                    .iter()
+                    .find(|&&(checked, _)| checked == module)
+                    .map(|&(_, check)| check)
+            });
+        let first = checks.next()??;
+        checks.all(|check| check.is_some()).then_some(first)
+    }
+
+    /// At most three exports with similar names and the same arity, that
+    /// could have been meant by a call to `fun/arity`
+    fn similar_exports(&self, exports: &[(Atom, u32)], fun: Atom, arity: u32) -> Vec<(Atom, u32)> {
+        let mut names: Vec<_> = exports
+            .iter()
+            .filter(|&&(f, a)| a == arity && f != fun)
+            .map(|&(f, _)| self.name(f))
+            .filter(|name| !name.starts_with("MACRO-"))
+            .collect();
+        names.sort_unstable();
+        suggest::closest(self.name(fun), names.iter().copied())
+            .into_iter()
+            .take(3)
+            .filter_map(|name| self.atom(name))
+            .map(|f| (f, arity))
+            .collect()
+    }
+
+    fn check_dynamic_calls(&self, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        self.modules[&module]
+            .dynamic_calls
+            .iter()
+            .filter_map(|call| {
+                let module_missing = match self.modules.get(&call.module) {
+                    Some(called) => {
+                        let fa = (call.function, call.arity);
+                        if called.exports.contains(&fa) || self.name(call.function) == "module_info"
+                        {
+                            return None;
+                        }
+                        false
+                    }
+                    None if call.fun_ref => true,
+                    None => return None,
+                };
+                let result = if call.fun_ref {
+                    AnalysisResult::UndefinedCapture {
+                        module: call.module,
+                        function: call.function,
+                        arity: call.arity,
+                        module_missing,
+                    }
+                } else {
+                    AnalysisResult::UndefinedDynamicCall {
+                        module: call.module,
+                        function: call.function,
+                        arity: call.arity,
+                    }
+                };
+                Some((module, result))
+            })
+            .collect()
+    }
+
+    /// Parse transforms have to be available to compile the module, so
+    /// their applications are dependencies like called ones
+    fn check_parse_transforms(&self, module: Atom) -> Vec<(Atom, AnalysisResult)> {
+        self.modules[&module]
+            .parse_transforms
+            .iter()
+            .flat_map(|&transform| {
+                if self.modules.contains_key(&transform) {
+                    self.check_missing_dep(module, transform)
+                } else {
+                    let result = AnalysisResult::UndefinedParseTransform { transform };
+                    vec![(module, result)]
+                }
+            })
+            .collect()
+    }
+
+    fn check_missing_dep(&self, module: Atom, imported: Atom) -> Vec<(Atom, AnalysisResult)> {
+        let app_from = self.modules_rev[&module];
+
+        if let Some(&app_to) = self.modules_rev.get(&imported) {
+            // An included application only runs once its includer started,
+            // and with it the includer's dependencies
+            let reachable = |app| algo::has_path_connecting(&self.app_deps, app, app_to, None);
+            if reachable(app_from) || self.includers(app_from).into_iter().any(reachable) {
+                vec![]
+            } else {
+                vec![(
+                    module,
+                    AnalysisResult::MissingDependency {
+                        module: imported,
+                        app_from,
+                        app_to,
+                    },
+                )]
+            }
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Extracts `priv/<file>` out of a command line or path
+fn priv_path(string: &str) -> Option<PathBuf> {
+    let start = string.find("priv/")?;
+    if start > 0 && !string[..start].ends_with([' ', '"', '\'']) {
+        return None;
+    }
+    let path = string[start..]
+        .split(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+        .next()?;
+    if path.len() > "priv/".len() && !path.contains('*') {
+        Some(PathBuf::from(path))
+    } else {
+        None
+    }
+}
+
+// Concurrent queries rely on this, fail the build instead of the embedders
+#[allow(dead_code)]
+fn assert_send_sync() {
+    fn assert<T: Send + Sync>() {}
+    assert::<Analyzer>();
+    assert::<Workspace>();
+}
+
+/// Whether the directory has a `.so`, `.dll` or `.dylib` file, at any depth
+fn has_shared_library(dir: &Path) -> bool {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        if path.is_dir() {
+            return has_shared_library(&path);
+        }
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| matches!(ext, "so" | "dll" | "dylib"))
+    })
+}
+
+/// The application a beam file was loaded from, the one with the longest
+/// matching directory
+fn app_of_path(app_paths: &AppPaths, path: &Path) -> Option<Atom> {
+    app_paths
+        .iter()
+        .filter(|(_, app_path)| path.starts_with(app_path))
+        .max_by_key(|(_, app_path)| app_path.as_os_str().len())
+        .map(|(&app, _)| app)
 }
@@ -0,0 +1,122 @@
+//! The API surface of an application: which of its exports other loaded
+//! applications call, and from which modules. What nobody calls can be
+//! changed or deprecated without breaking them.
+
+use std::{collections::BTreeMap, io::Write};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::{
+    analyzer::Analyzer,
+    types::{Atom, FunctionDoc},
+};
+
+pub struct Export {
+    pub module: Atom,
+    pub function: Atom,
+    pub arity: u32,
+    /// Calling modules by application, both by name
+    pub callers: BTreeMap<String, Vec<String>>,
+    /// From the Docs chunk, if it has any
+    pub doc: FunctionDoc,
+}
+
+/// Every export of the modules of `app` but `module_info`, sorted by name,
+/// with its callers from other applications. Calls through `{M, F, Args}`
+/// and fun literals count.
+pub fn surface(analyzer: &Analyzer, app: Atom) -> Vec<Export> {
+    let mut exports = vec![];
+    for &module in analyzer.app_modules().get(&app).into_iter().flatten() {
+        let loaded = analyzer.module(module).unwrap();
+        for &(function, arity) in &loaded.exports {
+            if analyzer.name(function) == "module_info" {
+                continue;
+            }
+            let mut callers = BTreeMap::<_, Vec<_>>::new();
+            for &caller in analyzer.callers(module, function, arity) {
+                match analyzer.owner(caller) {
+                    Some(owner) if owner != app => callers
+                        .entry(analyzer.name(owner).to_string())
+                        .or_default()
+                        .push(analyzer.name(caller).to_string()),
+                    _ => {}
+                }
+            }
+            for modules in callers.values_mut() {
+                modules.sort_unstable();
+            }
+            exports.push(Export {
+                module,
+                function,
+                arity,
+                callers,
+                doc: loaded
+                    .docs
+                    .get(&(function, arity))
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+    }
+    exports.sort_by_key(|export| {
+        (
+            analyzer.name(export.module),
+            analyzer.name(export.function),
+            export.arity,
+        )
+    });
+    exports
+}
+
+fn name(analyzer: &Analyzer, export: &Export) -> String {
+    format!(
+        "{}:{}/{}",
+        analyzer.name(export.module),
+        analyzer.name(export.function),
+        export.arity
+    )
+}
+
+pub fn write_human(analyzer: &Analyzer, exports: &[Export], out: &mut dyn Write) -> Result<()> {
+    let (called, uncalled): (Vec<_>, Vec<_>) = exports
+        .iter()
+        .partition(|export| !export.callers.is_empty());
+
+    writeln!(
+        out,
+        "called by other applications ({} of {}):",
+        called.len(),
+        exports.len()
+    )?;
+    for export in called {
+        writeln!(out, "  {}", name(analyzer, export))?;
+        for (app, modules) in &export.callers {
+            writeln!(out, "    {}: {}", app, modules.join(", "))?;
+        }
+    }
+
+    writeln!(out, "\nnot called by other applications:")?;
+    for export in uncalled {
+        writeln!(out, "  {}", name(analyzer, export))?;
+    }
+    Ok(())
+}
+
+pub fn write_json(analyzer: &Analyzer, exports: &[Export], out: &mut dyn Write) -> Result<()> {
+    let json: Vec<_> = exports
+        .iter()
+        .map(|export| {
+            json!({
+                "function": name(analyzer, export),
+                "callers": export.callers,
+                "hidden": export.doc.hidden,
+                "deprecated": export.doc.deprecated,
+                "since": export.doc.since,
+            })
+        })
+        .collect();
+    serde_json::to_writer_pretty(&mut *out, &json)?;
+    writeln!(out)?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Snapshots of known findings, so that only new ones get reported. Findings
+//! are identified by check, module and target rather than by message, which
+//! keeps the baseline valid when messages are reworded.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{analyzer::AnalysisResult, types::Atom, types::Interner};
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    version: u32,
+    findings: BTreeSet<Entry>,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct Entry {
+    check: String,
+    module: String,
+    target: String,
+}
+
+impl Entry {
+    fn new(subject: Atom, result: &AnalysisResult, interner: &Interner) -> Entry {
+        Entry {
+            check: result.check_name().to_string(),
+            module: subject.resolve(interner).unwrap().to_string(),
+            target: result.target(interner),
+        }
+    }
+}
+
+impl Baseline {
+    pub fn new(results: &[(Atom, AnalysisResult)], interner: &Interner) -> Baseline {
+        Baseline {
+            version: VERSION,
+            findings: results
+                .iter()
+                .map(|(subject, result)| Entry::new(*subject, result, interner))
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Baseline> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("reading baseline: {}", path.display()))?;
+        let baseline: Baseline = serde_json::from_str(&text)
+            .with_context(|| format!("parsing baseline: {}", path.display()))?;
+        if baseline.version != VERSION {
+            anyhow::bail!(
+                "unsupported baseline version {} in {}",
+                baseline.version,
+                path.display()
+            );
+        }
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        fs::write(path, json).with_context(|| format!("writing baseline: {}", path.display()))
+    }
+
+    pub fn contains(&self, subject: Atom, result: &AnalysisResult, interner: &Interner) -> bool {
+        self.findings
+            .contains(&Entry::new(subject, result, interner))
+    }
+}
@@ -0,0 +1,147 @@
+//! The smallest set of applications needed to run some others, following
+//! the calls between modules rather than the dependencies declared in the
+//! `.app` files.
+
+use std::io::Write;
+
+use anyhow::Result;
+use fxhash::{FxHashMap, FxHashSet};
+
+use crate::{analyzer::Analyzer, types::Atom};
+
+/// Applications every release has to include to boot
+const BOOT_APPS: &[&str] = &["kernel", "stdlib"];
+
+pub struct Needed {
+    pub app: Atom,
+    /// Applications whose modules call into it, sorted by name. Empty for
+    /// the roots and the applications needed to boot.
+    pub by: Vec<Atom>,
+}
+
+/// Applications needed by `roots`, in the order they were reached. Every
+/// module of a root is taken as run, then the modules they call, directly
+/// or through `{M, F, Args}` and fun literals, and the application
+/// callback of every application reached, as starting it runs it. The
+/// included applications of one reached are started by it, so needed too.
+pub fn needed_apps(analyzer: &Analyzer, roots: &[Atom]) -> Vec<Needed> {
+    let mut order = vec![];
+    let mut by = FxHashMap::<Atom, FxHashSet<Atom>>::default();
+    let mut reached = FxHashSet::default();
+    let mut queue = vec![];
+
+    let boot = BOOT_APPS.iter().filter_map(|name| analyzer.app(name));
+    for app in roots.iter().copied().chain(boot) {
+        if by.insert(app, FxHashSet::default()).is_none() {
+            order.push(app);
+            queue.extend(analyzer.app_modules().get(&app).into_iter().flatten());
+        }
+    }
+    for app in order.clone() {
+        include(analyzer, app, &mut order, &mut by, &mut queue);
+    }
+
+    while let Some(module) = queue.pop() {
+        if !reached.insert(module) {
+            continue;
+        }
+        let (caller_app, loaded) = match (analyzer.owner(module), analyzer.module(module)) {
+            (Some(app), Some(loaded)) => (app, loaded),
+            _ => continue,
+        };
+        let called = loaded
+            .imports
+            .keys()
+            .copied()
+            .chain(loaded.dynamic_calls.iter().map(|call| call.module));
+        for called in called {
+            let app = match analyzer.owner(called) {
+                Some(app) => app,
+                None => continue,
+            };
+            queue.push(called);
+            if app == caller_app {
+                continue;
+            }
+            if !by.contains_key(&app) {
+                order.push(app);
+                queue.extend(analyzer.app_callback(app));
+                include(analyzer, app, &mut order, &mut by, &mut queue);
+            }
+            by.entry(app).or_default().insert(caller_app);
+        }
+    }
+
+    order
+        .into_iter()
+        .map(|app| {
+            let mut by: Vec<_> = by.remove(&app).unwrap_or_default().into_iter().collect();
+            by.sort_by_key(|&app| analyzer.name(app));
+            Needed { app, by }
+        })
+        .collect()
+}
+
+/// Reaches the included applications of `app`, and theirs in turn
+fn include(
+    analyzer: &Analyzer,
+    app: Atom,
+    order: &mut Vec<Atom>,
+    by: &mut FxHashMap<Atom, FxHashSet<Atom>>,
+    queue: &mut Vec<Atom>,
+) {
+    for &included in analyzer.included_apps(app) {
+        if analyzer.app_modules().contains_key(&included) && !by.contains_key(&included) {
+            order.push(included);
+            queue.extend(analyzer.app_callback(included));
+            by.entry(included).or_default().insert(app);
+            include(analyzer, included, order, by, queue);
+        }
+    }
+}
+
+pub fn write(
+    analyzer: &Analyzer,
+    roots: &[Atom],
+    needed: &[Needed],
+    out: &mut dyn Write,
+) -> Result<()> {
+    let width = needed
+        .iter()
+        .map(|needed| analyzer.name(needed.app).len())
+        .max()
+        .unwrap_or(0);
+    for Needed { app, by } in needed {
+        let name = analyzer.name(*app);
+        let reason = if roots.contains(app) {
+            "root".to_string()
+        } else if by.is_empty() {
+            "needed to boot".to_string()
+        } else {
+            let (included_by, called_by): (Vec<Atom>, Vec<Atom>) = by
+                .iter()
+                .partition(|&&by| analyzer.included_apps(by).contains(app));
+            let names = |apps: Vec<Atom>| -> Vec<&str> {
+                apps.into_iter().map(|app| analyzer.name(app)).collect()
+            };
+            let mut reasons = vec![];
+            if !included_by.is_empty() {
+                reasons.push(format!("included by {}", names(included_by).join(", ")));
+            }
+            if !called_by.is_empty() {
+                reasons.push(format!("called by {}", names(called_by).join(", ")));
+            }
+            reasons.join("; ")
+        };
+        writeln!(out, "{:width$}  {}", name, reason, width = width)?;
+    }
+
+    let loaded = analyzer.app_modules().len();
+    writeln!(
+        out,
+        "\n{} of {} loaded applications needed",
+        needed.len(),
+        loaded
+    )?;
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Findings of the same code analyzed against several OTP installations,
+//! to see which of them depend on the OTP version. Every installation is
+//! loaded into its own workspace, so findings are compared by their text.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::{
+    analyzer::{AnalysisResult, Analyzer},
+    types::Atom,
+};
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone)]
+pub struct Finding {
+    pub check: &'static str,
+    /// The module or application the finding is reported for
+    pub subject: String,
+    pub message: String,
+}
+
+pub struct Matrix {
+    pub versions: Vec<String>,
+    /// Findings that don't appear under every version
+    pub rows: Vec<Row>,
+    /// Number of findings appearing under every version
+    pub common: usize,
+}
+
+pub struct Row {
+    pub finding: Finding,
+    /// Whether the finding appears, by version
+    pub cells: Vec<bool>,
+}
+
+pub fn findings(analyzer: &Analyzer, results: &[(Atom, AnalysisResult)]) -> BTreeSet<Finding> {
+    let interner = analyzer.interner();
+    results
+        .iter()
+        .map(|(subject, result)| Finding {
+            check: result.check_name(),
+            subject: analyzer.name(*subject).to_string(),
+            message: result.fmt(interner),
+        })
+        .collect()
+}
+
+/// Builds the matrix from the findings under every named version
+pub fn matrix(runs: Vec<(String, BTreeSet<Finding>)>) -> Matrix {
+    let mut findings = BTreeMap::<Finding, Vec<bool>>::new();
+    for (idx, (_, run)) in runs.iter().enumerate() {
+        for finding in run {
+            findings
+                .entry(finding.clone())
+                .or_insert_with(|| vec![false; runs.len()])[idx] = true;
+        }
+    }
+
+    let (common, rows): (Vec<_>, Vec<_>) = findings
+        .into_iter()
+        .map(|(finding, cells)| Row { finding, cells })
+        .partition(|row| row.cells.iter().all(|&found| found));
+    Matrix {
+        versions: runs.into_iter().map(|(version, _)| version).collect(),
+        rows,
+        common: common.len(),
+    }
+}
+
+pub fn write_human(matrix: &Matrix, out: &mut dyn Write) -> Result<()> {
+    let headers: Vec<_> = matrix
+        .versions
+        .iter()
+        .map(|version| format!("OTP {}", version))
+        .collect();
+    writeln!(out, "{}", headers.join("  "))?;
+
+    for row in &matrix.rows {
+        let mut line = String::new();
+        for (&found, header) in row.cells.iter().zip(&headers) {
+            let mark = if found { "x" } else { "" };
+            line.push_str(&format!("{:width$}  ", mark, width = header.len()));
+        }
+        writeln!(
+            out,
+            "{}{}: {}",
+            line, row.finding.subject, row.finding.message
+        )?;
+    }
+
+    if matrix.rows.is_empty() {
+        writeln!(out, "\nno findings depend on the OTP version")?;
+    }
+    writeln!(
+        out,
+        "\n{} findings under every OTP version are not shown",
+        matrix.common
+    )?;
+    Ok(())
+}
+
+pub fn write_json(matrix: &Matrix, out: &mut dyn Write) -> Result<()> {
+    let json = json!({
+        "versions": matrix.versions,
+        "findings": matrix.rows.iter().map(|row| json!({
+            "check": row.finding.check,
+            "subject": row.finding.subject,
+            "message": row.finding.message,
+            "versions": matrix
+                .versions
+                .iter()
+                .zip(&row.cells)
+                .filter(|(_, &found)| found)
+                .map(|(version, _)| version)
+                .collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "common": matrix.common,
+    });
+
+    serde_json::to_writer_pretty(&mut *out, &json)?;
+    writeln!(out)?;
+    Ok(())
+}
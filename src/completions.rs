@@ -0,0 +1,256 @@
+//! Shell completion scripts for the command line. Application and module
+//! names are completed by running `xref list` with the lib paths, config
+//! and profile typed so far, so they come from the project in the current
+//! directory unless those are given.
+
+use std::{io::Write, str::FromStr};
+
+use anyhow::Result;
+
+use crate::output::FORMATS;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Shell> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => anyhow::bail!("unknown shell: {} (expected one of: bash, zsh, fish)", s),
+        }
+    }
+}
+
+const COMMANDS: &[(&str, &str)] = &[
+    ("check", "report problems in the analyzed applications"),
+    ("graph", "application dependency graph"),
+    ("query", "owner, implementors, dep, calls or api queries"),
+    ("list", "loaded applications or modules"),
+    ("list-apps", "loaded applications"),
+    ("list-modules", "loaded modules"),
+    ("manifest", "declared and actually used dependencies"),
+    ("tree", "application dependency tree"),
+    ("why", "dependency path between applications"),
+    ("closure", "applications needed to run some others"),
+    ("start-order", "order the applications can be started in"),
+    ("stats", "workspace totals and most coupled applications"),
+    (
+        "impact",
+        "which releases ship an application and use its exports",
+    ),
+    ("diff", "HTML page comparing the workspace with a base"),
+    ("completions", "shell completion script"),
+];
+
+/// Flags taking an application name
+const APP_FLAGS: &[&str] = &["--analyze", "--app", "--exclude-app"];
+/// Flags taking a module name
+const MODULE_FLAGS: &[&str] = &["--analyze-module", "--exclude-module"];
+/// Flags taking a file or directory
+const PATH_FLAGS: &[&str] = &[
+    "--lib-path",
+    "--config",
+    "--ignore-file",
+    "--release",
+    "--output",
+    "--baseline",
+    "--write-baseline",
+    "--src-root",
+    "--base-lib-path",
+    "--files-from",
+    "--escript",
+    "--otp",
+];
+const OTHER_FLAGS: &[&str] = &[
+    "--erl-libs",
+    "--profile",
+    "--format",
+    "--color",
+    "--analyze-all",
+    "--show-paths",
+    "--summary",
+    "--collapse-otp",
+    "--ignore-older-than",
+    "--sample",
+    "--seed",
+    "--max-warnings",
+    "--reachability",
+    "--min-cycle-size",
+    "--otp-version",
+    "--check",
+    "--entry-point",
+    "--per-app",
+    "--all",
+    "--top-couplings",
+    "--metrics",
+    "--top-modules",
+    "--verbose",
+    "--watch",
+    "--jobs",
+    "--no-parallel",
+    "--help",
+];
+/// Flags passed on to `xref list` when completing names
+const CONTEXT_FLAGS: &str = "--lib-path --config --profile --release";
+
+const COLORS: &str = "auto always never";
+
+pub fn write(shell: Shell, out: &mut dyn Write) -> Result<()> {
+    match shell {
+        Shell::Bash => write_bash(out),
+        Shell::Zsh => write_zsh(out),
+        Shell::Fish => write_fish(out),
+    }
+}
+
+fn all_flags() -> Vec<&'static str> {
+    let mut flags = [APP_FLAGS, MODULE_FLAGS, PATH_FLAGS, OTHER_FLAGS].concat();
+    flags.sort_unstable();
+    flags
+}
+
+fn write_bash(out: &mut dyn Write) -> Result<()> {
+    let commands: Vec<_> = COMMANDS.iter().map(|(name, _)| *name).collect();
+    writeln!(
+        out,
+        r#"_xref_names() {{
+    local args=() i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i]}}" in
+            {context}) args+=("${{COMP_WORDS[i]}}" "${{COMP_WORDS[i+1]}}") ;;
+        esac
+    done
+    xref list "$1" "${{args[@]}}" 2>/dev/null | cut -d' ' -f1
+}}
+
+_xref() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "$prev" in
+        {apps}) COMPREPLY=($(compgen -W "$(_xref_names apps)" -- "$cur")); return ;;
+        {modules}) COMPREPLY=($(compgen -W "$(_xref_names modules)" -- "$cur")); return ;;
+        {paths}) COMPREPLY=($(compgen -f -- "$cur")); return ;;
+        --format) COMPREPLY=($(compgen -W "{formats}" -- "$cur")); return ;;
+        --color) COMPREPLY=($(compgen -W "{colors}" -- "$cur")); return ;;
+        completions) COMPREPLY=($(compgen -W "bash zsh fish" -- "$cur")); return ;;
+    esac
+    if ((COMP_CWORD == 1)); then
+        COMPREPLY=($(compgen -W "{commands}" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -W "{flags}" -- "$cur"))
+    fi
+}}
+
+complete -F _xref xref"#,
+        context = CONTEXT_FLAGS.replace(' ', "|"),
+        apps = APP_FLAGS.join("|"),
+        modules = MODULE_FLAGS.join("|"),
+        paths = PATH_FLAGS.join("|"),
+        formats = FORMATS.join(" "),
+        colors = COLORS,
+        commands = commands.join(" "),
+        flags = all_flags().join(" "),
+    )?;
+    Ok(())
+}
+
+fn write_zsh(out: &mut dyn Write) -> Result<()> {
+    let commands: Vec<_> = COMMANDS
+        .iter()
+        .map(|(name, description)| format!("'{}:{}'", name, description))
+        .collect();
+    writeln!(
+        out,
+        r#"#compdef xref
+
+_xref_names() {{
+    local -a args
+    local i
+    for ((i = 2; i < CURRENT; i++)); do
+        case ${{words[i]}} in
+            {context}) args+=(${{words[i]}} ${{words[i+1]}}) ;;
+        esac
+    done
+    xref list $1 $args 2>/dev/null | cut -d' ' -f1
+}}
+
+_xref() {{
+    case ${{words[CURRENT-1]}} in
+        {apps}) compadd -- ${{(f)"$(_xref_names apps)"}}; return ;;
+        {modules}) compadd -- ${{(f)"$(_xref_names modules)"}}; return ;;
+        {paths}) _files; return ;;
+        --format) compadd -- {formats}; return ;;
+        --color) compadd -- {colors}; return ;;
+        completions) compadd -- bash zsh fish; return ;;
+    esac
+    if ((CURRENT == 2)); then
+        local -a commands=({commands})
+        _describe command commands
+    else
+        compadd -- {flags}
+    fi
+}}
+
+_xref "$@""#,
+        context = CONTEXT_FLAGS.replace(' ', "|"),
+        apps = APP_FLAGS.join("|"),
+        modules = MODULE_FLAGS.join("|"),
+        paths = PATH_FLAGS.join("|"),
+        formats = FORMATS.join(" "),
+        colors = COLORS,
+        commands = commands.join(" "),
+        flags = all_flags().join(" "),
+    )?;
+    Ok(())
+}
+
+fn write_fish(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        r#"function __xref_names
+    set -l args
+    set -l tokens (commandline -opc)
+    for i in (seq 2 (count $tokens))
+        switch $tokens[$i]
+            case {context}
+                set -a args $tokens[$i] $tokens[(math $i + 1)]
+        end
+    end
+    xref list $argv[1] $args 2>/dev/null | string split -f1 ' '
+end
+
+complete -c xref -f"#,
+        context = CONTEXT_FLAGS,
+    )?;
+    for (name, description) in COMMANDS {
+        writeln!(
+            out,
+            "complete -c xref -n __fish_use_subcommand -a {} -d '{}'",
+            name, description
+        )?;
+    }
+    writeln!(
+        out,
+        "complete -c xref -n '__fish_seen_subcommand_from completions' -a 'bash zsh fish'"
+    )?;
+    for flag in all_flags() {
+        let name = flag.trim_start_matches("--");
+        let args = match flag {
+            _ if APP_FLAGS.contains(&flag) => " -x -a '(__xref_names apps)'".to_string(),
+            _ if MODULE_FLAGS.contains(&flag) => " -x -a '(__xref_names modules)'".to_string(),
+            _ if PATH_FLAGS.contains(&flag) => " -r -F".to_string(),
+            "--format" => format!(" -x -a '{}'", FORMATS.join(" ")),
+            "--color" => format!(" -x -a '{}'", COLORS),
+            _ => String::new(),
+        };
+        writeln!(out, "complete -c xref -l {}{}", name, args)?;
+    }
+    Ok(())
+}
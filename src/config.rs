@@ -0,0 +1,247 @@
+//! Project level configuration, read from `xref.toml`. Options given on the
+//! command line take precedence over the file.
+//!
+//! ```toml
+//! lib_paths = ["_build/default/lib"]
+//! analyze = ["my_app"]
+//! checks = ["undefined_function", "undefined_module"]
+//!
+//! # Called from outside of the loaded code, never unused exports
+//! entry_points = ["my_app_cli:main/1", "*_sup:init/1"]
+//!
+//! # Report the exports and modules entry points and callbacks don't reach
+//! reachability = true
+//!
+//! # Declared only to be started first, never unused dependencies
+//! start_only_deps = ["sasl", "runtime_tools"]
+//!
+//! # Private to their application, besides the ones hidden from the docs
+//! internal_modules = ["*_internal"]
+//!
+//! # Smallest module cycle reported by the module_cycle check
+//! min_cycle_size = 3
+//!
+//! # OTP release to report deprecated and removed function calls for
+//! otp_version = 27
+//!
+//! [severity]
+//! missing_dependency = "error"
+//!
+//! [[ignore]]
+//! check = "undefined_module"
+//! target = "eunit"
+//! reason = "only used in tests"
+//!
+//! # Calls the modules of core must not make, see `rules`
+//! [[forbidden_calls]]
+//! apps = ["core"]
+//! calls = ["io:format/2", "hackney"]
+//! reason = "core doesn't do IO"
+//!
+//! # Reported by the risky_call check besides the built-in ones, see `rules`
+//! [[risky_calls]]
+//! calls = ["my_shell:run/1"]
+//! reason = "runs a shell command"
+//!
+//! # Architecture layers from the top down, see `rules`
+//! [[layers]]
+//! name = "web"
+//! apps = ["web"]
+//!
+//! [[layers]]
+//! name = "domain"
+//! apps = ["core"]
+//!
+//! # Groups of applications and the ones they may call, see `rules`
+//! [[groups]]
+//! name = "accounts"
+//! apps = ["accounts"]
+//! may_call = ["shared"]
+//!
+//! [[groups]]
+//! name = "shared"
+//! apps = ["shared_*"]
+//!
+//! # Production code calling it is reported, see `rules`
+//! [test_code]
+//! lib_paths = ["_build/test/lib"]
+//!
+//! [exclude]
+//! apps = ["vendored_*"]
+//! modules = ["/_pb$/"]
+//!
+//! # Selected with --profile, overriding the settings above
+//! [profile.full]
+//! analyze_all = true
+//! checks = ["undefined_function"]
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    analyzer::{Severity, CHECKS},
+    ignore::Ignore,
+    pattern::{Excludes, FunctionPattern, Pattern},
+    rules::{ForbiddenCalls, Group, Layer, RiskyCalls, TestCode},
+};
+
+pub const FILE_NAME: &str = "xref.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Relative paths are resolved against the directory of the file
+    pub lib_paths: Vec<PathBuf>,
+    pub analyze: Vec<String>,
+    pub analyze_all: bool,
+    /// All checks run when not given
+    pub checks: Option<Vec<String>>,
+    pub severity: BTreeMap<String, Severity>,
+    pub ignore: Vec<Ignore>,
+    pub exclude: Excludes,
+    pub entry_points: Vec<FunctionPattern>,
+    pub reachability: bool,
+    pub min_cycle_size: Option<usize>,
+    pub otp_version: Option<u32>,
+    pub forbidden_calls: Vec<ForbiddenCalls>,
+    pub risky_calls: Vec<RiskyCalls>,
+    pub layers: Vec<Layer>,
+    pub groups: Vec<Group>,
+    pub start_only_deps: Vec<Pattern>,
+    pub internal_modules: Vec<Pattern>,
+    pub test_code: TestCode,
+    pub profile: BTreeMap<String, Profile>,
+}
+
+/// Named set of overrides. Severities are merged into the base ones, other
+/// settings replace them when given.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub lib_paths: Option<Vec<PathBuf>>,
+    pub analyze: Option<Vec<String>>,
+    pub analyze_all: Option<bool>,
+    pub checks: Option<Vec<String>>,
+    pub severity: BTreeMap<String, Severity>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("reading config: {}", path.display()))?;
+        let mut config: Config =
+            toml::from_str(&text).with_context(|| format!("parsing config: {}", path.display()))?;
+
+        let checks = config.checks.iter().flatten();
+        let checks = checks
+            .chain(config.severity.keys())
+            .chain(config.ignore.iter().flat_map(|ignore| &ignore.check))
+            .chain(config.profile.values().flat_map(|profile| {
+                profile
+                    .checks
+                    .iter()
+                    .flatten()
+                    .chain(profile.severity.keys())
+            }));
+        for check in checks {
+            if !CHECKS.contains(&check.as_str()) {
+                anyhow::bail!(
+                    "unknown check in {}: {} (expected one of: {})",
+                    path.display(),
+                    check,
+                    CHECKS.join(", ")
+                );
+            }
+        }
+
+        for layer in &config.layers {
+            for name in layer.may_call.iter().flatten() {
+                if !config.layers.iter().any(|layer| layer.name == *name) {
+                    anyhow::bail!(
+                        "unknown layer in {}: {} may call {}, which is not a layer",
+                        path.display(),
+                        layer.name,
+                        name
+                    );
+                }
+            }
+        }
+
+        for group in &config.groups {
+            for name in &group.may_call {
+                if !config.groups.iter().any(|group| group.name == *name) {
+                    anyhow::bail!(
+                        "unknown group in {}: {} may call {}, which is not a group",
+                        path.display(),
+                        group.name,
+                        name
+                    );
+                }
+            }
+        }
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let profile_lib_paths = config
+            .profile
+            .values_mut()
+            .flat_map(|profile| profile.lib_paths.iter_mut().flatten());
+        let lib_paths = config
+            .lib_paths
+            .iter_mut()
+            .chain(&mut config.test_code.lib_paths)
+            .chain(profile_lib_paths);
+        for lib_path in lib_paths {
+            *lib_path = base.join(&*lib_path);
+        }
+
+        Ok(config)
+    }
+
+    /// Applies the named profile. A config without profiles accepts any
+    /// name, as `--profile` also picks the build profile of a project.
+    pub fn with_profile(mut self, name: &str) -> Result<Config> {
+        if self.profile.is_empty() {
+            return Ok(self);
+        }
+        let profile = self.profile.remove(name).with_context(|| {
+            let names: Vec<_> = self.profile.keys().map(String::as_str).collect();
+            format!(
+                "unknown profile: {} (expected one of: {})",
+                name,
+                names.join(", ")
+            )
+        })?;
+
+        if let Some(lib_paths) = profile.lib_paths {
+            self.lib_paths = lib_paths;
+        }
+        if let Some(analyze) = profile.analyze {
+            self.analyze = analyze;
+        }
+        if let Some(analyze_all) = profile.analyze_all {
+            self.analyze_all = analyze_all;
+        }
+        if profile.checks.is_some() {
+            self.checks = profile.checks;
+        }
+        self.severity.extend(profile.severity);
+        Ok(self)
+    }
+
+    /// Loads `xref.toml` from the current directory, if there is one
+    pub fn load_default() -> Result<Config> {
+        let path = Path::new(FILE_NAME);
+        if path.exists() {
+            Config::load(path)
+        } else {
+            Ok(Config::default())
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::BTreeSet, io::Write};
+
+use anyhow::Result;
+
+use crate::{
+    graph::Mermaid,
+    types::{Atom, Workspace},
+};
+
+/// Differences between two loaded workspaces. Interners of different
+/// workspaces don't agree on symbols, so everything is compared by name.
+pub struct WorkspaceDiff {
+    pub apps: Changes<String>,
+    pub modules: Changes<(String, String)>,
+    pub app_deps: Changes<(String, String)>,
+}
+
+pub struct Changes<T> {
+    pub added: BTreeSet<T>,
+    pub removed: BTreeSet<T>,
+    pub unchanged: BTreeSet<T>,
+}
+
+impl<T: Ord + Clone> Changes<T> {
+    fn new(old: BTreeSet<T>, new: BTreeSet<T>) -> Changes<T> {
+        Changes {
+            added: new.difference(&old).cloned().collect(),
+            removed: old.difference(&new).cloned().collect(),
+            unchanged: old.intersection(&new).cloned().collect(),
+        }
+    }
+}
+
+pub fn diff(old: &Workspace, new: &Workspace) -> WorkspaceDiff {
+    WorkspaceDiff {
+        apps: Changes::new(apps(old), apps(new)),
+        modules: Changes::new(modules(old), modules(new)),
+        app_deps: Changes::new(app_deps(old), app_deps(new)),
+    }
+}
+
+fn apps(workspace: &Workspace) -> BTreeSet<String> {
+    workspace
+        .app_modules
+        .keys()
+        .map(|app| name(workspace, app))
+        .collect()
+}
+
+fn modules(workspace: &Workspace) -> BTreeSet<(String, String)> {
+    workspace
+        .app_modules
+        .iter()
+        .flat_map(|(app, modules)| {
+            modules
+                .iter()
+                .map(move |module| (name(workspace, app), name(workspace, module)))
+        })
+        .collect()
+}
+
+fn app_deps(workspace: &Workspace) -> BTreeSet<(String, String)> {
+    workspace
+        .app_deps
+        .all_edges()
+        .map(|(from, to, _)| (name(workspace, &from), name(workspace, &to)))
+        .collect()
+}
+
+fn name(workspace: &Workspace, atom: &Atom) -> String {
+    atom.resolve(&workspace.interner).unwrap().to_string()
+}
+
+/// Renders a standalone HTML page with the application graph of both
+/// workspaces merged, added parts highlighted in green and removed in red.
+pub fn write_html(diff: &WorkspaceDiff, out: &mut dyn Write) -> Result<()> {
+    let mut graph = Mermaid::new("LR");
+    graph.line("classDef added fill:#c8f7c5,stroke:#2e7d32");
+    graph.line("classDef removed fill:#f7c5c5,stroke:#c62828,stroke-dasharray:4");
+
+    for app in diff.apps.unchanged.iter() {
+        graph.node(app);
+    }
+    for app in diff.apps.added.iter() {
+        graph.class(app, "added");
+    }
+    for app in diff.apps.removed.iter() {
+        graph.class(app, "removed");
+    }
+    for (from, to) in diff.app_deps.unchanged.iter() {
+        graph.edge(from, to);
+    }
+    for (from, to) in diff.app_deps.added.iter() {
+        let edge = graph.edge(from, to);
+        graph.line(&format!(
+            "linkStyle {} stroke:#2e7d32,stroke-width:2px",
+            edge
+        ));
+    }
+    for (from, to) in diff.app_deps.removed.iter() {
+        let edge = graph.edge(from, to);
+        graph.line(&format!(
+            "linkStyle {} stroke:#c62828,stroke-width:2px,stroke-dasharray:4",
+            edge
+        ));
+    }
+
+    writeln!(
+        out,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>xref workspace diff</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+.added {{ color: #2e7d32; }}
+.removed {{ color: #c62828; }}
+table {{ border-collapse: collapse; }}
+td, th {{ border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }}
+</style>
+<script type="module">
+import mermaid from "https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs";
+mermaid.initialize({{ startOnLoad: true, maxTextSize: 1000000 }});
+</script>
+</head>
+<body>
+<h1>Workspace diff</h1>
+<p>
+<span class="added">{} apps added</span>, <span class="removed">{} apps removed</span>;
+<span class="added">{} modules added</span>, <span class="removed">{} modules removed</span>;
+<span class="added">{} dependencies added</span>, <span class="removed">{} dependencies removed</span>
+</p>
+<h2>Application dependencies</h2>
+<pre class="mermaid">
+{}</pre>"#,
+        diff.apps.added.len(),
+        diff.apps.removed.len(),
+        diff.modules.added.len(),
+        diff.modules.removed.len(),
+        diff.app_deps.added.len(),
+        diff.app_deps.removed.len(),
+        escape_html(&graph.finish()),
+    )?;
+
+    writeln!(out, "<h2>Modules</h2>")?;
+    writeln!(
+        out,
+        "<table>\n<tr><th>application</th><th>module</th><th>change</th></tr>"
+    )?;
+    let changes = diff
+        .modules
+        .added
+        .iter()
+        .map(|module| (module, "added"))
+        .chain(
+            diff.modules
+                .removed
+                .iter()
+                .map(|module| (module, "removed")),
+        );
+    for ((app, module), change) in changes {
+        writeln!(
+            out,
+            r#"<tr class="{}"><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            change,
+            escape_html(app),
+            escape_html(module),
+            change
+        )?;
+    }
+    writeln!(out, "</table>\n</body>\n</html>")?;
+
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,365 @@
+//! Disassembling the Code chunk of BEAM files, just enough to find the
+//! calls every function makes. Instructions are decoded by their arity,
+//! as listed in `genop.tab` of the Erlang compiler, operands in the
+//! compact term encoding.
+
+use anyhow::{Context, Result};
+use beam_file::chunk::CodeChunk;
+use fxhash::FxHashMap;
+
+/// A function of the module, with its name and the calls in its body.
+/// Names are indices into the atom table, starting at 1 like the code
+/// refers to them.
+#[derive(Debug)]
+pub struct Function {
+    pub name: u32,
+    pub arity: u32,
+    pub calls: Vec<Call>,
+    /// Atoms the body refers to, like ones passed as arguments, by
+    /// index into the atom table
+    pub atoms: Vec<u32>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Call {
+    /// Call to the function defining the label
+    Local(u32),
+    /// Call of an entry of the import table
+    External(u32),
+    /// Creation of the fun at an index of the fun table
+    Fun(u32),
+}
+
+/// The code's functions, and the index of the function defining every
+/// label
+#[derive(Debug)]
+pub struct Code {
+    pub functions: Vec<Function>,
+    pub labels: FxHashMap<u32, usize>,
+}
+
+impl Code {
+    /// The function defining the label, as a local call refers to it
+    pub fn function_at(&self, label: u32) -> Option<&Function> {
+        self.labels.get(&label).map(|&idx| &self.functions[idx])
+    }
+}
+
+/// Operand counts of the generic instructions, by opcode
+const ARITIES: &[u8] = &[
+    0, // unused
+    1, 3, 0, 2, 3, 2, 2, 3, 2, 4, // 1-10: label .. bif1
+    5, 2, 3, 2, 3, 2, 1, 1, 0, 0, // 11-20: bif2 .. send
+    0, 0, 2, 1, 1, 2, 4, 4, 4, 4, // 21-30: remove_message .. m_div
+    4, 4, 4, 4, 4, 4, 4, 3, 3, 3, // 31-40: int_div .. is_ge
+    3, 3, 3, 3, 2, 2, 2, 2, 2, 2, // 41-50: is_eq .. is_reference
+    2, 2, 2, 2, 2, 2, 2, 3, 3, 3, // 51-60: is_port .. select_tuple_arity
+    1, 2, 1, 2, 3, 3, 3, 3, 3, 2, // 61-70: jump .. put_tuple
+    1, 1, 0, 1, 1, 3, 2, 2, 2, 5, // 71-80: put .. bs_get_integer
+    5, 5, 4, 2, 1, 1, 2, 2, 5, 5, // 81-90: bs_get_float .. bs_put_binary
+    5, 2, 1, 0, 1, 2, 2, 4, 4, 4, // 91-100: bs_put_float .. fmul
+    4, 3, 1, 2, 1, 1, 1, 2, 6, 3, // 101-110: fdiv .. bs_bits_to_bytes
+    5, 1, 2, 2, 3, 5, 7, 7, 7, 5, // 111-120: bs_add .. bs_skip_bits2
+    3, 2, 2, 5, 6, 2, 2, 2, 2, 1, // 121-130: bs_test_tail2 .. bs_context_to_binary
+    3, 4, 0, 8, 6, 2, 6, 5, 4, 5, // 131-140: bs_test_unit .. bs_get_utf16
+    4, 5, 4, 3, 3, 3, 3, 3, 0, 1, // 141-150: bs_skip_utf16 .. recv_mark
+    1, 7, 1, 5, 5, 2, 3, 3, 4, 0, // 151-160: recv_set .. build_stacktrace
+    0, 2, 2, 2, 3, 4, 3, 2, 2, 4, // 161-170: raw_raise .. bs_start_match4
+    3, 1, 2, 1, 1, 1, 6, 3, 0, 1, // 171-180: make_fun3 .. badrecord
+    5, 3, 2, 4, // 181-184: update_record .. debug_line
+];
+
+const LABEL: u8 = 1;
+const FUNC_INFO: u8 = 2;
+const INT_CODE_END: u8 = 3;
+const CALL: u8 = 4;
+const CALL_LAST: u8 = 5;
+const CALL_ONLY: u8 = 6;
+const CALL_EXT: u8 = 7;
+const CALL_EXT_LAST: u8 = 8;
+const CALL_EXT_ONLY: u8 = 78;
+const MAKE_FUN2: u8 = 103;
+const MAKE_FUN3: u8 = 171;
+
+/// Size of the header fields `CodeChunk` reads before the code
+const HEADER_SIZE: u32 = 16;
+
+pub fn disassemble(chunk: &CodeChunk) -> Result<Code> {
+    // Newer compilers may add header fields, which come before the code
+    let skip = chunk.info_size.saturating_sub(HEADER_SIZE) as usize;
+    let mut reader = Reader {
+        bytes: chunk.bytecode.get(skip..).unwrap_or_default(),
+        pos: 0,
+    };
+    let mut code = Code {
+        functions: vec![],
+        labels: FxHashMap::default(),
+    };
+
+    while let Some(opcode) = reader.byte() {
+        let arity = *ARITIES
+            .get(opcode as usize)
+            .filter(|_| opcode != 0)
+            .with_context(|| format!("unknown opcode: {}", opcode))?;
+        let operands = (0..arity)
+            .map(|_| reader.operand())
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("decoding operands of opcode {}", opcode))?;
+
+        let call = match (opcode, &operands[..]) {
+            (INT_CODE_END, _) => break,
+            // The label before func_info is still taken for the previous
+            // function, but only the ones after it are called
+            (LABEL, [Operand::Literal(label)]) => {
+                if let Some(idx) = code.functions.len().checked_sub(1) {
+                    code.labels.insert(*label as u32, idx);
+                }
+                None
+            }
+            (FUNC_INFO, [_, Operand::Atom(name), Operand::Literal(arity)]) => {
+                code.functions.push(Function {
+                    name: *name,
+                    arity: *arity as u32,
+                    calls: vec![],
+                    atoms: vec![],
+                });
+                None
+            }
+            (CALL | CALL_LAST | CALL_ONLY, [_, Operand::Label(label), ..]) => {
+                Some(Call::Local(*label))
+            }
+            (CALL_EXT | CALL_EXT_LAST | CALL_EXT_ONLY, [_, Operand::Literal(import), ..]) => {
+                Some(Call::External(*import as u32))
+            }
+            (MAKE_FUN2, [Operand::Literal(fun)]) | (MAKE_FUN3, [Operand::Literal(fun), ..]) => {
+                Some(Call::Fun(*fun as u32))
+            }
+            _ => None,
+        };
+        let function = match code.functions.last_mut() {
+            Some(function) if opcode != FUNC_INFO => function,
+            _ => continue,
+        };
+        if let Some(call) = call {
+            if !function.calls.contains(&call) {
+                function.calls.push(call);
+            }
+        }
+        for operand in &operands {
+            if let Operand::Atom(atom) = *operand {
+                if atom != 0 && !function.atoms.contains(&atom) {
+                    function.atoms.push(atom);
+                }
+            }
+        }
+    }
+    Ok(code)
+}
+
+#[derive(Debug)]
+enum Operand {
+    Literal(u64),
+    Atom(u32),
+    Label(u32),
+    Other,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        let first = self.byte().context("unexpected end of code")?;
+        match first & 0b111 {
+            0 => Ok(Operand::Literal(self.value(first)?)),
+            2 => Ok(Operand::Atom(self.value(first)? as u32)),
+            5 => Ok(Operand::Label(self.value(first)? as u32)),
+            7 => self.extended(first),
+            // Integers, registers and characters
+            _ => {
+                self.value(first)?;
+                Ok(Operand::Other)
+            }
+        }
+    }
+
+    fn extended(&mut self, first: u8) -> Result<Operand> {
+        match first >> 4 {
+            // Float, written out as 8 bytes
+            0 => {
+                self.pos += 8;
+            }
+            // List, allocation list: a length and that many operands or
+            // pairs of them
+            1 | 3 => {
+                let len = self.untagged()?;
+                let count = if first >> 4 == 1 { len } else { len * 2 };
+                for _ in 0..count {
+                    self.operand()?;
+                }
+            }
+            // Floating point register, literal table index
+            2 | 4 => {
+                self.operand()?;
+            }
+            // Register with type information
+            5 => {
+                self.operand()?;
+                self.operand()?;
+            }
+            tag => anyhow::bail!("unknown extended operand tag: {}", tag),
+        }
+        Ok(Operand::Other)
+    }
+
+    /// An operand that is a plain number, whatever its tag
+    fn untagged(&mut self) -> Result<u64> {
+        let first = self.byte().context("unexpected end of code")?;
+        self.value(first)
+    }
+
+    /// The value following the tag in `first`, possibly in further bytes
+    fn value(&mut self, first: u8) -> Result<u64> {
+        if first & 0b1000 == 0 {
+            return Ok((first >> 4) as u64);
+        }
+        if first & 0b1_0000 == 0 {
+            let next = self.byte().context("unexpected end of code")?;
+            return Ok((((first & 0b1110_0000) as u64) << 3) | next as u64);
+        }
+        let mut len = (first >> 5) as usize + 2;
+        if len == 9 {
+            len = self.untagged()? as usize + 9;
+        }
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .context("unexpected end of code")?;
+        self.pos += len;
+        // Only small values are of interest, big ones are truncated
+        Ok(bytes
+            .iter()
+            .fold(0u64, |value, &byte| (value << 8) | byte as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVE: u8 = 64;
+
+    /// An operand in the compact term encoding, tagged `u` (0), `a` (2),
+    /// `x` (3) or `f` (5)
+    fn operand(tag: u8, value: u16) -> Vec<u8> {
+        if value < 16 {
+            vec![(value as u8) << 4 | tag]
+        } else {
+            vec![
+                ((value >> 3) as u8 & 0b1110_0000) | 0b1000 | tag,
+                value as u8,
+            ]
+        }
+    }
+
+    fn u(value: u16) -> Vec<u8> {
+        operand(0, value)
+    }
+
+    fn a(value: u16) -> Vec<u8> {
+        operand(2, value)
+    }
+
+    fn x(value: u16) -> Vec<u8> {
+        operand(3, value)
+    }
+
+    fn f(value: u16) -> Vec<u8> {
+        operand(5, value)
+    }
+
+    fn instruction(opcode: u8, operands: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = vec![opcode];
+        bytes.extend(operands.concat());
+        bytes
+    }
+
+    fn chunk(instructions: &[Vec<u8>]) -> CodeChunk {
+        CodeChunk {
+            info_size: HEADER_SIZE,
+            version: 0,
+            opcode_max: MAKE_FUN3 as u32,
+            label_count: 0,
+            function_count: 0,
+            bytecode: instructions.concat(),
+        }
+    }
+
+    #[test]
+    fn finds_functions_and_calls() {
+        // An empty list operand, `{list, []}`
+        let empty_list = vec![0b0001_0111, 0];
+        let code = disassemble(&chunk(&[
+            instruction(LABEL, &[u(1)]),
+            instruction(FUNC_INFO, &[a(1), a(2), u(0)]),
+            instruction(LABEL, &[u(2)]),
+            instruction(CALL, &[u(0), f(4)]),
+            instruction(CALL_EXT, &[u(1), u(0)]),
+            instruction(MAKE_FUN3, &[u(0), x(0), empty_list]),
+            instruction(CALL_EXT_ONLY, &[u(1), u(0)]),
+            instruction(LABEL, &[u(3)]),
+            instruction(FUNC_INFO, &[a(1), a(3), u(1)]),
+            instruction(LABEL, &[u(4)]),
+            instruction(MOVE, &[a(5), x(0)]),
+            instruction(CALL_LAST, &[u(1), f(2), u(0)]),
+            instruction(CALL_EXT, &[u(1), u(300)]),
+            instruction(INT_CODE_END, &[]),
+        ]))
+        .unwrap();
+
+        assert_eq!(code.functions.len(), 2);
+        let first = &code.functions[0];
+        assert_eq!((first.name, first.arity), (2, 0));
+        assert_eq!(
+            first.calls,
+            [Call::Local(4), Call::External(0), Call::Fun(0)]
+        );
+        assert!(first.atoms.is_empty());
+        let second = &code.functions[1];
+        assert_eq!((second.name, second.arity), (3, 1));
+        assert_eq!(second.calls, [Call::Local(2), Call::External(300)]);
+        assert_eq!(second.atoms, [5]);
+
+        let mut labels: Vec<_> = code.labels.into_iter().collect();
+        labels.sort();
+        assert_eq!(labels, [(2, 0), (3, 0), (4, 1)]);
+    }
+
+    #[test]
+    fn truncated_operands() {
+        let missing_operand = chunk(&[instruction(CALL_EXT, &[u(1)])]);
+        let err = disassemble(&missing_operand).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "decoding operands of opcode 7: unexpected end of code"
+        );
+
+        // A value continuing into a byte that isn't there
+        let missing_byte = chunk(&[vec![LABEL, 0b1000]]);
+        assert!(disassemble(&missing_byte).is_err());
+
+        let unknown = chunk(&[vec![0]]);
+        assert_eq!(
+            disassemble(&unknown).unwrap_err().to_string(),
+            "unknown opcode: 0"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Finding lib paths when none are given explicitly.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Lib directories from `ERL_LIBS`, separated like `PATH`. Like the code
+/// server does at startup, directories that don't exist are skipped.
+pub fn erl_libs() -> Vec<PathBuf> {
+    env::var_os("ERL_LIBS")
+        .map(|libs| {
+            env::split_paths(&libs)
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Lib paths and own applications of a build tool project
+#[derive(Debug, Default)]
+pub struct Project {
+    pub lib_paths: Vec<PathBuf>,
+    /// The project's applications, as opposed to its dependencies. Empty
+    /// for a release, where every application is analyzed.
+    pub apps: Vec<String>,
+}
+
+/// The project in `root`, trying rebar3, Mix and then a release layout.
+/// `profile` is the rebar3 profile or Mix environment, defaulting to
+/// `default` and `$MIX_ENV` or `dev` respectively.
+pub fn project(root: &Path, profile: Option<&str>) -> Option<Project> {
+    rebar3(root, profile.unwrap_or("default"))
+        .or_else(|| {
+            let env = env::var("MIX_ENV").ok();
+            mix(root, profile.or(env.as_deref()).unwrap_or("dev"))
+        })
+        .or_else(|| release(root))
+}
+
+/// A rebar3 project rooted at `root`, with its dependencies and
+/// applications in `_build/<profile>/lib`. Own applications are the ones
+/// with an `.app.src` in `src` or in `apps/*/src`.
+pub fn rebar3(root: &Path, profile: &str) -> Option<Project> {
+    if !root.join("rebar.config").is_file() {
+        return None;
+    }
+
+    let mut app_dirs = vec![root.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(root.join("apps")) {
+        app_dirs.extend(entries.filter_map(|entry| Some(entry.ok()?.path())));
+    }
+    let mut apps: Vec<_> = app_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir.join("src")).ok())
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            Some(name.to_str()?.strip_suffix(".app.src")?.to_string())
+        })
+        .collect();
+    apps.sort_unstable();
+
+    Some(Project {
+        lib_paths: vec![root.join("_build").join(profile).join("lib")],
+        apps,
+    })
+}
+
+/// A Mix project rooted at `root`, built into `_build/<env>/lib`. Own
+/// applications are the project's, or every one in `apps` for an umbrella.
+pub fn mix(root: &Path, env: &str) -> Option<Project> {
+    let mix_exs = root.join("mix.exs");
+    if !mix_exs.is_file() {
+        return None;
+    }
+
+    // Umbrella projects have no application of their own
+    let mut apps: Vec<_> = match mix_app(&mix_exs) {
+        Some(app) => vec![app],
+        None => fs::read_dir(root.join("apps"))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| mix_app(&entry.ok()?.path().join("mix.exs")))
+            .collect(),
+    };
+    apps.sort_unstable();
+
+    Some(Project {
+        lib_paths: vec![root.join("_build").join(env).join("lib")],
+        apps,
+    })
+}
+
+/// The application name from the `project` keyword list of a `mix.exs`
+fn mix_app(path: &Path) -> Option<String> {
+    lazy_static! {
+        static ref APP: Regex = Regex::new(r"\bapp:\s*:([a-z_][0-9a-zA-Z_]*)").unwrap();
+    }
+    let contents = fs::read_to_string(path).ok()?;
+    Some(APP.captures(&contents)?[1].to_string())
+}
+
+/// An assembled release, as made by `mix release` or `rebar3 release`, with
+/// versioned application directories in `lib/<app>-<vsn>`
+pub fn release(root: &Path) -> Option<Project> {
+    if !root.join("releases").is_dir() || !root.join("lib").is_dir() {
+        return None;
+    }
+
+    Some(Project {
+        lib_paths: vec![root.join("lib")],
+        apps: vec![],
+    })
+}
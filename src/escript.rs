@@ -0,0 +1,198 @@
+//! Escripts: a header of up to three lines (`#!` line, comment and `%%!`
+//! emulator arguments) followed by a zip archive or a single compiled
+//! module. The bundled `.beam` and `.app` files are read into memory and
+//! loaded like applications on a lib path.
+
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    ffi::OsStr,
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+pub struct Escript {
+    pub path: PathBuf,
+    /// Bundled `.beam` and `.app` files, by their path in the archive
+    /// joined to `path`
+    pub files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+/// Applications the escript bundles, see `Escript::apps`
+pub struct BundledApp {
+    /// From the `.app` file, or else the directory or the escript
+    pub name: String,
+    pub dir: PathBuf,
+    pub app_path: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+impl fmt::Debug for Escript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Escript")
+            .field("path", &self.path)
+            .field("files", &self.files.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Escript {
+    pub fn load(path: &Path) -> Result<Escript> {
+        let bytes =
+            fs::read(path).with_context(|| format!("reading escript: {}", path.display()))?;
+        let body = &bytes[header_len(&bytes)..];
+        let files = if body.starts_with(b"PK\x03\x04") {
+            read_zip(body)
+                .with_context(|| format!("reading the archive of escript: {}", path.display()))?
+                .into_iter()
+                .filter(|(name, _)| name.ends_with(".beam") || name.ends_with(".app"))
+                .map(|(name, data)| (path.join(name), data))
+                .collect()
+        } else if body.starts_with(b"FOR1") {
+            let stem = path
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .unwrap_or("escript");
+            let mut files = BTreeMap::new();
+            files.insert(path.join(format!("{}.beam", stem)), body.to_vec());
+            files
+        } else {
+            anyhow::bail!(
+                "escript {} is Erlang source, only archives and compiled modules can be analyzed",
+                path.display()
+            );
+        };
+        Ok(Escript {
+            path: path.to_path_buf(),
+            files,
+        })
+    }
+
+    /// The bundled files grouped into applications by directory, like
+    /// `Loader::read_files` does. Modules at the top of the archive are
+    /// named after the escript.
+    pub fn apps(&self) -> Vec<BundledApp> {
+        let mut dirs = BTreeMap::<&Path, Vec<PathBuf>>::new();
+        for file in self.files.keys() {
+            let dir = file.parent().unwrap_or(Path::new(""));
+            dirs.entry(dir).or_default().push(file.clone());
+        }
+        dirs.into_iter()
+            .filter(|(_, files)| files.iter().any(|file| has_extension(file, "beam")))
+            .map(|(dir, files)| {
+                let app_path = match dir.file_name() {
+                    Some(name) if name == "ebin" && dir != self.path => dir.parent().unwrap_or(dir),
+                    _ => dir,
+                };
+                let app_file = files
+                    .iter()
+                    .find(|file| has_extension(file, "app"))
+                    .and_then(|file| file.file_stem());
+                let name = match app_file {
+                    Some(name) => name,
+                    None if app_path == self.path => self.path.file_stem().unwrap_or_default(),
+                    None => app_path.file_name().unwrap_or_default(),
+                };
+                BundledApp {
+                    name: name.to_string_lossy().into_owned(),
+                    dir: dir.to_path_buf(),
+                    app_path: app_path.to_path_buf(),
+                    files,
+                }
+            })
+            .collect()
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension() == Some(OsStr::new(extension))
+}
+
+/// Length of the header: the `#!` line, then a comment and `%%!` line
+fn header_len(bytes: &[u8]) -> usize {
+    let mut len = 0;
+    for line in 0..3 {
+        let rest = &bytes[len..];
+        let header = match line {
+            0 => rest.starts_with(b"#!"),
+            _ => rest.starts_with(b"%"),
+        };
+        if !header {
+            break;
+        }
+        len += rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(rest.len(), |end| end + 1);
+    }
+    len
+}
+
+const END_OF_CENTRAL_DIRECTORY: &[u8] = b"PK\x05\x06";
+const CENTRAL_DIRECTORY_ENTRY: &[u8] = b"PK\x01\x02";
+const LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+
+/// Files of a zip archive, stored or deflated
+fn read_zip(zip: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let u16_at = |offset: usize| -> Result<usize> {
+        let bytes = zip.get(offset..offset + 2).context("truncated archive")?;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    let u32_at = |offset: usize| -> Result<usize> {
+        let bytes = zip.get(offset..offset + 4).context("truncated archive")?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+
+    // The end record is last, followed only by a comment
+    let end = zip
+        .windows(4)
+        .rposition(|window| window == END_OF_CENTRAL_DIRECTORY)
+        .context("missing the end of the central directory")?;
+    let count = u16_at(end + 10)?;
+    let mut entry = u32_at(end + 16)?;
+
+    let mut files = vec![];
+    for _ in 0..count {
+        if zip.get(entry..entry + 4) != Some(CENTRAL_DIRECTORY_ENTRY) {
+            anyhow::bail!("invalid central directory entry at {}", entry);
+        }
+        let method = u16_at(entry + 10)?;
+        let compressed_size = u32_at(entry + 20)?;
+        let name_len = u16_at(entry + 28)?;
+        let extra_len = u16_at(entry + 30)?;
+        let comment_len = u16_at(entry + 32)?;
+        let local = u32_at(entry + 42)?;
+        let name = zip
+            .get(entry + 46..entry + 46 + name_len)
+            .context("truncated archive")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        entry += 46 + name_len + extra_len + comment_len;
+        if name.ends_with('/') {
+            continue;
+        }
+
+        if zip.get(local..local + 4) != Some(LOCAL_HEADER) {
+            anyhow::bail!("invalid local header of {}", name);
+        }
+        let start = local + 30 + u16_at(local + 26)? + u16_at(local + 28)?;
+        let data = zip
+            .get(start..start + compressed_size)
+            .with_context(|| format!("truncated data of {}", name))?;
+        let data = match method {
+            0 => data.to_vec(),
+            8 => {
+                let mut inflated = vec![];
+                libflate::deflate::Decoder::new(data)
+                    .read_to_end(&mut inflated)
+                    .with_context(|| format!("inflating {}", name))?;
+                inflated
+            }
+            _ => anyhow::bail!("unsupported compression method {} of {}", method, name),
+        };
+        files.push((name, data));
+    }
+    Ok(files)
+}
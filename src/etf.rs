@@ -0,0 +1,121 @@
+//! Helpers for picking apart terms decoded from the External Term Format
+//! chunks of BEAM files (CInf, Attr, LitT, ...).
+
+use eetf::Term;
+
+pub fn decode(bytes: &[u8]) -> Option<Term> {
+    Term::decode(bytes).ok()
+}
+
+pub fn atom(term: &Term) -> Option<&str> {
+    match term {
+        Term::Atom(atom) => Some(&atom.name),
+        _ => None,
+    }
+}
+
+pub fn int(term: &Term) -> Option<i64> {
+    match term {
+        Term::FixInteger(int) => Some(int.value.into()),
+        _ => None,
+    }
+}
+
+pub fn tuple(term: &Term) -> Option<&[Term]> {
+    match term {
+        Term::Tuple(tuple) => Some(&tuple.elements),
+        _ => None,
+    }
+}
+
+pub fn list(term: &Term) -> Option<&[Term]> {
+    match term {
+        Term::List(list) => Some(&list.elements),
+        _ => None,
+    }
+}
+
+/// Strings are lists of code points, encoded compactly as bytes when
+/// every one of them fits
+pub fn string(term: &Term) -> Option<String> {
+    match term {
+        Term::ByteList(list) => Some(list.bytes.iter().map(|&byte| byte as char).collect()),
+        Term::List(list) => list
+            .elements
+            .iter()
+            .map(|elem| char::from_u32(int(elem)? as u32))
+            .collect(),
+        Term::Binary(binary) => String::from_utf8(binary.bytes.clone()).ok(),
+        _ => None,
+    }
+}
+
+/// Collects every string nested anywhere in `term`, including binaries
+pub fn strings(term: &Term, out: &mut Vec<String>) {
+    if let Some(string) = string(term).filter(|string| !string.is_empty()) {
+        out.push(string);
+        return;
+    }
+    match term {
+        Term::List(list) => list.elements.iter().for_each(|elem| strings(elem, out)),
+        Term::ImproperList(list) => list.elements.iter().for_each(|elem| strings(elem, out)),
+        Term::Tuple(tuple) => tuple.elements.iter().for_each(|elem| strings(elem, out)),
+        Term::Map(map) => map.map.iter().for_each(|(key, value)| {
+            strings(key, out);
+            strings(value, out);
+        }),
+        _ => {}
+    }
+}
+
+/// Collects the calls nested anywhere in `term`: `{Module, Function, Args}`
+/// tuples, as passed to `apply/3`, and `fun Module:Function/Arity`
+/// references, flagged with `true`
+pub fn calls<'a>(term: &'a Term, out: &mut Vec<(&'a str, &'a str, u32, bool)>) {
+    match term {
+        Term::ExternalFun(fun) => {
+            out.push((&fun.module.name, &fun.function.name, fun.arity.into(), true))
+        }
+        Term::Tuple(tuple) => {
+            if let [module, function, args] = &tuple.elements[..] {
+                let arity = match args {
+                    Term::List(list) => Some(list.elements.len()),
+                    Term::ByteList(list) => Some(list.bytes.len()),
+                    _ => None,
+                };
+                if let (Some(module), Some(function), Some(arity)) =
+                    (atom(module), atom(function), arity)
+                {
+                    out.push((module, function, arity as u32, false));
+                }
+            }
+            tuple.elements.iter().for_each(|elem| calls(elem, out))
+        }
+        Term::List(list) => list.elements.iter().for_each(|elem| calls(elem, out)),
+        Term::ImproperList(list) => list.elements.iter().for_each(|elem| calls(elem, out)),
+        Term::Map(map) => map.map.iter().for_each(|(key, value)| {
+            calls(key, out);
+            calls(value, out);
+        }),
+        _ => {}
+    }
+}
+
+/// Looks up the atom `key` in a map
+pub fn map_get<'a>(term: &'a Term, key: &str) -> Option<&'a Term> {
+    match term {
+        Term::Map(map) => map
+            .map
+            .iter()
+            .find_map(|(k, value)| (atom(k) == Some(key)).then_some(value)),
+        _ => None,
+    }
+}
+
+/// Looks up `key` in a proplist of `{Key, Value}` tuples
+pub fn proplist_get<'a>(term: &'a Term, key: &str) -> Option<&'a Term> {
+    list(term)?.iter().find_map(|elem| match tuple(elem)? {
+        [k, value] if atom(k) == Some(key) => Some(value),
+        _ => None,
+    })
+}
@@ -0,0 +1,155 @@
+use std::{fmt::Write as _, io::Write, str::FromStr};
+
+use anyhow::Result;
+use fxhash::{FxHashMap, FxHashSet};
+use petgraph::visit::{Dfs, Walker};
+
+use crate::types::{AppDeps, Atom, Interner};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum GraphFormat {
+    Mermaid,
+    Dot,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<GraphFormat> {
+        match s {
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            "dot" => Ok(GraphFormat::Dot),
+            _ => anyhow::bail!(
+                "unknown graph format: {} (expected one of: mermaid, dot)",
+                s
+            ),
+        }
+    }
+}
+
+/// Writes the application dependency graph. When `roots` are given, only
+/// the applications reachable from them are included.
+pub fn write_app_graph(
+    format: GraphFormat,
+    app_deps: &AppDeps,
+    roots: &[Atom],
+    interner: &Interner,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let included: Option<FxHashSet<Atom>> = if roots.is_empty() {
+        None
+    } else {
+        Some(
+            roots
+                .iter()
+                .flat_map(|&root| Dfs::new(app_deps, root).iter(app_deps))
+                .collect(),
+        )
+    };
+    let included = |app: &Atom| included.as_ref().is_none_or(|apps| apps.contains(app));
+
+    let mut nodes: Vec<_> = app_deps
+        .nodes()
+        .filter(included)
+        .map(|app| app.resolve(interner).unwrap())
+        .collect();
+    nodes.sort_unstable();
+    let mut edges: Vec<_> = app_deps
+        .all_edges()
+        .filter(|(from, _, _)| included(from))
+        .map(|(from, to, _)| {
+            (
+                from.resolve(interner).unwrap(),
+                to.resolve(interner).unwrap(),
+            )
+        })
+        .collect();
+    edges.sort_unstable();
+
+    match format {
+        GraphFormat::Mermaid => {
+            let mut graph = Mermaid::new("TD");
+            for node in nodes {
+                graph.node(node);
+            }
+            for (from, to) in edges {
+                graph.edge(from, to);
+            }
+            writeln!(out, "```mermaid\n{}```", graph.finish())?;
+        }
+        GraphFormat::Dot => {
+            writeln!(out, "digraph apps {{")?;
+            for node in nodes {
+                writeln!(out, "    {};", quote(node))?;
+            }
+            for (from, to) in edges {
+                writeln!(out, "    {} -> {};", quote(from), quote(to))?;
+            }
+            writeln!(out, "}}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Incrementally renders a Mermaid flowchart. Atoms can contain characters
+/// Mermaid doesn't allow in node ids, so nodes get synthetic ids and the
+/// name is only used as a label.
+pub struct Mermaid {
+    out: String,
+    ids: FxHashMap<String, usize>,
+    edges: usize,
+}
+
+impl Mermaid {
+    pub fn new(direction: &str) -> Mermaid {
+        Mermaid {
+            out: format!("graph {}\n", direction),
+            ids: FxHashMap::default(),
+            edges: 0,
+        }
+    }
+
+    pub fn node(&mut self, name: &str) -> String {
+        let next = self.ids.len();
+        let mut new = false;
+        let id = *self.ids.entry(name.to_string()).or_insert_with(|| {
+            new = true;
+            next
+        });
+        if new {
+            writeln!(self.out, "    n{}[\"{}\"]", id, escape(name)).unwrap();
+        }
+        format!("n{}", id)
+    }
+
+    /// Returns the index of the edge, for use with `linkStyle`
+    pub fn edge(&mut self, from: &str, to: &str) -> usize {
+        let from = self.node(from);
+        let to = self.node(to);
+        writeln!(self.out, "    {} --> {}", from, to).unwrap();
+        self.edges += 1;
+        self.edges - 1
+    }
+
+    pub fn class(&mut self, name: &str, class: &str) {
+        let id = self.node(name);
+        writeln!(self.out, "    class {} {}", id, class).unwrap();
+    }
+
+    pub fn line(&mut self, line: &str) {
+        writeln!(self.out, "    {}", line).unwrap();
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn escape(name: &str) -> String {
+    name.replace('"', "#quot;")
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,187 @@
+//! Suppressing individual findings. Every field of an ignore that is set
+//! has to match for a finding to be ignored.
+//!
+//! Besides the `[[ignore]]` tables of `xref.toml`, ignores are read from an
+//! ignore file with one finding per line, `*` matching anything:
+//!
+//! ```text
+//! # <check> <module> [<target>] [# reason]
+//! undefined_function legacy_dispatch jsx:decode/1  # called through apply
+//! undefined_module * eunit
+//! ```
+//!
+//! Modules can also suppress their own findings with an `-ignore_xref`
+//! attribute, which the analyzer honors before these apply.
+
+use std::{fmt, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    analyzer::{AnalysisResult, Analyzer, CHECKS},
+    timestamp::Timestamp,
+    types::Atom,
+};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ignore {
+    pub check: Option<String>,
+    pub app: Option<String>,
+    /// Module the finding is reported for, i.e. the caller
+    pub module: Option<String>,
+    /// What the finding points at, see `AnalysisResult::target`. A module
+    /// name also matches all of its functions.
+    pub target: Option<String>,
+    /// Not used for matching, documents why the finding is fine
+    pub reason: Option<String>,
+    /// From this day on the ignore no longer applies
+    pub expires: Option<Timestamp>,
+}
+
+impl Ignore {
+    pub fn matches(&self, subject: Atom, result: &AnalysisResult, analyzer: &Analyzer) -> bool {
+        let interner = analyzer.interner();
+
+        self.check
+            .as_ref()
+            .is_none_or(|check| check == result.check_name())
+            && self.app.as_ref().is_none_or(|app| {
+                analyzer
+                    .result_app(subject, result)
+                    .and_then(|result_app| result_app.resolve(interner))
+                    == Some(app)
+            })
+            && self
+                .module
+                .as_ref()
+                .is_none_or(|module| subject.resolve(interner) == Some(module))
+            && self.target.as_ref().is_none_or(|target| {
+                let result_target = result.target(interner);
+                result_target == *target
+                    || result_target
+                        .strip_prefix(target.as_str())
+                        .is_some_and(|rest| rest.starts_with(':'))
+            })
+    }
+
+    pub fn is_expired(&self, today: Timestamp) -> bool {
+        self.expires.is_some_and(|expires| expires <= today)
+    }
+}
+
+impl fmt::Display for Ignore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [
+            ("check", &self.check),
+            ("app", &self.app),
+            ("module", &self.module),
+            ("target", &self.target),
+        ];
+        let fields: Vec<_> = fields
+            .iter()
+            .filter_map(|(name, value)| Some(format!("{} = {}", name, value.as_ref()?)))
+            .collect();
+        if fields.is_empty() {
+            write!(f, "{{}}")
+        } else {
+            write!(f, "{{ {} }}", fields.join(", "))
+        }
+    }
+}
+
+pub const FILE_NAME: &str = ".xrefignore";
+
+pub fn load_file(path: &Path) -> Result<Vec<Ignore>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("reading ignore file: {}", path.display()))?;
+
+    text.lines()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let (line, reason) = match line.split_once('#') {
+                Some((line, reason)) => (line, Some(reason.trim())),
+                None => (line, None),
+            };
+            let fields: Vec<_> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                return None;
+            }
+            Some(
+                parse_line(&fields, reason)
+                    .with_context(|| format!("{}:{}", path.display(), idx + 1)),
+            )
+        })
+        .collect()
+}
+
+fn parse_line(fields: &[&str], reason: Option<&str>) -> Result<Ignore> {
+    let field = |idx: usize| {
+        fields
+            .get(idx)
+            .filter(|&&field| field != "*")
+            .map(|field| field.to_string())
+    };
+
+    if fields.len() < 2 || fields.len() > 3 {
+        anyhow::bail!("expected: <check> <module> [<target>]");
+    }
+    if fields[0] != "*" && !CHECKS.contains(&fields[0]) {
+        anyhow::bail!(
+            "unknown check: {} (expected one of: {})",
+            fields[0],
+            CHECKS.join(", ")
+        );
+    }
+
+    Ok(Ignore {
+        check: field(0),
+        module: field(1),
+        target: field(2),
+        reason: reason.filter(|reason| !reason.is_empty()).map(String::from),
+        ..Ignore::default()
+    })
+}
+
+/// Ignores that need attention after `apply`
+#[derive(Debug, Default)]
+pub struct Outcome<'a> {
+    /// Didn't match any finding, so can likely be removed
+    pub stale: Vec<&'a Ignore>,
+    /// Past their expiry date, their findings are reported again
+    pub expired: Vec<&'a Ignore>,
+}
+
+/// Drops the findings matched by any of the ignores that are not expired
+pub fn apply<'a>(
+    ignores: &'a [Ignore],
+    results: &mut Vec<(Atom, AnalysisResult)>,
+    analyzer: &Analyzer,
+    today: Timestamp,
+) -> Outcome<'a> {
+    let (expired, active): (Vec<_>, Vec<_>) =
+        ignores.iter().partition(|ignore| ignore.is_expired(today));
+    let mut used = vec![false; active.len()];
+
+    results.retain(|(subject, result)| {
+        let mut ignored = false;
+        for (ignore, used) in active.iter().zip(used.iter_mut()) {
+            if ignore.matches(*subject, result, analyzer) {
+                *used = true;
+                ignored = true;
+            }
+        }
+        !ignored
+    });
+
+    Outcome {
+        stale: active
+            .into_iter()
+            .zip(used)
+            .filter(|(_, used)| !used)
+            .map(|(ignore, _)| ignore)
+            .collect(),
+        expired,
+    }
+}
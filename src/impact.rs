@@ -0,0 +1,174 @@
+//! How a shared application is used across several releases, to plan
+//! breaking changes to it. Every release is loaded into its own workspace,
+//! so everything is compared by name.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::{analyzer::Analyzer, types::Atom};
+
+pub struct Matrix {
+    pub app: String,
+    pub releases: Vec<Release>,
+    /// Functions of the application, as `module:function/arity`
+    pub rows: Vec<Row>,
+}
+
+pub struct Release {
+    pub name: String,
+    /// `None` when the release doesn't include the application at all
+    pub version: Option<String>,
+}
+
+pub struct Row {
+    pub function: String,
+    pub cells: Vec<Cell>,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Cell {
+    /// Not part of the release's version of the application, nor called
+    Absent,
+    Exported,
+    Called,
+    /// Called, but not exported by the version in the release
+    Broken,
+}
+
+/// Builds the matrix of every exported or called function of `app` against
+/// the named releases
+pub fn impact(app: &str, releases: &[(String, Analyzer)]) -> Matrix {
+    let mut functions = BTreeMap::<String, Vec<Cell>>::new();
+    let mut columns = vec![];
+
+    for (idx, (name, analyzer)) in releases.iter().enumerate() {
+        let interner = analyzer.interner();
+        let app_atom = analyzer.app(app);
+
+        columns.push(Release {
+            name: name.clone(),
+            version: app_atom.and_then(|app| analyzer.app_version(app).map(String::from)),
+        });
+        let app_atom = match app_atom {
+            Some(app) => app,
+            None => continue,
+        };
+
+        let mut exported = BTreeSet::new();
+        for &module in &analyzer.app_modules()[&app_atom] {
+            for &(fun, arity) in &analyzer.module(module).unwrap().exports {
+                if fun.resolve(interner) != Some("module_info") {
+                    exported.insert(mfa(analyzer, module, fun, arity));
+                }
+            }
+        }
+        let called: BTreeSet<_> = analyzer
+            .calls_into(app_atom)
+            .into_iter()
+            .map(|(_, module, fun, arity)| mfa(analyzer, module, fun, arity))
+            .collect();
+
+        for function in exported.union(&called) {
+            let cell = match (exported.contains(function), called.contains(function)) {
+                (true, true) => Cell::Called,
+                (true, false) => Cell::Exported,
+                (false, _) => Cell::Broken,
+            };
+            functions
+                .entry(function.clone())
+                .or_insert_with(|| vec![Cell::Absent; releases.len()])[idx] = cell;
+        }
+    }
+
+    Matrix {
+        app: app.to_string(),
+        releases: columns,
+        rows: functions
+            .into_iter()
+            .map(|(function, cells)| Row { function, cells })
+            .collect(),
+    }
+}
+
+fn mfa(analyzer: &Analyzer, module: Atom, fun: Atom, arity: u32) -> String {
+    let interner = analyzer.interner();
+    format!(
+        "{}:{}/{}",
+        module.resolve(interner).unwrap(),
+        fun.resolve(interner).unwrap(),
+        arity
+    )
+}
+
+pub fn write_human(matrix: &Matrix, out: &mut dyn Write) -> Result<()> {
+    let headers: Vec<_> = matrix
+        .releases
+        .iter()
+        .map(|release| match &release.version {
+            Some(version) => format!("{} ({})", release.name, version),
+            None => format!("{} (-)", release.name),
+        })
+        .collect();
+    let first = matrix
+        .rows
+        .iter()
+        .map(|row| row.function.len())
+        .chain(Some(matrix.app.len()))
+        .max()
+        .unwrap_or(0);
+
+    write!(out, "{:width$}", matrix.app, width = first)?;
+    for header in &headers {
+        write!(out, "  {}", header)?;
+    }
+    writeln!(out)?;
+
+    for row in &matrix.rows {
+        let mut line = format!("{:width$}", row.function, width = first);
+        for (cell, header) in row.cells.iter().zip(&headers) {
+            let mark = match cell {
+                Cell::Absent => "",
+                Cell::Exported => ".",
+                Cell::Called => "x",
+                Cell::Broken => "!",
+            };
+            line.push_str(&format!("  {:width$}", mark, width = header.len()));
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    writeln!(
+        out,
+        "\nx: called, .: exported but not called, !: called but not exported"
+    )?;
+    Ok(())
+}
+
+pub fn write_json(matrix: &Matrix, out: &mut dyn Write) -> Result<()> {
+    let cell = |cell: &Cell| match cell {
+        Cell::Absent => "absent",
+        Cell::Exported => "exported",
+        Cell::Called => "called",
+        Cell::Broken => "broken",
+    };
+    let json = json!({
+        "app": matrix.app,
+        "releases": matrix.releases.iter().map(|release| json!({
+            "name": release.name,
+            "version": release.version,
+        })).collect::<Vec<_>>(),
+        "functions": matrix.rows.iter().map(|row| json!({
+            "function": row.function,
+            "releases": row.cells.iter().map(cell).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    });
+
+    serde_json::to_writer_pretty(&mut *out, &json)?;
+    writeln!(out)?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+mod abstract_code;
+pub mod analyzer;
+pub mod api;
+pub mod baseline;
+pub mod closure;
+pub mod compat;
+pub mod completions;
+pub mod config;
+pub mod diff;
+pub mod disasm;
+pub mod discover;
+pub mod escript;
+mod etf;
+pub mod graph;
+pub mod ignore;
+pub mod impact;
+pub mod loader;
+pub mod metrics;
+pub mod otp;
+pub mod output;
+pub mod pattern;
+pub mod progress;
+pub mod release;
+pub mod report;
+pub mod rules;
+mod source;
+pub mod start;
+pub mod suggest;
+mod term;
+pub mod timestamp;
+pub mod tree;
+pub mod types;
+pub mod watch;
+pub mod why;
@@ -1,32 +1,131 @@
 use std::{
+    collections::BTreeMap,
+    convert::TryInto,
     ffi::OsStr,
     fs,
+    hash::{Hash, Hasher},
+    io::Read,
     path::{Path, PathBuf},
     sync::Mutex,
+    time::SystemTime,
 };
 
 use anyhow::{Context, Result};
-use beam_file::{
-    chunk::{AtomChunk, ExpTChunk, ImpTChunk, StandardChunk},
-    StandardBeamFile,
+use beam_file::chunk::{
+    AtomChunk, Chunk, CodeChunk, ExpTChunk, FunTChunk, ImpTChunk, LitTChunk, LocTChunk,
+    StandardChunk,
 };
-use lazy_static::lazy_static;
+use eetf::Term;
+use fxhash::{FxHashMap, FxHashSet, FxHasher};
 use rayon::prelude::*;
-use regex::Regex;
 
-use crate::types::{AppDeps, AppModules, Atom, Exports, Imports, Interner, Modules};
+use crate::{
+    abstract_code, disasm,
+    escript::Escript,
+    etf,
+    pattern::Excludes,
+    progress::{Callback, Event},
+    release::Release,
+    source, term,
+    timestamp::Timestamp,
+    types::{
+        AppCallbacks, AppDeps, AppModules, AppPaths, AppVersions, Atom, Attributes, DeclaredDeps,
+        Deprecation, DynamicCall, Exports, FunctionCalls, FunctionDoc, IgnoreXref, Imports,
+        Interner, LoadChecks, Mfa, Module, Modules, RegisteredNames, ShadowedModules, Workspace,
+    },
+};
+
+/// Beam files compiled with the `compressed` option are gzipped
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
 
 pub struct Loader {
     interner: Mutex<Interner>,
     modules: Mutex<Modules>,
     app_modules: Mutex<AppModules>,
-    app_deps: Mutex<AppDeps>,
+    declared_deps: Mutex<DeclaredDeps>,
+    included_apps: Mutex<DeclaredDeps>,
+    app_paths: Mutex<AppPaths>,
+    app_versions: Mutex<AppVersions>,
+    app_callbacks: Mutex<AppCallbacks>,
+    /// Position of every application directory on the lib paths, earlier
+    /// ones shadow the modules of later ones like on the code path
+    app_order: Mutex<FxHashMap<PathBuf, usize>>,
+    shadowed: Mutex<ShadowedModules>,
+    shadowed_builds: Mutex<FxHashMap<PathBuf, u64>>,
+    listed_modules: Mutex<AppModules>,
+    optional_deps: Mutex<DeclaredDeps>,
+    runtime_deps: Mutex<DeclaredDeps>,
+    registered: Mutex<RegisteredNames>,
+    /// Protocols consolidated by Mix, replacing the compiled ones
+    consolidated: Mutex<Modules>,
+    test_lib_paths: Vec<PathBuf>,
+    /// Whether every copy of the application was on a test lib path
+    test_apps: Mutex<FxHashMap<Atom, bool>>,
+    progress: Option<Callback>,
+    excludes: Excludes,
+    excluded: Mutex<FxHashSet<Atom>>,
+    release: Option<Release>,
+    cache: Option<CachedModules>,
+    cached: Mutex<CachedModules>,
+    /// Files and chunks skipped as unreadable, see `warnings`
+    warnings: Mutex<Vec<String>>,
+}
+
+type CachedModules = FxHashMap<PathBuf, (SystemTime, Atom, Module)>;
+
+/// Parsed modules of a previous load, reused as long as their beam file
+/// isn't modified. Holds on to the interner, so that atoms in the cached
+/// modules stay valid.
+pub struct Cache {
+    interner: Interner,
+    modules: CachedModules,
+}
+
+impl Default for Cache {
+    fn default() -> Cache {
+        Cache {
+            interner: Interner::new(),
+            modules: CachedModules::default(),
+        }
+    }
 }
 
 struct App {
     pub name: Atom,
     pub deps: Vec<Atom>,
+    pub included: Vec<Atom>,
+    pub vsn: Option<String>,
+    pub callback: Option<Atom>,
     pub modules: Vec<Atom>,
+    pub listed_modules: Option<Vec<Atom>>,
+    pub optional_deps: Vec<Atom>,
+    pub runtime_deps: Option<Vec<Atom>>,
+    pub registered: Vec<Atom>,
+}
+
+/// What's extracted from an `.app` file
+#[derive(Default)]
+struct AppFile {
+    deps: Vec<Atom>,
+    /// `included_applications`
+    included: Vec<Atom>,
+    vsn: Option<String>,
+    /// The application callback module, from `{mod, {Module, Args}}`
+    callback: Option<Atom>,
+    /// The `modules` list
+    modules: Option<Vec<Atom>>,
+    /// `optional_applications`, also listed in `applications`
+    optional_deps: Vec<Atom>,
+    /// Names of the `"App-Vsn"` strings of `runtime_dependencies`
+    runtime_deps: Option<Vec<Atom>>,
+    /// The `registered` process names
+    registered: Vec<Atom>,
+}
+
+impl Default for Loader {
+    fn default() -> Loader {
+        Loader::new()
+    }
 }
 
 impl Loader {
@@ -35,14 +134,91 @@ impl Loader {
             interner: Mutex::new(Interner::new()),
             modules: Mutex::new(Modules::default()),
             app_modules: Mutex::new(AppModules::default()),
-            app_deps: Mutex::new(AppDeps::default()),
+            declared_deps: Mutex::new(DeclaredDeps::default()),
+            included_apps: Mutex::new(DeclaredDeps::default()),
+            app_paths: Mutex::new(AppPaths::default()),
+            app_versions: Mutex::new(AppVersions::default()),
+            app_callbacks: Mutex::new(AppCallbacks::default()),
+            app_order: Mutex::new(FxHashMap::default()),
+            shadowed: Mutex::new(ShadowedModules::default()),
+            shadowed_builds: Mutex::new(FxHashMap::default()),
+            listed_modules: Mutex::new(AppModules::default()),
+            optional_deps: Mutex::new(DeclaredDeps::default()),
+            runtime_deps: Mutex::new(DeclaredDeps::default()),
+            registered: Mutex::new(RegisteredNames::default()),
+            consolidated: Mutex::new(Modules::default()),
+            test_lib_paths: vec![],
+            test_apps: Mutex::new(FxHashMap::default()),
+            progress: None,
+            excludes: Excludes::default(),
+            excluded: Mutex::new(FxHashSet::default()),
+            release: None,
+            cache: None,
+            cached: Mutex::new(CachedModules::default()),
+            warnings: Mutex::new(vec![]),
+        }
+    }
+
+    /// Reuse the modules of a previous load, see `finish_cached`
+    pub fn with_cache(self, cache: Cache) -> Loader {
+        Loader {
+            interner: Mutex::new(cache.interner),
+            cache: Some(cache.modules),
+            ..self
+        }
+    }
+
+    /// Skip loading the matching applications and modules. Their names are
+    /// still recorded, so that references to them can be told apart from
+    /// references to code that doesn't exist.
+    pub fn with_excludes(self, excludes: Excludes) -> Loader {
+        Loader { excludes, ..self }
+    }
+
+    /// Load only the application versions shipped in the release, anything
+    /// else is skipped as if it wasn't in the lib paths at all
+    pub fn with_release(self, release: Release) -> Loader {
+        Loader {
+            release: Some(release),
+            ..self
+        }
+    }
+
+    /// Applications loaded only from these lib paths are test code. The
+    /// paths still have to be read with `read_libs`.
+    pub fn with_test_lib_paths(self, test_lib_paths: Vec<PathBuf>) -> Loader {
+        Loader {
+            test_lib_paths,
+            ..self
+        }
+    }
+
+    /// Report progress of loading through `callback`
+    pub fn with_progress(self, callback: Callback) -> Loader {
+        Loader {
+            progress: Some(callback),
+            ..self
         }
     }
 
+    /// Loads every application in the lib directories. Paths can be globs,
+    /// like `_build/*/lib`. A path naming an `ebin` directory, such as
+    /// `deps/*/ebin`, loads just that application. Applications that weren't
+    /// built, with an `.app.src` file but no `.beam` files, are read from
+    /// their sources.
     pub fn read_libs(&self, paths: &[PathBuf]) -> Result<()> {
-        let erts = Atom(self.interner.lock().unwrap().get_or_intern_static("erts"));
+        let _span = tracing::info_span!("load", ?paths).entered();
+        let mut lib_paths = vec![];
+        let mut ebin_app_paths = vec![];
+        for path in expand_globs(paths)? {
+            if path.file_name() == Some(OsStr::new("ebin")) {
+                ebin_app_paths.push(path.parent().unwrap_or(Path::new("")).to_path_buf());
+            } else {
+                lib_paths.push(path);
+            }
+        }
 
-        paths
+        let mut app_paths: Vec<PathBuf> = lib_paths
             .par_iter()
             .flat_map(|path| match fs::read_dir(path) {
                 Ok(dirs) => dirs
@@ -60,72 +236,397 @@ impl Loader {
                     entry
                         .file_name()
                         .to_str()
-                        .map_or(false, |name| !name.starts_with("."))
+                        .is_some_and(|name| !name.starts_with('.'))
                 })
             })
-            .try_for_each(|entry| {
-                let ebin_path = entry?.path().join("ebin");
-
-                if ebin_path.is_dir() {
-                    let app = self.read_app(&ebin_path)?;
-
-                    {
-                        let mut app_deps = self.app_deps.lock().unwrap();
-                        // Dependency on erts is implicit
-                        app_deps.add_edge(app.name, erts, ());
-                        for &dep in &app.deps {
-                            app_deps.add_edge(app.name, dep, ());
-                        }
-                    }
-                    {
-                        let mut apps = self.app_modules.lock().unwrap();
-                        apps.insert(app.name, app.modules);
+            .map(|entry| entry.map(|entry| entry.path()))
+            .filter(|app_path| {
+                app_path.as_ref().map_or(true, |app_path| {
+                    app_path.join("ebin").is_dir() || app_src(app_path).is_some()
+                })
+            })
+            .collect::<Result<_>>()?;
+        app_paths.sort_by_cached_key(|app_path| {
+            let lib = lib_paths
+                .iter()
+                .position(|lib| app_path.parent() == Some(lib.as_path()));
+            (lib, app_path.clone())
+        });
+        app_paths.extend(ebin_app_paths);
+        tracing::info!(apps = app_paths.len(), "discovered applications");
+        {
+            let mut app_order = self.app_order.lock().unwrap();
+            for app_path in &app_paths {
+                let next = app_order.len();
+                app_order.entry(app_path.clone()).or_insert(next);
+            }
+        }
+
+        self.emit(Event::AppsDiscovered {
+            count: app_paths.len(),
+        });
+
+        // Mix consolidates protocols into the application directory, or
+        // next to the lib directory for umbrella projects
+        let consolidated: Vec<_> = app_paths
+            .iter()
+            .map(|app_path| app_path.join("consolidated"))
+            .chain(
+                lib_paths
+                    .iter()
+                    .filter_map(|lib| Some(lib.parent()?.join("consolidated"))),
+            )
+            .filter(|path| path.is_dir())
+            .collect();
+
+        app_paths
+            .into_par_iter()
+            .try_for_each(|app_path| -> Result<()> {
+                let _span = tracing::debug_span!("read_app", path = %app_path.display()).entered();
+                let ebin_path = app_path.join("ebin");
+                let app = match app_src(&app_path) {
+                    Some(app_src) if !has_beams(&ebin_path) => {
+                        self.read_source_app(&app_path, &app_src)?
                     }
+                    _ => self.read_app(&ebin_path)?,
+                };
+                if let Some(app) = app {
+                    self.add_app(app, app_path);
                 }
-
                 Ok(())
-            })
+            })?;
+        consolidated
+            .par_iter()
+            .try_for_each(|path| self.read_consolidated(path))
+    }
+
+    /// Reads consolidated protocols, used in place of the compiled ones in
+    /// `finish`
+    fn read_consolidated(&self, path: &Path) -> Result<()> {
+        let _span = tracing::debug_span!("read_consolidated", path = %path.display()).entered();
+        for entry in
+            fs::read_dir(path).with_context(|| format!("reading directory: {}", path.display()))?
+        {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new("beam")) {
+                continue;
+            }
+            let (name, module) = self
+                .read_module_cached(&path)
+                .with_context(|| format!("failed to read BEAM file: {}", path.display()))?;
+            self.consolidated.lock().unwrap().insert(name, module);
+        }
+        Ok(())
+    }
+
+    /// Loads exactly the listed files, for builds without lib directories.
+    /// Files are grouped into applications by directory, named by the
+    /// `.app` file next to them, or else after the directory - its parent
+    /// for `ebin`.
+    pub fn read_files(&self, files: &[PathBuf]) -> Result<()> {
+        let mut dirs = BTreeMap::<PathBuf, Vec<PathBuf>>::new();
+        for file in files {
+            let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+            dirs.entry(dir).or_default().push(file.clone());
+        }
+
+        self.emit(Event::AppsDiscovered { count: dirs.len() });
+
+        dirs.into_par_iter().try_for_each(|(dir, mut paths)| {
+            let _span = tracing::debug_span!("read_app", path = %dir.display()).entered();
+            for entry in fs::read_dir(&dir)
+                .with_context(|| format!("reading directory: {}", dir.display()))?
+            {
+                let path = entry?.path();
+                if path.extension() == Some(OsStr::new("app")) {
+                    paths.push(path);
+                }
+            }
+            paths.sort();
+            paths.dedup();
+
+            let app_path = match dir.file_name() {
+                Some(name) if name == "ebin" => dir.parent().unwrap_or(Path::new("")),
+                _ => &dir,
+            };
+            let fallback = app_path.file_name().and_then(OsStr::to_str);
+            if let Some(app) = self.read_app_files(&dir, paths, fallback, None)? {
+                self.add_app(app, app_path.to_path_buf());
+            }
+            Ok(())
+        })
     }
 
-    pub fn finish(self) -> (Interner, Modules, AppModules, AppDeps) {
-        (
-            self.interner.into_inner().unwrap(),
-            self.modules.into_inner().unwrap(),
-            self.app_modules.into_inner().unwrap(),
-            self.app_deps.into_inner().unwrap(),
-        )
+    /// Loads every directory as the named application, whatever its `.app`
+    /// file says, if it has one
+    pub fn read_named(&self, apps: &[(String, PathBuf)]) -> Result<()> {
+        apps.par_iter().try_for_each(|(name, dir)| {
+            let _span = tracing::debug_span!("read_app", path = %dir.display()).entered();
+            let paths = fs::read_dir(dir)
+                .with_context(|| format!("reading directory: {}", dir.display()))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(mut app) = self.read_app_files(dir, paths, Some(name), None)? {
+                app.name = Atom(self.interner.lock().unwrap().get_or_intern(name));
+                let app_path = match dir.file_name() {
+                    Some(file_name) if file_name == "ebin" => dir.parent().unwrap_or(dir),
+                    _ => dir,
+                };
+                self.add_app(app, app_path.to_path_buf());
+            }
+            Ok(())
+        })
+    }
+
+    /// Loads the applications an escript bundles, see `Escript::apps`
+    pub fn read_escript(&self, escript: &Escript) -> Result<()> {
+        let apps = escript.apps();
+        self.emit(Event::AppsDiscovered { count: apps.len() });
+
+        apps.into_par_iter().try_for_each(|bundled| {
+            let _span = tracing::debug_span!("read_app", path = %bundled.dir.display()).entered();
+            let app = self.read_app_files(
+                &bundled.dir,
+                bundled.files,
+                Some(&bundled.name),
+                Some(escript),
+            )?;
+            if let Some(app) = app {
+                self.add_app(app, bundled.app_path);
+            }
+            Ok(())
+        })
     }
 
-    fn read_app(&self, ebin_path: &Path) -> Result<App> {
+    fn add_app(&self, app: App, app_path: PathBuf) {
+        tracing::debug!(modules = app.modules.len(), "loaded application");
+
+        self.emit(Event::AppLoaded {
+            name: app
+                .name
+                .resolve(&self.interner.lock().unwrap())
+                .unwrap()
+                .to_string(),
+            modules: app.modules.len(),
+        });
+        {
+            let mut declared_deps = self.declared_deps.lock().unwrap();
+            declared_deps.insert(app.name, app.deps);
+        }
+        if !app.included.is_empty() {
+            let mut included_apps = self.included_apps.lock().unwrap();
+            included_apps.insert(app.name, app.included);
+        }
+        {
+            let mut apps = self.app_modules.lock().unwrap();
+            apps.insert(app.name, app.modules);
+        }
+        {
+            let test = app_path
+                .parent()
+                .is_some_and(|lib| self.test_lib_paths.iter().any(|test| test == lib));
+            let mut test_apps = self.test_apps.lock().unwrap();
+            *test_apps.entry(app.name).or_insert(test) &= test;
+        }
+        {
+            let mut app_paths = self.app_paths.lock().unwrap();
+            app_paths.insert(app.name, app_path);
+        }
+        if let Some(vsn) = app.vsn {
+            let mut app_versions = self.app_versions.lock().unwrap();
+            app_versions.insert(app.name, vsn);
+        }
+        if let Some(callback) = app.callback {
+            let mut app_callbacks = self.app_callbacks.lock().unwrap();
+            app_callbacks.insert(app.name, callback);
+        }
+        if !app.optional_deps.is_empty() {
+            let mut optional_deps = self.optional_deps.lock().unwrap();
+            optional_deps.insert(app.name, app.optional_deps);
+        }
+        if let Some(runtime_deps) = app.runtime_deps {
+            let mut all_runtime_deps = self.runtime_deps.lock().unwrap();
+            all_runtime_deps.insert(app.name, runtime_deps);
+        }
+        if !app.registered.is_empty() {
+            let mut registered = self.registered.lock().unwrap();
+            registered.insert(app.name, app.registered);
+        }
+        if let Some(listed) = app.listed_modules {
+            let mut listed_modules = self.listed_modules.lock().unwrap();
+            listed_modules.insert(app.name, listed);
+        }
+    }
+
+    /// Beam files skipped and chunks dropped so far because they couldn't
+    /// be read, sorted
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = self.warnings.lock().unwrap().clone();
+        warnings.sort();
+        warnings
+    }
+
+    fn warn(&self, warning: String) {
+        tracing::warn!("{}", warning);
+        self.warnings.lock().unwrap().push(warning);
+    }
+
+    pub fn finish(self) -> Workspace {
+        self.finish_cached().0
+    }
+
+    /// Like `finish`, also returning the parsed modules for the next load
+    pub fn finish_cached(self) -> (Workspace, Cache) {
+        let _span = tracing::info_span!("build_graph").entered();
+        let mut interner = self.interner.into_inner().unwrap();
+        let app_modules = self.app_modules.into_inner().unwrap();
+        let declared_deps = self.declared_deps.into_inner().unwrap();
+        let included_apps = self.included_apps.into_inner().unwrap();
+        let mut modules = self.modules.into_inner().unwrap();
+        for (name, module) in self.consolidated.into_inner().unwrap() {
+            if let Some(compiled) = modules.get_mut(&name) {
+                *compiled = module;
+            }
+        }
+
+        // The graph only connects applications that were actually loaded,
+        // dependencies missing from the lib paths are reported by the analyzer
+        let mut app_deps = AppDeps::default();
+        let erts = Atom::intern(&mut interner, "erts");
+        for (&app, deps) in &declared_deps {
+            app_deps.add_node(app);
+            // Dependency on erts is implicit
+            if app != erts && app_modules.contains_key(&erts) {
+                app_deps.add_edge(app, erts, ());
+            }
+            let included = included_apps.get(&app).into_iter().flatten();
+            for &dep in deps.iter().chain(included) {
+                if app_modules.contains_key(&dep) {
+                    app_deps.add_edge(app, dep, ());
+                }
+            }
+        }
+
+        let cache = Cache {
+            interner: interner.clone(),
+            modules: self.cached.into_inner().unwrap(),
+        };
+        let workspace = Workspace {
+            interner,
+            modules,
+            app_modules,
+            app_deps,
+            declared_deps,
+            included_apps,
+            app_paths: self.app_paths.into_inner().unwrap(),
+            app_versions: self.app_versions.into_inner().unwrap(),
+            app_callbacks: self.app_callbacks.into_inner().unwrap(),
+            shadowed: self.shadowed.into_inner().unwrap(),
+            shadowed_builds: self.shadowed_builds.into_inner().unwrap(),
+            listed_modules: self.listed_modules.into_inner().unwrap(),
+            optional_deps: self.optional_deps.into_inner().unwrap(),
+            runtime_deps: self.runtime_deps.into_inner().unwrap(),
+            registered: self.registered.into_inner().unwrap(),
+            test_apps: self
+                .test_apps
+                .into_inner()
+                .unwrap()
+                .into_iter()
+                .filter(|&(_, test)| test)
+                .map(|(app, _)| app)
+                .collect(),
+            excluded: self.excluded.into_inner().unwrap(),
+        };
+        (workspace, cache)
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(progress) = &self.progress {
+            progress(event)
+        }
+    }
+
+    /// Returns `None` when the application is excluded
+    fn read_app(&self, ebin_path: &Path) -> Result<Option<App>> {
+        let paths = fs::read_dir(ebin_path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.read_app_files(ebin_path, paths, None, None)
+    }
+
+    /// Reads the application from some files of `ebin_path`, or of the
+    /// escript bundling them. Without an `.app` file, it's named
+    /// `fallback_name` and has no dependencies.
+    fn read_app_files(
+        &self,
+        ebin_path: &Path,
+        mut paths: Vec<PathBuf>,
+        fallback_name: Option<&str>,
+        escript: Option<&Escript>,
+    ) -> Result<Option<App>> {
         let mut app_modules = vec![];
         let mut app_name = None;
-        let mut app_deps = None;
+        let mut app_file = None;
 
-        for entry in fs::read_dir(ebin_path)? {
-            let entry = entry?;
-            let path = entry.path();
+        // Knowing the application first allows skipping excluded ones early
+        paths.sort_by_key(|path| path.extension() != Some(OsStr::new("app")));
 
+        for path in &paths {
             if let Some(extension) = path.extension().and_then(OsStr::to_str) {
                 match extension {
                     "beam" => {
-                        let (module, imports, exports) =
-                            self.read_module(&path).with_context(|| {
-                                format!("failed to read BEAM file: {}", path.display())
-                            })?;
+                        let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("");
+                        if self.excludes.module(stem) {
+                            tracing::debug!(module = stem, "excluded module");
+                            self.exclude(stem);
+                            continue;
+                        }
 
-                        let mut modules = self.modules.lock().unwrap();
+                        let module = match escript {
+                            Some(escript) => self.parse_module(path, &escript.files[path]),
+                            None => self.read_module_cached(path),
+                        };
+                        let (name, module) = match module {
+                            Ok(module) => module,
+                            Err(err) => {
+                                self.warn(format!(
+                                    "skipped unreadable BEAM file {}: {:#}",
+                                    path.display(),
+                                    err
+                                ));
+                                continue;
+                            }
+                        };
 
-                        app_modules.push(module);
-                        modules.insert(module, (imports, exports));
+                        self.emit(Event::BeamParsed { path: path.clone() });
+                        app_modules.push(name);
+                        self.insert_module(name, module);
                     }
                     "app" => {
-                        app_name = path
-                            .file_stem()
-                            .and_then(OsStr::to_str)
-                            .map(|app| Atom(self.interner.lock().unwrap().get_or_intern(app)));
-                        app_deps = Some(self.read_app_deps(&path).with_context(|| {
+                        let name = path.file_stem().and_then(OsStr::to_str);
+                        if let Some(name) = name.filter(|name| self.excludes.app(name)) {
+                            tracing::debug!(app = name, "excluded application");
+                            self.exclude_app(name, &paths);
+                            return Ok(None);
+                        }
+                        app_name =
+                            name.map(|app| Atom(self.interner.lock().unwrap().get_or_intern(app)));
+                        let text = match escript {
+                            Some(escript) => {
+                                String::from_utf8_lossy(&escript.files[path]).into_owned()
+                            }
+                            None => fs::read_to_string(path)?,
+                        };
+                        let file = self.read_app_file(&text).with_context(|| {
                             format!("failed to parse .app file: {}", path.display())
-                        })?)
+                        })?;
+                        if let (Some(release), Some(name)) = (&self.release, name) {
+                            if !release.contains(name, file.vsn.as_deref()) {
+                                tracing::debug!(app = name, vsn = ?file.vsn, "not in the release");
+                                return Ok(None);
+                            }
+                        }
+                        app_file = Some(file);
                     }
                     "appup" | "hrl" | "am" => continue,
                     _ => anyhow::bail!("unexpected file: {:?}", path),
@@ -133,62 +634,695 @@ impl Loader {
             }
         }
 
-        Ok(App {
+        let app_name = app_name.or_else(|| {
+            fallback_name.map(|app| Atom(self.interner.lock().unwrap().get_or_intern(app)))
+        });
+        let app_file = app_file.unwrap_or_default();
+        Ok(Some(App {
             name: app_name
                 .with_context(|| format!("missing .app file in {}", ebin_path.display()))?,
-            deps: app_deps.unwrap(),
+            deps: app_file.deps,
+            included: app_file.included,
+            vsn: app_file.vsn,
+            callback: app_file.callback,
             modules: app_modules,
-        })
+            listed_modules: app_file.modules,
+            optional_deps: app_file.optional_deps,
+            runtime_deps: app_file.runtime_deps,
+            registered: app_file.registered,
+        }))
     }
 
-    fn read_app_deps(&self, path: &Path) -> Result<Vec<Atom>> {
-        // This is a very naive way of extracting app dependency information
-        // based on a regex, to avoid full parsing. It will probably break
-        // at custom-built files, but should be fine with rebar3 emitted ones
-        lazy_static! {
-            static ref APPS: Regex =
-                Regex::new(r"\{\s*(?:included_)?applications\s*,\s*\[\s*([0-9a-z_,\s]+)\s*\]\s*\}")
-                    .unwrap();
+    /// Reads an application that wasn't built from its `.app.src` file and
+    /// the `.erl` files in `src` and its subdirectories, see `source`.
+    /// Modules the `.app.src` file lists aren't checked, as the build fills
+    /// them in.
+    fn read_source_app(&self, app_path: &Path, app_src: &Path) -> Result<Option<App>> {
+        let mut paths = vec![];
+        find_sources(&app_path.join("src"), &mut paths)?;
+        paths.sort();
+
+        let name = app_src
+            .file_name()
+            .and_then(OsStr::to_str)
+            .and_then(|name| name.strip_suffix(".app.src"))
+            .unwrap_or_default();
+        if self.excludes.app(name) {
+            tracing::debug!(app = name, "excluded application");
+            self.exclude_app(name, &paths);
+            return Ok(None);
+        }
+        let text = fs::read_to_string(app_src)?;
+        let app_file = self
+            .read_app_file(&text)
+            .with_context(|| format!("failed to parse .app.src file: {}", app_src.display()))?;
+
+        let mut app_modules = vec![];
+        for path in &paths {
+            let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("");
+            if self.excludes.module(stem) {
+                tracing::debug!(module = stem, "excluded module");
+                self.exclude(stem);
+                continue;
+            }
+            let (name, module) = match self.read_source_module(path) {
+                Ok(module) => module,
+                Err(err) => {
+                    self.warn(format!(
+                        "skipped unreadable source file {}: {:#}",
+                        path.display(),
+                        err
+                    ));
+                    continue;
+                }
+            };
+            self.emit(Event::BeamParsed { path: path.clone() });
+            app_modules.push(name);
+            self.insert_module(name, module);
         }
 
-        let text = fs::read_to_string(path)?;
+        Ok(Some(App {
+            name: Atom::intern(&mut self.interner.lock().unwrap(), name),
+            deps: app_file.deps,
+            included: app_file.included,
+            vsn: app_file.vsn,
+            callback: app_file.callback,
+            modules: app_modules,
+            listed_modules: None,
+            optional_deps: app_file.optional_deps,
+            runtime_deps: app_file.runtime_deps,
+            registered: app_file.registered,
+        }))
+    }
 
-        let deps = {
-            let mut interner = self.interner.lock().unwrap();
-            APPS.captures_iter(&text)
-                .flat_map(|caps| caps.get(1).unwrap().as_str().split(","))
-                .map(|app_string| app_string.trim())
-                .map(|app| Atom(interner.get_or_intern(app)))
-                .collect()
+    /// Keeps the module that comes first on the lib paths when another
+    /// application has one with the same name, recording the other
+    fn insert_module(&self, name: Atom, module: Module) {
+        let mut modules = self.modules.lock().unwrap();
+        let previous = match modules.get(&name) {
+            Some(previous) => previous,
+            None => {
+                modules.insert(name, module);
+                return;
+            }
+        };
+        let shadowed = if self.precedes(&module.path, &previous.path) {
+            modules.insert(name, module).unwrap()
+        } else {
+            module
         };
+        tracing::debug!(?name, path = %shadowed.path.display(), "shadowed module");
+        let mut shadowed_builds = self.shadowed_builds.lock().unwrap();
+        shadowed_builds.insert(shadowed.path.clone(), shadowed.build);
+        let mut shadowed_modules = self.shadowed.lock().unwrap();
+        shadowed_modules
+            .entry(name)
+            .or_default()
+            .push(shadowed.path);
+    }
+
+    /// Whether the beam file `a` comes before `b` on the lib paths. Files
+    /// outside of them come last, by path.
+    fn precedes(&self, a: &Path, b: &Path) -> bool {
+        let app_order = self.app_order.lock().unwrap();
+        let rank = |path: &Path| {
+            let app_path = path.parent().and_then(Path::parent)?;
+            app_order.get(app_path).copied()
+        };
+        (rank(a).unwrap_or(usize::MAX), a) < (rank(b).unwrap_or(usize::MAX), b)
+    }
 
-        Ok(deps)
+    fn exclude(&self, name: &str) {
+        let atom = Atom(self.interner.lock().unwrap().get_or_intern(name));
+        self.excluded.lock().unwrap().insert(atom);
+    }
+
+    /// Records the application together with the modules of its files
+    fn exclude_app(&self, name: &str, paths: &[PathBuf]) {
+        self.exclude(name);
+        for path in paths {
+            if matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("beam" | "erl")
+            ) {
+                if let Some(module) = path.file_stem().and_then(OsStr::to_str) {
+                    self.exclude(module);
+                }
+            }
+        }
     }
 
-    fn read_module(&self, path: &Path) -> Result<(Atom, Imports, Exports)> {
-        let beam = StandardBeamFile::from_file(path)?;
+    fn read_app_file(&self, text: &str) -> Result<AppFile> {
+        let terms = term::consult(text)?;
+        let properties = match terms.as_slice() {
+            [app] => match etf::tuple(app) {
+                Some([tag, _, properties]) if etf::atom(tag) == Some("application") => properties,
+                _ => anyhow::bail!("expected {{application, Name, Properties}}"),
+            },
+            _ => anyhow::bail!("expected a single term, found {}", terms.len()),
+        };
+        let property = |key| etf::proplist_get(properties, key);
+
+        let mut interner = self.interner.lock().unwrap();
+        let mut atoms = |key| -> Option<Vec<Atom>> {
+            let atoms = etf::list(property(key)?)?
+                .iter()
+                .filter_map(etf::atom)
+                .map(|atom| Atom(interner.get_or_intern(atom)))
+                .collect();
+            Some(atoms)
+        };
+        let deps = atoms("applications").unwrap_or_default();
+        let included = atoms("included_applications").unwrap_or_default();
+        let optional_deps = atoms("optional_applications").unwrap_or_default();
+        let modules = atoms("modules");
+        let registered = atoms("registered").unwrap_or_default();
+
+        let vsn = property("vsn").and_then(etf::string);
+
+        let callback = property("mod")
+            .and_then(etf::tuple)
+            .and_then(|callback| match callback {
+                [module, _] => etf::atom(module),
+                _ => None,
+            })
+            .map(|module| Atom(interner.get_or_intern(module)));
+
+        // `"App-Vsn"` strings
+        let runtime_deps = property("runtime_dependencies")
+            .and_then(etf::list)
+            .map(|deps| {
+                deps.iter()
+                    .filter_map(etf::string)
+                    .filter_map(|dep| Some(dep.split_once('-')?.0.to_string()))
+                    .map(|app| Atom(interner.get_or_intern(app)))
+                    .collect()
+            });
+
+        Ok(AppFile {
+            deps,
+            included,
+            vsn,
+            callback,
+            modules,
+            optional_deps,
+            runtime_deps,
+            registered,
+        })
+    }
 
+    fn read_module_cached(&self, path: &Path) -> Result<(Atom, Module)> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return self.read_module(path),
+        };
+        let modified = fs::metadata(path)?.modified()?;
+        let (name, module) = match cache.get(path) {
+            Some((time, name, module)) if *time == modified => (*name, module.clone()),
+            Some(_) => {
+                tracing::debug!(path = %path.display(), "parsing changed module");
+                self.read_module(path)?
+            }
+            None => self.read_module(path)?,
+        };
+        let mut cached = self.cached.lock().unwrap();
+        cached.insert(path.to_path_buf(), (modified, name, module.clone()));
+        Ok((name, module))
+    }
+
+    fn read_module(&self, path: &Path) -> Result<(Atom, Module)> {
+        let bytes = fs::read(path)?;
+        self.parse_module(path, &bytes)
+    }
+
+    /// A module from its source, with only what `source::parse` finds
+    fn read_source_module(&self, path: &Path) -> Result<(Atom, Module)> {
+        let bytes = fs::read(path)?;
+        let parsed = source::parse(&String::from_utf8_lossy(&bytes))?;
+        let mut hasher = FxHasher::default();
+        bytes.hash(&mut hasher);
+
+        let mut interner = self.interner.lock().unwrap();
+        let mut imports = Imports::default();
+        let mut dynamic_calls = vec![];
+        let mut call_lines = FxHashMap::<Mfa, u32>::default();
+        for call in &parsed.calls {
+            let module = Atom::intern(&mut interner, &call.module);
+            let function = Atom::intern(&mut interner, &call.function);
+            if call.capture {
+                dynamic_calls.push(DynamicCall {
+                    module,
+                    function,
+                    arity: call.arity,
+                    fun_ref: true,
+                });
+            } else {
+                let functions = imports.entry(module).or_default();
+                if !functions.contains(&(function, call.arity)) {
+                    functions.push((function, call.arity));
+                }
+            }
+            call_lines
+                .entry((module, function, call.arity))
+                .or_insert(call.line);
+        }
+        dynamic_calls.sort();
+        dynamic_calls.dedup();
+        let exports = parsed
+            .exports
+            .iter()
+            .map(|(function, arity)| (Atom::intern(&mut interner, function), *arity))
+            .collect();
+        let locals = parsed
+            .locals
+            .iter()
+            .map(|(function, arity)| (Atom::intern(&mut interner, function), *arity))
+            .collect();
+        let behaviours = parsed
+            .behaviours
+            .iter()
+            .map(|behaviour| Atom::intern(&mut interner, behaviour))
+            .collect();
+        let compiled = fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .map(Timestamp::from_system_time);
+
+        Ok((
+            Atom::intern(&mut interner, &parsed.name),
+            Module {
+                path: path.to_path_buf(),
+                imports,
+                exports,
+                attributes: Attributes::default(),
+                locals,
+                compiled,
+                source: Some(path.to_path_buf()),
+                literal_strings: vec![],
+                parse_transforms: vec![],
+                deprecated: vec![],
+                behaviours,
+                protocol: false,
+                protocol_impl: None,
+                on_load: None,
+                ignore_xref: vec![],
+                dynamic_calls,
+                function_calls: None,
+                call_lines,
+                load_checks: LoadChecks::default(),
+                crypto_atoms: FxHashMap::default(),
+                build: hasher.finish(),
+                hidden: false,
+                docs: FxHashMap::default(),
+            },
+        ))
+    }
+
+    /// Chunks of a beam file, gunzipped first when compiled with the
+    /// `compressed` option. A chunk that doesn't decode, or a truncated
+    /// last one, is dropped with a warning, keeping the others.
+    fn read_chunks(&self, path: &Path, bytes: &[u8]) -> Result<Vec<StandardChunk>> {
+        let mut gunzipped = vec![];
+        let bytes = if bytes.starts_with(GZIP_MAGIC) {
+            libflate::gzip::Decoder::new(bytes)
+                .and_then(|mut decoder| decoder.read_to_end(&mut gunzipped))
+                .context("gunzipping")?;
+            &gunzipped[..]
+        } else {
+            bytes
+        };
+        if bytes.get(0..4) != Some(b"FOR1") || bytes.get(8..12) != Some(b"BEAM") {
+            anyhow::bail!("not a BEAM file");
+        }
+
+        let mut chunks = vec![];
+        let mut rest = &bytes[12..];
+        while !rest.is_empty() {
+            let name = String::from_utf8_lossy(&rest[..rest.len().min(4)]);
+            let data = match rest.get(4..8) {
+                Some(size) => {
+                    let size = u32::from_be_bytes(size.try_into().unwrap()) as usize;
+                    rest.get(8..8 + size)
+                }
+                None => None,
+            };
+            let data = match data {
+                Some(data) => data,
+                None => {
+                    self.warn(format!(
+                        "{}: dropped truncated {} chunk",
+                        path.display(),
+                        name
+                    ));
+                    break;
+                }
+            };
+            let id: [u8; 4] = rest[0..4].try_into().unwrap();
+            match StandardChunk::decode_data(&id, data) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(err) => self.warn(format!(
+                    "{}: dropped unreadable {} chunk: {}",
+                    path.display(),
+                    name,
+                    err
+                )),
+            }
+            // Chunks are padded to four bytes
+            rest = rest.get((8 + data.len() + 3) & !3..).unwrap_or_default();
+        }
+        Ok(chunks)
+    }
+
+    fn parse_module(&self, path: &Path, bytes: &[u8]) -> Result<(Atom, Module)> {
         let mut atom_chunk = None;
         let mut import_chunk = None;
         let mut export_chunk = None;
+        let mut local_chunk = None;
+        let mut compile_info_chunk = None;
+        let mut literal_chunk = None;
+        let mut attr_chunk = None;
+        let mut code_chunk = None;
+        let mut fun_chunk = None;
+        let mut docs_chunk = None;
+        let mut debug_info_chunk = None;
 
-        for chunk in beam.chunks {
+        for chunk in self.read_chunks(path, bytes)? {
             match chunk {
                 StandardChunk::Atom(atom) => atom_chunk = Some(atom),
                 StandardChunk::ExpT(export) => export_chunk = Some(export),
                 StandardChunk::ImpT(import) => import_chunk = Some(import),
+                StandardChunk::LocT(locals) => local_chunk = Some(locals),
+                StandardChunk::CInf(compile_info) => compile_info_chunk = Some(compile_info),
+                StandardChunk::LitT(literals) => literal_chunk = Some(literals),
+                StandardChunk::Attr(attrs) => attr_chunk = Some(attrs),
+                StandardChunk::Code(code) => code_chunk = Some(code),
+                StandardChunk::FunT(funs) => fun_chunk = Some(funs),
+                StandardChunk::Docs(docs) => docs_chunk = Some(docs),
+                StandardChunk::Dbgi(debug_info) => debug_info_chunk = Some(debug_info.term),
+                // Replaced by Dbgi in OTP 20, and empty without debug info
+                StandardChunk::Abst(abstract_code) if !abstract_code.term.is_empty() => {
+                    debug_info_chunk = Some(abstract_code.term)
+                }
                 _ => continue,
             }
         }
 
-        let atoms = {
+        let atom_chunk = atom_chunk.context("missing Atom chunk")?;
+        let import_chunk = import_chunk.context("missing ImpT chunk")?;
+        let export_chunk = export_chunk.context("missing ExpT chunk")?;
+        if atom_chunk.atoms.is_empty() {
+            anyhow::bail!("empty Atom chunk");
+        }
+
+        let attrs = attr_chunk
+            .as_ref()
+            .and_then(|chunk| etf::decode(&chunk.term));
+        let literals: Vec<_> = literal_chunk
+            .iter()
+            .flat_map(|chunk| &chunk.literals)
+            .filter_map(|literal| etf::decode(literal))
+            .collect();
+        let compile_info = compile_info_chunk
+            .as_ref()
+            .and_then(|chunk| etf::decode(&chunk.term));
+        let debug_info = debug_info_chunk.and_then(|chunk| etf::decode(&chunk));
+        let abstract_functions = debug_info.as_ref().and_then(abstract_code::functions);
+        let (
+            atoms,
+            attributes,
+            deprecated,
+            behaviours,
+            protocol_impl,
+            protocol,
+            on_load,
+            ignore_xref,
+            dynamic_calls,
+            parse_transforms,
+            abstract_calls,
+            docs,
+        ) = {
             let mut interner = self.interner.lock().unwrap();
-            load_atoms(&mut interner, &atom_chunk.unwrap())
+            let atoms = load_atoms(&mut interner, &atom_chunk);
+            let attributes = attrs
+                .as_ref()
+                .map(|attrs| load_attributes(&mut interner, attrs))
+                .unwrap_or_default();
+            let mut deprecated = load_deprecated(&mut interner, &attributes);
+            let behaviours = load_behaviours(&mut interner, &attributes);
+            let protocol_impl = load_protocol_impl(&mut interner, &attributes);
+            let protocol = !attribute(&interner, &attributes, &["__protocol__"]).is_empty();
+            let on_load = load_on_load(&mut interner, &attributes);
+            let ignore_xref = load_ignore_xref(&mut interner, &attributes, atoms[0]);
+            let dynamic_calls = load_dynamic_calls(&mut interner, &literals);
+            let parse_transforms = compile_info
+                .as_ref()
+                .map(|compile_info| load_parse_transforms(&mut interner, compile_info))
+                .unwrap_or_default();
+            let abstract_calls = abstract_functions
+                .as_ref()
+                .map(|functions| load_abstract_calls(&mut interner, atoms[0], functions));
+            let mut docs = docs_chunk
+                .and_then(|chunk| etf::decode(&chunk.term))
+                .map(|docs| load_docs(&mut interner, &docs))
+                .unwrap_or_default();
+            deprecated.append(&mut docs.deprecated);
+            (
+                atoms,
+                attributes,
+                deprecated,
+                behaviours,
+                protocol_impl,
+                protocol,
+                on_load,
+                ignore_xref,
+                dynamic_calls,
+                parse_transforms,
+                abstract_calls,
+                docs,
+            )
         };
-        let imports = load_imports(&atoms, &import_chunk.unwrap());
-        let exports = load_exports(&atoms, &export_chunk.unwrap());
+        let imports = load_imports(&atoms, &import_chunk);
+        let exports = load_exports(&atoms, &export_chunk);
+        let locals = local_chunk
+            .as_ref()
+            .map(|local_chunk| load_locals(&atoms, local_chunk))
+            .unwrap_or_default();
+        let loaded = [
+            (
+                "ImpT",
+                imports.values().map(Vec::len).sum(),
+                import_chunk.imports.len(),
+            ),
+            ("ExpT", exports.len(), export_chunk.exports.len()),
+            (
+                "LocT",
+                locals.len(),
+                local_chunk.map_or(0, |local_chunk| local_chunk.locals.len()),
+            ),
+        ];
+        for (name, loaded, entries) in loaded {
+            if loaded < entries {
+                self.warn(format!(
+                    "{}: dropped {} of the {} {} chunk entries, with an invalid atom index",
+                    path.display(),
+                    entries - loaded,
+                    entries,
+                    name
+                ));
+            }
+        }
+        let build = load_build(
+            &atom_chunk,
+            &import_chunk,
+            &export_chunk,
+            code_chunk.as_ref(),
+            literal_chunk.as_ref(),
+        );
+        let code = code_chunk.and_then(|code| {
+            disasm::disassemble(&code)
+                .map_err(
+                    |err| tracing::debug!(path = %path.display(), "disassembly failed: {:#}", err),
+                )
+                .ok()
+        });
+        let (abstract_function_calls, call_lines) = abstract_calls.unzip();
+        let function_calls = code
+            .as_ref()
+            .and_then(|code| {
+                load_function_calls(&atoms, &import_chunk, fun_chunk.as_ref(), code)
+                    .map_err(
+                        |err| tracing::debug!(path = %path.display(), "disassembly failed: {:#}", err),
+                    )
+                    .ok()
+            })
+            .or(abstract_function_calls);
+        let load_checks = code
+            .as_ref()
+            .map(|code| load_load_checks(&atoms, &atom_chunk, &import_chunk, code))
+            .unwrap_or_default();
+        let crypto_atoms = code
+            .as_ref()
+            .map(|code| load_crypto_atoms(&atoms, &atom_chunk, &import_chunk, code))
+            .unwrap_or_default();
+        let compiled = compile_info
+            .as_ref()
+            .and_then(load_compile_time)
+            .or_else(|| {
+                let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+                Some(Timestamp::from_system_time(modified))
+            });
+
+        Ok((
+            atoms[0],
+            Module {
+                path: path.to_path_buf(),
+                imports,
+                exports,
+                attributes,
+                locals,
+                compiled,
+                source: compile_info.as_ref().and_then(load_source),
+                literal_strings: load_literal_strings(&literals),
+                parse_transforms,
+                deprecated,
+                behaviours,
+                protocol,
+                protocol_impl,
+                on_load,
+                ignore_xref,
+                dynamic_calls,
+                function_calls,
+                call_lines: call_lines.unwrap_or_default(),
+                load_checks,
+                crypto_atoms,
+                build,
+                hidden: docs.hidden,
+                docs: docs.functions,
+            },
+        ))
+    }
+}
+
+pub(crate) fn expand_globs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = vec![];
+    for path in paths {
+        let pattern = path.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) {
+            expanded.push(path.clone());
+            continue;
+        }
+
+        let matches = glob::glob(&pattern)
+            .with_context(|| format!("invalid lib path pattern: {}", pattern))?
+            .filter_map(|entry| entry.ok())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            anyhow::bail!("no directories match lib path: {}", pattern);
+        }
+        expanded.extend(matches);
+    }
+    Ok(expanded)
+}
+
+/// The `.app.src` file in the `src` directory of an application
+fn app_src(app_path: &Path) -> Option<PathBuf> {
+    fs::read_dir(app_path.join("src"))
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .find(|path| path.to_string_lossy().ends_with(".app.src"))
+}
+
+fn has_beams(ebin_path: &Path) -> bool {
+    fs::read_dir(ebin_path).is_ok_and(|entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.path().extension() == Some(OsStr::new("beam")))
+    })
+}
+
+/// The `.erl` files in `dir` and its subdirectories
+fn find_sources(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("reading directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_sources(&path, out)?;
+        } else if path.extension() == Some(OsStr::new("erl")) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// What the Docs chunk says about the module and its functions
+#[derive(Default)]
+struct Docs {
+    hidden: bool,
+    /// Of the module and of functions
+    deprecated: Vec<Deprecation>,
+    functions: FxHashMap<(Atom, u32), FunctionDoc>,
+}
+
+/// An EEP 48 Docs chunk, `{docs_v1, Anno, Language, Format, ModuleDoc,
+/// Metadata, Docs}`, where every entry of `Docs` is `{{Kind, Name, Arity},
+/// Anno, Signature, Doc, Metadata}`. Docs are `hidden`, `none` or a map of
+/// translations, and metadata a map with keys like `deprecated` and `since`.
+fn load_docs(interner: &mut Interner, docs: &Term) -> Docs {
+    let (module_doc, metadata, entries) = match etf::tuple(docs) {
+        Some([tag, _, _, _, module_doc, metadata, entries])
+            if etf::atom(tag) == Some("docs_v1") =>
+        {
+            (module_doc, metadata, entries)
+        }
+        _ => return Docs::default(),
+    };
+    let text = |metadata, key| etf::map_get(metadata, key).and_then(etf::string);
 
-        Ok((atoms[0], imports, exports))
+    let mut deprecated: Vec<_> = text(metadata, "deprecated")
+        .map(|hint| Deprecation {
+            function: None,
+            arity: None,
+            hint: Some(hint),
+        })
+        .into_iter()
+        .collect();
+    let mut functions = FxHashMap::default();
+    for entry in etf::list(entries).unwrap_or_default() {
+        let (key, doc, metadata) = match etf::tuple(entry) {
+            Some([key, _, _, doc, metadata]) => (key, doc, metadata),
+            _ => continue,
+        };
+        let (name, arity) = match etf::tuple(key) {
+            Some([kind, name, arity]) if etf::atom(kind) == Some("function") => {
+                match (etf::atom(name), etf::int(arity)) {
+                    (Some(name), Some(arity)) => (Atom::intern(interner, name), arity as u32),
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        let doc = FunctionDoc {
+            hidden: etf::atom(doc) == Some("hidden"),
+            deprecated: text(metadata, "deprecated"),
+            since: text(metadata, "since"),
+        };
+        if let Some(hint) = &doc.deprecated {
+            deprecated.push(Deprecation {
+                function: Some(name),
+                arity: Some(arity),
+                hint: Some(hint.clone()),
+            });
+        }
+        if doc != FunctionDoc::default() {
+            functions.insert((name, arity), doc);
+        }
+    }
+    Docs {
+        hidden: etf::atom(module_doc) == Some("hidden"),
+        deprecated,
+        functions,
     }
 }
 
@@ -200,23 +1334,626 @@ fn load_atoms(interner: &mut Interner, atom_chunk: &AtomChunk) -> Vec<Atom> {
         .collect()
 }
 
+fn load_build(
+    atom_chunk: &AtomChunk,
+    import_chunk: &ImpTChunk,
+    export_chunk: &ExpTChunk,
+    code_chunk: Option<&CodeChunk>,
+    literal_chunk: Option<&LitTChunk>,
+) -> u64 {
+    let mut hasher = FxHasher::default();
+    for atom in &atom_chunk.atoms {
+        atom.name.hash(&mut hasher);
+    }
+    for import in &import_chunk.imports {
+        (import.module, import.function, import.arity).hash(&mut hasher);
+    }
+    for export in &export_chunk.exports {
+        (export.function, export.arity, export.label).hash(&mut hasher);
+    }
+    if let Some(code) = code_chunk {
+        code.bytecode.hash(&mut hasher);
+    }
+    for literal in literal_chunk.iter().flat_map(|chunk| &chunk.literals) {
+        literal.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The atom of a chunk's 1-based index into the atom table
+fn atom_at(atoms: &[Atom], idx: u32) -> Option<Atom> {
+    atoms.get((idx as usize).checked_sub(1)?).copied()
+}
+
+/// Entries with an invalid atom index are left out
 fn load_imports(atoms: &[Atom], import_chunk: &ImpTChunk) -> Imports {
     let mut imports = Imports::default();
 
     for import in import_chunk.imports.iter() {
+        let (module, function) = match (
+            atom_at(atoms, import.module),
+            atom_at(atoms, import.function),
+        ) {
+            (Some(module), Some(function)) => (module, function),
+            _ => continue,
+        };
         imports
-            .entry(atoms[import.module as usize - 1])
+            .entry(module)
             .or_default()
-            .push((atoms[import.function as usize - 1], import.arity))
+            .push((function, import.arity))
     }
 
     imports
 }
 
+/// Entries with an invalid atom index are left out
 fn load_exports(atoms: &[Atom], export_chunk: &ExpTChunk) -> Exports {
     export_chunk
         .exports
         .iter()
-        .map(|export| (atoms[export.function as usize - 1], export.arity))
+        .filter_map(|export| Some((atom_at(atoms, export.function)?, export.arity)))
+        .collect()
+}
+
+/// Entries with an invalid atom index are left out
+fn load_locals(atoms: &[Atom], local_chunk: &LocTChunk) -> Exports {
+    local_chunk
+        .locals
+        .iter()
+        .filter_map(|local| Some((atom_at(atoms, local.function)?, local.arity)))
+        .collect()
+}
+
+fn load_function_calls(
+    atoms: &[Atom],
+    import_chunk: &ImpTChunk,
+    fun_chunk: Option<&FunTChunk>,
+    code: &disasm::Code,
+) -> Result<FunctionCalls> {
+    let atom = |idx: u32| {
+        let idx = idx.checked_sub(1).context("invalid atom index")?;
+        atoms
+            .get(idx as usize)
+            .copied()
+            .context("invalid atom index")
+    };
+    let local = |function: &disasm::Function| -> Result<Mfa> {
+        Ok((atoms[0], atom(function.name)?, function.arity))
+    };
+
+    let mut function_calls = FunctionCalls::default();
+    for function in &code.functions {
+        let mut calls = vec![];
+        for call in &function.calls {
+            let called = match *call {
+                disasm::Call::Local(label) => code.function_at(label).map(local),
+                disasm::Call::External(idx) => import_chunk
+                    .imports
+                    .get(idx as usize)
+                    .map(|import| Ok((atom(import.module)?, atom(import.function)?, import.arity))),
+                disasm::Call::Fun(idx) => fun_chunk
+                    .and_then(|funs| funs.functions.get(idx as usize))
+                    .and_then(|fun| code.function_at(fun.label))
+                    .map(local),
+            };
+            if let Some(called) = called {
+                calls.push(called?);
+            }
+        }
+        function_calls.insert((atom(function.name)?, function.arity), calls);
+    }
+    Ok(function_calls)
+}
+
+/// The calls of every function in the debug info, and the line of the first
+/// call of every remote function. Local calls to functions the module
+/// doesn't define are auto-imported BIFs.
+fn load_abstract_calls(
+    interner: &mut Interner,
+    module: Atom,
+    functions: &[abstract_code::Function],
+) -> (FunctionCalls, FxHashMap<Mfa, u32>) {
+    let defined: FxHashSet<_> = functions
+        .iter()
+        .map(|function| (function.name.as_str(), function.arity))
+        .collect();
+    let mut function_calls = FunctionCalls::default();
+    let mut call_lines = FxHashMap::<Mfa, u32>::default();
+    for function in functions {
+        let mut calls = vec![];
+        for call in &function.calls {
+            let called_module = match call.module {
+                Some(called) => Atom::intern(interner, called),
+                None if defined.contains(&(call.function, call.arity)) => module,
+                None => Atom::intern(interner, "erlang"),
+            };
+            let called = (
+                called_module,
+                Atom::intern(interner, call.function),
+                call.arity,
+            );
+            calls.push(called);
+            if let Some(line) = call.line.filter(|_| called_module != module) {
+                call_lines
+                    .entry(called)
+                    .and_modify(|first| *first = (*first).min(line))
+                    .or_insert(line);
+            }
+        }
+        let name = Atom::intern(interner, &function.name);
+        function_calls.insert((name, function.arity), calls);
+    }
+    (function_calls, call_lines)
+}
+
+/// Functions checking whether a module is loaded, taking it as the first
+/// argument
+const LOAD_CHECKS: &[(&str, &str, u32)] = &[
+    ("code", "ensure_loaded", 1),
+    ("erlang", "function_exported", 3),
+    ("erlang", "module_loaded", 1),
+    ("Elixir.Code", "ensure_loaded?", 1),
+    ("Elixir.Code", "ensure_loaded", 1),
+];
+
+/// The modules every function checks are loaded: atoms its body refers to,
+/// if it calls one of `LOAD_CHECKS`. Optional integrations call modules
+/// only after such a check, so missing ones are expected.
+fn load_load_checks(
+    atoms: &[Atom],
+    atom_chunk: &AtomChunk,
+    import_chunk: &ImpTChunk,
+    code: &disasm::Code,
+) -> LoadChecks {
+    let name = |idx: u32| {
+        let idx = idx.checked_sub(1)? as usize;
+        Some((atom_chunk.atoms.get(idx)?.name.as_str(), *atoms.get(idx)?))
+    };
+    let mut load_checks = LoadChecks::default();
+    for function in &code.functions {
+        let check = function.calls.iter().find_map(|call| {
+            let import = match *call {
+                disasm::Call::External(idx) => import_chunk.imports.get(idx as usize)?,
+                _ => return None,
+            };
+            let (module, module_atom) = name(import.module)?;
+            let (fun, fun_atom) = name(import.function)?;
+            LOAD_CHECKS
+                .contains(&(module, fun, import.arity))
+                .then_some((module_atom, fun_atom, import.arity))
+        });
+        let (check, function_name) = match (check, name(function.name)) {
+            (Some(check), Some((_, function_name))) => (check, function_name),
+            _ => continue,
+        };
+        let checked = function
+            .atoms
+            .iter()
+            .filter_map(|&atom| name(atom))
+            .map(|(_, atom)| (atom, check))
+            .collect();
+        load_checks.insert((function_name, function.arity), checked);
+    }
+    load_checks
+}
+
+/// The atoms of every function calling `crypto`, by `crypto` function
+fn load_crypto_atoms(
+    atoms: &[Atom],
+    atom_chunk: &AtomChunk,
+    import_chunk: &ImpTChunk,
+    code: &disasm::Code,
+) -> FxHashMap<Mfa, Vec<Atom>> {
+    let atom = |idx: u32| atoms.get(idx.checked_sub(1)? as usize).copied();
+    let is_crypto = |idx: u32| {
+        let idx = idx.checked_sub(1)? as usize;
+        Some(atom_chunk.atoms.get(idx)?.name == "crypto")
+    };
+    let mut crypto_atoms = FxHashMap::<_, Vec<_>>::default();
+    for function in &code.functions {
+        for call in &function.calls {
+            let import = match *call {
+                disasm::Call::External(idx) => import_chunk.imports.get(idx as usize),
+                _ => None,
+            };
+            let called = import
+                .filter(|import| is_crypto(import.module) == Some(true))
+                .and_then(|import| {
+                    Some((atom(import.module)?, atom(import.function)?, import.arity))
+                });
+            if let Some(called) = called {
+                let entry = crypto_atoms.entry(called).or_default();
+                for found in function.atoms.iter().filter_map(|&idx| atom(idx)) {
+                    if !entry.contains(&found) {
+                        entry.push(found);
+                    }
+                }
+            }
+        }
+    }
+    crypto_atoms
+}
+
+// Compilers before OTP 19 record `{time, {Year, Month, Day, Hour, Minute, Second}}`,
+// newer ones leave it out to keep builds reproducible
+fn load_compile_time(compile_info: &Term) -> Option<Timestamp> {
+    let time = etf::tuple(etf::proplist_get(compile_info, "time")?)?;
+    let parts: Vec<u32> = time
+        .iter()
+        .map(|part| etf::int(part).map(|part| part as u32))
+        .collect::<Option<_>>()?;
+
+    match parts.as_slice() {
+        &[year, month, day, hour, minute, second] => Some(Timestamp {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }),
+        _ => None,
+    }
+}
+
+fn load_literal_strings(literals: &[Term]) -> Vec<String> {
+    let mut strings = vec![];
+    for literal in literals {
+        etf::strings(literal, &mut strings);
+    }
+    strings
+}
+
+fn load_dynamic_calls(interner: &mut Interner, literals: &[Term]) -> Vec<DynamicCall> {
+    let mut calls = vec![];
+    for literal in literals {
+        etf::calls(literal, &mut calls);
+    }
+    let mut calls: Vec<_> = calls
+        .into_iter()
+        .map(|(module, function, arity, fun_ref)| DynamicCall {
+            module: Atom::intern(interner, module),
+            function: Atom::intern(interner, function),
+            arity,
+            fun_ref,
+        })
+        .collect();
+    calls.sort();
+    calls.dedup();
+    calls
+}
+
+/// The `{Name, Value}` entries of the Attr chunk
+fn load_attributes(interner: &mut Interner, attrs: &Term) -> Attributes {
+    let mut attributes = Attributes::default();
+    for attr in etf::list(attrs).unwrap_or_default() {
+        if let Some([name, value]) = etf::tuple(attr) {
+            if let Some(name) = etf::atom(name) {
+                attributes
+                    .entry(Atom::intern(interner, name))
+                    .or_default()
+                    .push(value.clone());
+            }
+        }
+    }
+    attributes
+}
+
+/// Values of the attributes with any of the names, which are only in the
+/// interner if some module has them
+fn attribute<'a>(interner: &Interner, attributes: &'a Attributes, names: &[&str]) -> Vec<&'a Term> {
+    names
+        .iter()
+        .filter_map(|name| attributes.get(&Atom::get(interner, name)?))
+        .flatten()
         .collect()
 }
+
+/// `-deprecated(Spec)` attributes, where every spec is `module`, or
+/// `{F, A}` or `{F, A, Description}` with `'_'` for any function or arity
+fn load_deprecated(interner: &mut Interner, attributes: &Attributes) -> Vec<Deprecation> {
+    let mut deprecated = vec![];
+    for specs in attribute(interner, attributes, &["deprecated"]) {
+        // A single spec is also allowed instead of a list
+        let specs = etf::list(specs).unwrap_or(std::slice::from_ref(specs));
+        for spec in specs {
+            if etf::atom(spec) == Some("module") {
+                deprecated.push(Deprecation {
+                    function: None,
+                    arity: None,
+                    hint: None,
+                });
+                continue;
+            }
+            let (function, arity, hint) = match etf::tuple(spec) {
+                Some([function, arity]) => (function, arity, None),
+                Some([function, arity, hint]) => (function, arity, Some(hint)),
+                _ => continue,
+            };
+            let function = match etf::atom(function) {
+                Some("_") => None,
+                Some(name) => Some(Atom::intern(interner, name)),
+                None => continue,
+            };
+            let arity = match etf::atom(arity) {
+                Some("_") => None,
+                _ => match etf::int(arity) {
+                    Some(arity) => Some(arity as u32),
+                    None => continue,
+                },
+            };
+            let hint = hint.and_then(|hint| match etf::atom(hint) {
+                Some("next_version") => Some("removed in the next version".to_string()),
+                Some("next_major_release") => Some("removed in the next major release".to_string()),
+                Some("eventually") => Some("removed eventually".to_string()),
+                Some(_) => None,
+                None => etf::string(hint),
+            });
+            deprecated.push(Deprecation {
+                function,
+                arity,
+                hint,
+            });
+        }
+    }
+    deprecated
+}
+
+/// `-behaviour(Module)` attributes, also spelled `-behavior`
+fn load_behaviours(interner: &mut Interner, attributes: &Attributes) -> Vec<Atom> {
+    attribute(interner, attributes, &["behaviour", "behavior"])
+        .into_iter()
+        .filter_map(etf::list)
+        .flatten()
+        .filter_map(etf::atom)
+        .map(|behaviour| Atom::intern(interner, behaviour))
+        .collect()
+}
+
+/// The protocol of the `__impl__` attribute Elixir persists in protocol
+/// implementations, as `[protocol: Protocol, for: Type]`
+fn load_protocol_impl(interner: &mut Interner, attributes: &Attributes) -> Option<Atom> {
+    let attr = *attribute(interner, attributes, &["__impl__"]).first()?;
+    let protocol = etf::atom(etf::proplist_get(attr, "protocol")?)?;
+    Some(Atom::intern(interner, protocol))
+}
+
+/// `-ignore_xref` and `-xref_ignore` attributes of `module`, as rebar3
+/// reads them: a module, `{F, A}` of the module itself or `{M, F, A}`
+fn load_ignore_xref(
+    interner: &mut Interner,
+    attributes: &Attributes,
+    module: Atom,
+) -> Vec<IgnoreXref> {
+    let mut ignores = vec![];
+    for specs in attribute(interner, attributes, &["ignore_xref", "xref_ignore"]) {
+        for spec in etf::list(specs).unwrap_or(std::slice::from_ref(specs)) {
+            let ignore = match etf::tuple(spec) {
+                Some([function, arity]) => {
+                    etf::atom(function)
+                        .zip(etf::int(arity))
+                        .map(|(function, arity)| IgnoreXref {
+                            module,
+                            function: Some((Atom::intern(interner, function), arity as u32)),
+                        })
+                }
+                Some([ignored, function, arity]) => (etf::atom(ignored)
+                    .zip(etf::atom(function))
+                    .zip(etf::int(arity)))
+                .map(|((ignored, function), arity)| IgnoreXref {
+                    module: Atom::intern(interner, ignored),
+                    function: Some((Atom::intern(interner, function), arity as u32)),
+                }),
+                _ => etf::atom(spec).map(|ignored| IgnoreXref {
+                    module: Atom::intern(interner, ignored),
+                    function: None,
+                }),
+            };
+            ignores.extend(ignore);
+        }
+    }
+    ignores
+}
+
+/// `-on_load(F/0)`, recorded as `{on_load, [{F, 0}]}`
+fn load_on_load(interner: &mut Interner, attributes: &Attributes) -> Option<Atom> {
+    let spec = etf::list(attribute(interner, attributes, &["on_load"]).first()?)?.first()?;
+    let function = etf::atom(etf::tuple(spec)?.first()?)?;
+    Some(Atom::intern(interner, function))
+}
+
+/// `{parse_transform, Module}` in the compile options
+fn load_parse_transforms(interner: &mut Interner, compile_info: &Term) -> Vec<Atom> {
+    let options = etf::proplist_get(compile_info, "options").and_then(etf::list);
+    options
+        .into_iter()
+        .flatten()
+        .filter_map(|option| match etf::tuple(option)? {
+            [key, module] if etf::atom(key) == Some("parse_transform") => etf::atom(module),
+            _ => None,
+        })
+        .map(|module| Atom::intern(interner, module))
+        .collect()
+}
+
+fn load_source(compile_info: &Term) -> Option<PathBuf> {
+    etf::string(etf::proplist_get(compile_info, "source")?).map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::{
+        analyzer::{AnalysisResult, Analyzer},
+        escript::Escript,
+    };
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((data.len() as u32).to_be_bytes());
+        chunk.extend(data);
+        chunk.resize((chunk.len() + 3) & !3, 0);
+        chunk
+    }
+
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+
+    fn atoms(names: &[&str]) -> Vec<u8> {
+        let mut data = words(&[names.len() as u32]);
+        for name in names {
+            data.push(name.len() as u8);
+            data.extend(name.as_bytes());
+        }
+        chunk(b"AtU8", &data)
+    }
+
+    fn beam(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut beam = b"FOR1".to_vec();
+        beam.extend((body.len() as u32 + 4).to_be_bytes());
+        beam.extend(b"BEAM");
+        beam.extend(body);
+        beam
+    }
+
+    /// `m` exporting `f/0` and importing `erlang:g/1`
+    fn module_chunks() -> Vec<Vec<u8>> {
+        vec![
+            atoms(&["m", "f", "erlang", "g"]),
+            chunk(b"ImpT", &words(&[1, 3, 4, 1])),
+            chunk(b"ExpT", &words(&[1, 2, 0, 2])),
+        ]
+    }
+
+    fn ids(chunks: &[StandardChunk]) -> Vec<&[u8; 4]> {
+        chunks.iter().map(Chunk::id).collect()
+    }
+
+    fn warnings(loader: &Loader) -> Vec<String> {
+        loader.warnings.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn reads_gzipped_beam() {
+        let mut encoder = libflate::gzip::Encoder::new(vec![]).unwrap();
+        encoder.write_all(&beam(&module_chunks())).unwrap();
+        let gzipped = encoder.finish().into_result().unwrap();
+
+        let loader = Loader::new();
+        let chunks = loader.read_chunks(Path::new("m.beam"), &gzipped).unwrap();
+        assert_eq!(ids(&chunks), [b"AtU8", b"ImpT", b"ExpT"]);
+        assert!(warnings(&loader).is_empty());
+    }
+
+    #[test]
+    fn drops_truncated_chunk() {
+        let mut bytes = beam(&module_chunks());
+        bytes.extend(b"Code");
+        bytes.extend(100u32.to_be_bytes());
+        bytes.extend([0; 8]);
+
+        let loader = Loader::new();
+        let chunks = loader.read_chunks(Path::new("m.beam"), &bytes).unwrap();
+        assert_eq!(ids(&chunks), [b"AtU8", b"ImpT", b"ExpT"]);
+        assert_eq!(warnings(&loader), ["m.beam: dropped truncated Code chunk"]);
+    }
+
+    #[test]
+    fn drops_unreadable_chunk() {
+        let mut chunks = module_chunks();
+        // Claims an export it doesn't have
+        chunks.insert(1, chunk(b"LocT", &words(&[1])));
+
+        let loader = Loader::new();
+        let chunks = loader
+            .read_chunks(Path::new("m.beam"), &beam(&chunks))
+            .unwrap();
+        assert_eq!(ids(&chunks), [b"AtU8", b"ImpT", b"ExpT"]);
+        let warnings = warnings(&loader);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("m.beam: dropped unreadable LocT chunk: "));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let loader = Loader::new();
+        let err = loader
+            .read_chunks(Path::new("m.beam"), b"not a beam")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "not a BEAM file");
+    }
+
+    #[test]
+    fn drops_entries_with_invalid_atom_index() {
+        let chunks = [
+            atoms(&["m", "f", "erlang", "g"]),
+            chunk(b"ImpT", &words(&[2, 3, 4, 1, 0, 4, 1])),
+            chunk(b"ExpT", &words(&[2, 2, 0, 2, 9, 1, 3])),
+        ];
+
+        let loader = Loader::new();
+        let (name, module) = loader
+            .parse_module(Path::new("m.beam"), &beam(&chunks))
+            .unwrap();
+        let interner = loader.interner.lock().unwrap();
+        let atom = |name| Atom::get(&interner, name).unwrap();
+        assert_eq!(name, atom("m"));
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.imports[&atom("erlang")], [(atom("g"), 1)]);
+        assert_eq!(module.exports, [(atom("f"), 0)]);
+        assert_eq!(
+            warnings(&loader),
+            [
+                "m.beam: dropped 1 of the 2 ImpT chunk entries, with an invalid atom index",
+                "m.beam: dropped 1 of the 2 ExpT chunk entries, with an invalid atom index",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_calls_to_unexported_functions() {
+        // `callee` defines `foo/3` without exporting it, `caller` calls it
+        let callee = [
+            atoms(&["callee", "bar", "foo"]),
+            chunk(b"ImpT", &words(&[0])),
+            chunk(b"ExpT", &words(&[1, 2, 0, 2])),
+            chunk(b"LocT", &words(&[2, 3, 3, 4, 9, 1, 5])),
+        ];
+        let caller = [
+            atoms(&["caller", "go", "callee", "foo"]),
+            chunk(b"ImpT", &words(&[1, 3, 4, 3])),
+            chunk(b"ExpT", &words(&[1, 2, 0, 2])),
+        ];
+        let path = PathBuf::from("tool");
+        let mut files = BTreeMap::new();
+        files.insert(path.join("a/ebin/callee.beam"), beam(&callee));
+        files.insert(path.join("a/ebin/caller.beam"), beam(&caller));
+        files.insert(
+            path.join("a/ebin/a.app"),
+            b"{application, a, [{modules, [callee, caller]}]}.".to_vec(),
+        );
+
+        let loader = Loader::new();
+        loader.read_escript(&Escript { path, files }).unwrap();
+        assert_eq!(
+            loader.warnings(),
+            ["tool/a/ebin/callee.beam: dropped 1 of the 2 LocT chunk entries, with an invalid atom index"]
+        );
+        let analyzer = Analyzer::new(loader.finish());
+        let app = analyzer.find_app("a").unwrap();
+        let messages: Vec<_> = analyzer
+            .run(&[app])
+            .iter()
+            .filter(|(_, result)| matches!(result, AnalysisResult::UnexportedFunction { .. }))
+            .map(|(_, result)| result.fmt(analyzer.interner()))
+            .collect();
+        assert_eq!(
+            messages,
+            ["function not exported: callee:foo/3 exists but is not exported"]
+        );
+    }
+}
@@ -1,82 +1,1652 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, IsTerminal, Write},
+    mem,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use serde_json::json;
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
 
-mod analyzer;
-mod loader;
-mod types;
+use xref::{
+    analyzer::{AnalysisResult, Analyzer, Sample, SourceRoot, CHECKS},
+    api,
+    baseline::Baseline,
+    closure, compat,
+    completions::{self, Shell},
+    config::Config,
+    diff, discover,
+    escript::Escript,
+    graph, ignore, impact,
+    loader::{Cache, Loader},
+    metrics, otp,
+    output::{Output, OutputFormat, Target},
+    pattern::{FunctionPattern, Pattern},
+    progress,
+    release::Release,
+    report::{self, ColorChoice, Format, Human, Options},
+    start,
+    timestamp::Timestamp,
+    tree,
+    types::{Atom, Workspace},
+    watch::Snapshot,
+    why,
+};
 
-use analyzer::Analyzer;
-use loader::Loader;
-use types::Atom;
+const COMMANDS: &[&str] = &[
+    "check",
+    "graph",
+    "query",
+    "list",
+    "list-apps",
+    "list-modules",
+    "manifest",
+    "stats",
+    "tree",
+    "why",
+    "closure",
+    "start-order",
+    "diff",
+    "impact",
+    "completions",
+    "help",
+];
+
+const USAGE: &str = "\
+usage: xref <command> [<lib path>...] [--lib-path <path>...] [--erl-libs] [--release <path>]
+                      [--files-from <file>|-] [--escript <file>] [--config <file>]
+                      [--ignore-file <file>] [--check <check>...] [--entry-point <mfa>...]
+                      [--reachability]
+                      [--exclude-app <pattern>...] [--exclude-module <pattern>...]
+                      [--color auto|always|never] [-v | -vv] [--watch]
+                      [--jobs <n> | --no-parallel] [options]
+
+Without lib paths, a rebar3 or Mix project in the current directory is analyzed:
+its _build/<profile>/lib (--profile, default `default` for rebar3 and $MIX_ENV or
+`dev` for Mix) and by default its own apps, umbrella apps included. In a release
+(`releases` and `lib` directories) every app in lib is analyzed.
+Otherwise the lib paths in ERL_LIBS are used; --erl-libs adds them to the
+given ones. Lib paths can be globs (`_build/*/lib`), an `ebin` directory loads a single
+application, and `<app>@<dir>` loads a directory as the named application in
+addition to the others. App and module flags take comma separated lists.
+Applications that weren't built, with an .app.src file in src but no .beam files,
+are read from their .erl files: their exports, behaviours, remote calls and fun
+captures, without expanding macros other than ?MODULE.
+Patterns are globs (`vendor_*`) or regexes between slashes (`/_pb$/`).
+
+--check runs only the named checks: undefined_module, undefined_function,
+undefined_dynamic_call (`{M, F, Args}` literals), undefined_capture (`fun M:F/A`
+references), undefined_behaviour, undefined_parse_transform, deprecated_call,
+otp_upgrade (with --otp-version), forbidden_call (with forbidden_calls in
+xref.toml), layer_violation (with layers in xref.toml), group_boundary (with
+groups in xref.toml), test_code_call (calls into *_SUITE, *_tests and the
+test_code of xref.toml), missing_dependency, dependency_not_found,
+unused_dependency, self_dependency, undeclared_runtime_dependency,
+unused_runtime_dependency (with runtime_dependencies in the .app file),
+locals_not_used, missing_source, missing_priv_file, missing_on_load,
+duplicate_module, different_build, otp_module_clash, registered_name_clash,
+unlisted_module, missing_beam, app_callback, app_cycle and, only when named,
+unused_export, dead_module, module_cycle, nif_library (calls to NIF stubs of
+applications without a shared library in priv), redundant_dependency (unused
+dependencies another dependency already depends on), risky_call (every call of
+os:cmd, open_port, unsafe binary_to_term, weak crypto and the risky_calls of
+xref.toml) and internal_module_call (calls from other applications into modules
+hidden from the docs, like with @moduledoc false, or matching the
+internal_modules of xref.toml, and into functions hidden with @doc false). Entry
+points (`mod:fun/arity`, with patterns) are called from outside of the loaded
+code and never reported as unused; their modules, like application callbacks,
+are never dead. kernel, stdlib and the start_only_deps of xref.toml are never
+unused dependencies.
+
+--show-paths adds the .beam file of every finding and, for modules compiled
+with debug_info, the source file and line of the call it's about, which the
+json and gitlab formats always include.
+
+--reachability reports the exports and modules that aren't reached from the
+roots instead, following the calls of every function: entry points,
+application and behaviour callbacks, and the `{M, F, Args}` child specs and
+funs of the modules reached. Calls from unreached code don't count.
+
+--files-from loads the .beam files listed in a file or on stdin, one per line,
+grouped into apps by directory, and analyzes all of them.
+
+--escript loads the applications bundled in an escript's archive, or its single
+compiled module, and analyzes all of them. Give OTP's lib directory as a lib
+path: calls to modules neither of them provides are reported.
+
+--release loads only the app versions listed in a release's .rel file (or its
+releases/<vsn> or root directory), from its lib directory by default, and
+analyzes all of them. Calls to modules the release doesn't ship are reported.
+
+Settings are also read from xref.toml and ignores from .xrefignore in the
+current directory. --profile also selects a [profile.<name>] of xref.toml.
+While loading, a progress bar is shown if stderr is a terminal.
+-v logs the phases of a run and their timings to stderr, -vv every application.
+Loading and analysis use a thread per core, unless limited with --jobs;
+--no-parallel runs everything on a single thread.
+--watch runs the command again whenever a .beam or .app file in the lib paths,
+or a source of an application read from source, changes, parsing only the
+changed .beam files again.
+
+commands:
+  check                     report problems in the analyzed applications (default)
+      --analyze <app>... | --analyze-all | --analyze-module <module>...
+      --format <format> --output [<format>=]<path>...
+      --show-paths --summary --collapse-otp
+      --ignore-older-than <date> --src-root [<recorded>=]<dir>...
+      --sample <fraction> --seed <n>
+      --baseline <file> --write-baseline <file>
+      --max-warnings [<check>=]<n>...
+                            fail when there are more findings, in total or of a check
+      --min-cycle-size <n>  smallest module cycle reported by module_cycle (2)
+      --otp-version <release>
+                            report calls to OTP functions deprecated or removed by
+                            that release, e.g. 27
+      --otp <version>=<path>...
+                            check against every OTP installation and show which
+                            findings only appear under some versions
+  graph                     application dependency graph
+      --analyze <app>... --format mermaid|dot --output [<format>=]<path>...
+  query owner <module>      application and file providing a module
+  query implementors <fun>[/<arity>]
+                            modules exporting a function
+  query dep <app>           how the workspace uses a single dependency
+  query calls <mod>:<fun>/<arity>
+                            functions a function calls, including local ones
+  query api <app> [--format human|json]
+                            exports of an application other applications call,
+                            and from which modules
+  list apps                 loaded applications, their versions, module counts and paths
+  list modules [--app <app>...]
+                            loaded modules and their applications
+                            (also as list-apps and list-modules)
+  manifest deps [--analyze <app>... | --analyze-all] [--per-app]
+                            declared and actually used dependencies as JSON
+  tree [<app>...]
+                            application dependency tree from the .app files, from
+                            the analyzed apps or the ones nothing depends on
+  why <app> <dependency> [--all]
+                            shortest (or every) dependency path between applications,
+                            with the calls behind every step
+  closure [<app>...]        smallest set of applications needed to run the given ones,
+                            following the calls between modules
+  start-order [<app>...]    order the applications can be started in, dependencies
+                            first, or the dependencies that make it impossible
+  stats [--top-couplings <n>] [--metrics [--top-modules <n>]] [--format human|json]
+                            workspace totals and most coupled applications;
+                            with --metrics, fan-in, fan-out and afferent and
+                            efferent coupling of every application and the
+                            modules with the most (all of them as JSON)
+  impact <app> --release [<name>=]<lib path>... [--format human|json]
+                            which releases ship an application and use its exports
+  diff --base-lib-path <path>...
+                            HTML page comparing the workspace with a base
+  completions bash|zsh|fish shell completion script, completing app and module
+                            names of the project in the current directory
+";
 
 #[derive(Debug)]
-struct Args {
-    lib_paths: Vec<PathBuf>,
+enum Command {
+    Check(Box<CheckArgs>),
+    Graph {
+        roots: Vec<String>,
+        outputs: Vec<Output>,
+    },
+    Query(Query),
+    List(List),
+    Manifest {
+        analyze: Vec<String>,
+        analyze_all: bool,
+        per_app: bool,
+    },
+    Stats {
+        top_couplings: usize,
+        /// Coupling metrics of apps and modules, with how many modules
+        metrics: Option<usize>,
+        json: bool,
+    },
+    Diff {
+        base_lib_paths: Vec<PathBuf>,
+    },
+    Impact {
+        app: String,
+        releases: Vec<(String, PathBuf)>,
+        json: bool,
+    },
+    Completions(Shell),
+    Tree {
+        roots: Vec<String>,
+    },
+    Why {
+        from: String,
+        to: String,
+        all: bool,
+    },
+    Closure {
+        roots: Vec<String>,
+    },
+    StartOrder {
+        roots: Vec<String>,
+    },
+}
+
+#[derive(Debug)]
+enum Query {
+    Owner(String),
+    Implementors { fun: String, arity: Option<u32> },
+    Dep(String),
+    Calls(String),
+    Api { app: String, json: bool },
+}
+
+#[derive(Debug)]
+enum List {
+    Apps,
+    Modules { apps: Vec<String> },
+}
+
+#[derive(Debug)]
+struct CheckArgs {
     analyze: Vec<String>,
     analyze_all: bool,
+    analyze_modules: Vec<String>,
+    outputs: Vec<Output>,
+    show_paths: bool,
+    summary: bool,
+    collapse_otp: bool,
+    ignore_older_than: Option<Timestamp>,
+    src_roots: Vec<SourceRoot>,
+    sample: Option<Sample>,
+    baseline: Option<PathBuf>,
+    write_baseline: Option<PathBuf>,
+    max_warnings: Vec<Threshold>,
+    min_cycle_size: Option<usize>,
+    otp_version: Option<u32>,
+    /// OTP installations, by version, for the compatibility matrix
+    otps: Vec<(String, PathBuf)>,
 }
 
+/// `--max-warnings [<check>=]<n>`, failing the run when there are more
+/// findings, of the check or in total
+#[derive(Debug)]
+struct Threshold {
+    check: Option<String>,
+    max: usize,
+}
+
+impl FromStr for Threshold {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Threshold> {
+        let (check, max) = match s.split_once('=') {
+            Some((check, max)) => {
+                if !CHECKS.contains(&check) {
+                    anyhow::bail!(
+                        "unknown check: {} (expected one of: {})",
+                        check,
+                        CHECKS.join(", ")
+                    );
+                }
+                (Some(check.to_string()), max)
+            }
+            None => (None, s),
+        };
+        let max = max
+            .parse()
+            .with_context(|| format!("invalid --max-warnings: {}", s))?;
+        Ok(Threshold { check, max })
+    }
+}
+
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    lib_paths: Vec<PathBuf>,
+    /// Single applications given as `<app>@<dir>`
+    named_apps: Vec<(String, PathBuf)>,
+    color: ColorChoice,
+    config: Config,
+    release: Option<Release>,
+    verbosity: usize,
+    watch: bool,
+    jobs: Option<usize>,
+    /// Files to load in addition to the lib paths
+    files: Vec<PathBuf>,
+    escript: Option<Escript>,
+}
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
 fn main() -> Result<()> {
-    let args = parse_args()?;
-    let loader = Loader::new();
+    let args = match parse_args()? {
+        Some(args) => args,
+        None => {
+            print!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    init_tracing(args.verbosity);
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
+    }
+    if let Command::Completions(shell) = args.command {
+        return completions::write(shell, &mut io::stdout().lock());
+    }
+    if let Command::Impact {
+        app,
+        releases,
+        json,
+    } = &args.command
+    {
+        let releases = releases
+            .iter()
+            .map(|(name, path)| {
+                let loader = Loader::new().with_excludes(args.config.exclude.clone());
+                loader.read_libs(std::slice::from_ref(path))?;
+                Ok((name.clone(), Analyzer::new(loader.finish())))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-    loader.read_libs(&args.lib_paths)?;
+        let matrix = impact::impact(app, &releases);
+        let stdout = &mut io::stdout().lock();
+        return if *json {
+            impact::write_json(&matrix, stdout)
+        } else {
+            impact::write_human(&matrix, stdout)
+        };
+    }
 
-    let (mut interner, modules, app_modules, app_deps) = loader.finish();
+    if !args.watch {
+        return run(&args, Cache::default()).map(drop);
+    }
+    let mut cache = Cache::default();
+    loop {
+        let snapshot = Snapshot::take(&args.lib_paths);
+        if io::stdout().is_terminal() {
+            print!("\x1b[2J\x1b[H");
+        }
+        cache = match run(&args, mem::take(&mut cache)) {
+            Ok(cache) => cache,
+            Err(err) => {
+                eprintln!("Error: {:?}", err);
+                Cache::default()
+            }
+        };
+        eprintln!("\nwatching the lib paths for changes...");
+        snapshot.wait_for_change(&args.lib_paths, WATCH_INTERVAL);
+    }
+}
 
-    println!("\ntotal apps: {}", app_modules.len());
-    println!("total app dependencies: {}", app_deps.edge_count());
-    println!("total modules: {}", modules.len());
-    println!("total atoms: {}", interner.len());
+/// Loads the lib paths and runs the command, returning the parsed modules
+/// for the next run in watch mode
+fn run(args: &Args, cache: Cache) -> Result<Cache> {
+    if let Command::Check(check_args) = &args.command {
+        if !check_args.otps.is_empty() {
+            check_otp_matrix(args, check_args)?;
+            return Ok(cache);
+        }
+    }
 
-    let analyzer = Analyzer::new(modules, app_modules.clone(), app_deps.clone());
+    let mut loader = Loader::new()
+        .with_excludes(args.config.exclude.clone())
+        .with_test_lib_paths(args.config.test_code.lib_paths.clone())
+        .with_cache(cache);
+    if let Some(release) = &args.release {
+        loader = loader.with_release(release.clone());
+    }
+    let bar = progress::Bar::stderr().filter(|_| args.verbosity == 0);
+    if let Some(bar) = &bar {
+        loader = loader.with_progress(bar.callback());
+    }
 
-    let analyze: Vec<_> = if args.analyze_all {
-        app_deps.nodes().collect()
+    let loaded = loader
+        .read_libs(&args.lib_paths)
+        .and_then(|()| loader.read_named(&args.named_apps))
+        .and_then(|()| loader.read_files(&args.files))
+        .and_then(|()| match &args.escript {
+            Some(escript) => loader.read_escript(escript),
+            None => Ok(()),
+        });
+    if let Some(bar) = &bar {
+        bar.finish();
+    }
+    loaded?;
+    for warning in loader.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+
+    let (workspace, cache) = loader.finish_cached();
+
+    if let Some(release) = &args.release {
+        for (app, vsn) in &release.apps {
+            let atom = workspace.interner.get(app).map(Atom);
+            if atom.and_then(|app| workspace.app_versions.get(&app)) != Some(vsn) {
+                eprintln!(
+                    "warning: {}-{} of release {} {} is not in the lib paths",
+                    app, vsn, release.name, release.version
+                );
+            }
+        }
+    }
+
+    if let Command::Diff { base_lib_paths } = &args.command {
+        let base = Loader::new().with_excludes(args.config.exclude.clone());
+        base.read_libs(base_lib_paths)?;
+
+        let diff = diff::diff(&base.finish(), &workspace);
+        diff::write_html(&diff, &mut io::stdout().lock())?;
+        return Ok(cache);
+    }
+
+    let analyzer = build_analyzer(workspace, args)?;
+
+    let result = match &args.command {
+        Command::Check(check_args) => check(&analyzer, check_args, &args.config, args.color),
+        Command::Graph { roots, outputs } => write_graph(&analyzer, roots, outputs),
+        Command::Query(query) => run_query(&analyzer, query, args.color),
+        Command::List(list) => write_list(&analyzer, list),
+        Command::Manifest {
+            analyze,
+            analyze_all,
+            per_app,
+        } => write_manifest(&analyzer, analyze, *analyze_all, *per_app),
+        Command::Tree { roots } => write_tree(&analyzer, roots),
+        Command::Why { from, to, all } => write_why(&analyzer, from, to, *all),
+        Command::Closure { roots } => write_closure(&analyzer, roots),
+        Command::StartOrder { roots } => write_start_order(&analyzer, roots),
+        Command::Stats {
+            top_couplings,
+            metrics,
+            json,
+        } => write_stats(&analyzer, *top_couplings, *metrics, *json, args.color),
+        Command::Diff { .. } | Command::Impact { .. } | Command::Completions(_) => unreachable!(),
+    };
+    result.map(|()| cache)
+}
+
+/// The analyzer with the settings of the config and the command
+fn build_analyzer(workspace: Workspace, args: &Args) -> Result<Analyzer> {
+    let mut analyzer = Analyzer::new(workspace)
+        .with_excludes(args.config.exclude.clone())
+        .with_entry_points(args.config.entry_points.clone())
+        .with_reachability(args.config.reachability)
+        .with_start_only_deps(args.config.start_only_deps.clone())
+        .with_forbidden_calls(args.config.forbidden_calls.clone())
+        .with_risky_calls(args.config.risky_calls.clone())
+        .with_layers(args.config.layers.clone())
+        .with_groups(args.config.groups.clone())
+        .with_internal_modules(args.config.internal_modules.clone())
+        .with_test_code(args.config.test_code.clone())
+        .with_severities(
+            args.config
+                .severity
+                .iter()
+                .map(|(check, &severity)| (check.clone(), severity))
+                .collect(),
+        );
+    if let Some(checks) = &args.config.checks {
+        analyzer = analyzer.with_checks(checks.clone());
+    }
+    if let Command::Check(check_args) = &args.command {
+        if !check_args.analyze_modules.is_empty() {
+            let modules = check_args
+                .analyze_modules
+                .iter()
+                .map(|name| {
+                    analyzer
+                        .atom(name)
+                        .filter(|&module| analyzer.owner(module).is_some())
+                        .with_context(|| format!("unknown module: {}", name))
+                })
+                .collect::<Result<_>>()?;
+            analyzer = analyzer.with_modules(modules);
+        }
+        analyzer = analyzer.with_source_roots(check_args.src_roots.clone());
+        if let Some(sample) = check_args.sample {
+            analyzer = analyzer.with_sample(sample);
+        }
+        if let Some(size) = check_args.min_cycle_size.or(args.config.min_cycle_size) {
+            analyzer = analyzer.with_min_cycle_size(size);
+        }
+        if let Some(version) = check_args.otp_version.or(args.config.otp_version) {
+            analyzer = analyzer.with_otp_version(version);
+        }
+    }
+    Ok(analyzer)
+}
+
+/// Runs the checks with the lib paths against every OTP installation,
+/// showing which findings only appear under some of them
+fn check_otp_matrix(args: &Args, check_args: &CheckArgs) -> Result<()> {
+    let today = Timestamp::from_system_time(SystemTime::now());
+    let runs = check_args
+        .otps
+        .iter()
+        .map(|(version, otp_lib)| {
+            let _span = tracing::info_span!("otp", version = version.as_str()).entered();
+            let loader = Loader::new()
+                .with_excludes(args.config.exclude.clone())
+                .with_test_lib_paths(args.config.test_code.lib_paths.clone());
+            let mut lib_paths = args.lib_paths.clone();
+            lib_paths.push(otp_lib.clone());
+            loader.read_libs(&lib_paths)?;
+            loader.read_named(&args.named_apps)?;
+            loader.read_files(&args.files)?;
+            if let Some(escript) = &args.escript {
+                loader.read_escript(escript)?;
+            }
+
+            let analyzer = build_analyzer(loader.finish(), args)?;
+            let analyze = analyzed_apps(&analyzer, check_args)?;
+            let mut results = analyzer.run(&analyze);
+            ignore::apply(&args.config.ignore, &mut results, &analyzer, today);
+            Ok((version.clone(), compat::findings(&analyzer, &results)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let matrix = compat::matrix(runs);
+    for output in &check_args.outputs {
+        let out = &mut output.target.open()?;
+        match output.format {
+            OutputFormat::Report(Format::Json) => compat::write_json(&matrix, out)?,
+            OutputFormat::Report(Format::Human) => compat::write_human(&matrix, out)?,
+            _ => anyhow::bail!("the OTP compatibility matrix is only written as human or json"),
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// The applications to check: the analyzed ones and the owners of the
+/// analyzed modules, which give the modules their dependencies
+fn analyzed_apps(analyzer: &Analyzer, args: &CheckArgs) -> Result<Vec<Atom>> {
+    let mut analyze: Vec<_> = if args.analyze_all {
+        analyzer.app_modules().keys().copied().collect()
     } else {
         args.analyze
             .iter()
-            .map(|app| Atom::intern(&mut interner, app))
-            .collect()
+            .map(|app| analyzer.find_app(app))
+            .collect::<Result<_>>()?
     };
+    for module in &args.analyze_modules {
+        let owner = analyzer
+            .atom(module)
+            .and_then(|module| analyzer.owner(module));
+        analyze.extend(owner.filter(|app| !analyze.contains(app)));
+    }
+    Ok(analyze)
+}
+
+fn check(analyzer: &Analyzer, args: &CheckArgs, config: &Config, color: ColorChoice) -> Result<()> {
+    let interner = analyzer.interner();
+    let analyze = analyzed_apps(analyzer, args)?;
+
+    let mut results = analyzer.run(&analyze);
+    let today = Timestamp::from_system_time(SystemTime::now());
+    let outcome = ignore::apply(&config.ignore, &mut results, analyzer, today);
+
+    for ignore in outcome.expired {
+        eprintln!(
+            "warning: ignore {} expired on {}, its findings are reported again",
+            ignore,
+            ignore.expires.unwrap()
+        );
+    }
+    // A sample or a subset of the applications doesn't produce all the
+    // findings, an ignore can only be called stale when it had a chance
+    if args.sample.is_none() {
+        let stale = outcome.stale.into_iter().filter(|ignore| {
+            let app_analyzed = ignore.app.as_ref().is_none_or(|app| {
+                analyze
+                    .iter()
+                    .any(|analyzed| analyzed.resolve(interner) == Some(app))
+            });
+            let check_enabled = ignore.check.as_ref().is_none_or(|check| {
+                config
+                    .checks
+                    .as_ref()
+                    .is_none_or(|checks| checks.contains(check))
+            });
+            app_analyzed && check_enabled
+        });
+        for ignore in stale {
+            eprintln!(
+                "warning: stale ignore {}: it doesn't match any finding",
+                ignore
+            );
+        }
+    }
+
+    if let Some(cutoff) = args.ignore_older_than {
+        results.retain(|&(module, _)| {
+            analyzer
+                .module(module)
+                .and_then(|module| module.compiled)
+                .is_none_or(|compiled| compiled >= cutoff)
+        });
+    }
+
+    if let Some(path) = &args.write_baseline {
+        Baseline::new(&results, interner).save(path)?;
+    }
+    if let Some(path) = &args.baseline {
+        let baseline = Baseline::load(path)?;
+        results.retain(|(subject, result)| !baseline.contains(*subject, result, interner));
+    }
+
+    // Everything is loaded and analyzed once, no matter how many outputs
+    for output in &args.outputs {
+        let color = color.enabled(output.target.is_terminal());
+        let out = &mut output.target.open()?;
+
+        match output.format {
+            OutputFormat::Report(Format::Human) => {
+                write_human(args, analyzer, &analyze, &results, color, out)?
+            }
+            OutputFormat::Report(format) => {
+                let options = Options {
+                    color,
+                    show_paths: args.show_paths,
+                };
+                report::write(format, &results, analyzer, interner, options, out)?
+            }
+            OutputFormat::Graph(format) => {
+                let roots = if args.analyze_all { &[][..] } else { &analyze };
+                graph::write_app_graph(format, analyzer.app_deps(), roots, interner, out)?
+            }
+        }
+
+        out.flush()?;
+    }
+
+    for threshold in &args.max_warnings {
+        let count = results
+            .iter()
+            .filter(|(_, result)| {
+                threshold
+                    .check
+                    .as_ref()
+                    .is_none_or(|check| check == result.check_name())
+            })
+            .count();
+        if count > threshold.max {
+            match &threshold.check {
+                Some(check) => anyhow::bail!(
+                    "{} {} findings, more than the allowed {}",
+                    count,
+                    check,
+                    threshold.max
+                ),
+                None => anyhow::bail!(
+                    "{} findings, more than the allowed {}",
+                    count,
+                    threshold.max
+                ),
+            }
+        }
+    }
 
-    println!("\n");
-    for &app in &analyze {
-        println!(
-            "{}: {:?}",
-            app.resolve(&interner).unwrap(),
-            app_deps
-                .neighbors_directed(app, petgraph::EdgeDirection::Outgoing)
-                .flat_map(|name| name.resolve(&interner))
-                .collect::<Vec<_>>()
-        )
+    Ok(())
+}
+
+fn write_graph(analyzer: &Analyzer, roots: &[String], outputs: &[Output]) -> Result<()> {
+    let roots = roots
+        .iter()
+        .map(|app| analyzer.find_app(app))
+        .collect::<Result<Vec<_>>>()?;
+    for output in outputs {
+        let format = match output.format {
+            OutputFormat::Graph(format) => format,
+            OutputFormat::Report(_) => {
+                anyhow::bail!("the graph command only writes graph formats (mermaid, dot)")
+            }
+        };
+        let out = &mut output.target.open()?;
+        graph::write_app_graph(
+            format,
+            analyzer.app_deps(),
+            &roots,
+            analyzer.interner(),
+            out,
+        )?;
+        out.flush()?;
     }
+    Ok(())
+}
+
+fn run_query(analyzer: &Analyzer, query: &Query, color: ColorChoice) -> Result<()> {
+    let interner = analyzer.interner();
+    let stdout = &mut io::stdout().lock();
+
+    match query {
+        Query::Owner(module) => {
+            let module = analyzer
+                .atom(module)
+                .with_context(|| format!("unknown module: {}", module))?;
+            let app = analyzer.owner(module).with_context(|| {
+                format!("unknown module: {}", module.resolve(interner).unwrap())
+            })?;
+
+            writeln!(
+                stdout,
+                "{}: {} ({})",
+                app.resolve(interner).unwrap(),
+                analyzer.app_path(app).unwrap().display(),
+                analyzer.module(module).unwrap().path.display()
+            )?;
+        }
+        Query::Implementors { fun, arity } => {
+            // A function nothing exports was never interned
+            let fun = match analyzer.atom(fun) {
+                Some(fun) => fun,
+                None => return Ok(()),
+            };
+            for (module, arity) in analyzer.implementors(fun, *arity) {
+                writeln!(
+                    stdout,
+                    "{}:{}/{} ({})",
+                    module.resolve(interner).unwrap(),
+                    fun.resolve(interner).unwrap(),
+                    arity,
+                    analyzer
+                        .owner(module)
+                        .and_then(|app| app.resolve(interner))
+                        .unwrap_or("-")
+                )?;
+            }
+        }
+        Query::Dep(dep) => {
+            let dep = analyzer.find_app(dep)?;
+            let reporter = Human::new(interner, color.enabled(io::stdout().is_terminal()));
 
-    let results = analyzer.run(&analyze);
+            writeln!(
+                stdout,
+                "{} ({})",
+                dep.resolve(interner).unwrap(),
+                analyzer.app_path(dep).unwrap().display()
+            )?;
+            reporter.app_deps(&[dep], analyzer.app_deps(), stdout)?;
+            writeln!(stdout)?;
+            reporter.findings(&analyzer.run(&[dep]), analyzer, stdout)?;
 
-    println!("\n");
-    for (module, result) in results {
-        println!(
-            "{}: {}",
-            module.resolve(&interner).unwrap(),
-            result.fmt(&interner)
-        )
+            let calls = analyzer.calls_into(dep);
+            let mut usage = BTreeMap::<_, Vec<_>>::new();
+            for &module in &analyzer.app_modules()[&dep] {
+                for &(fun, arity) in &analyzer.module(module).unwrap().exports {
+                    if fun.resolve(interner) != Some("module_info") {
+                        usage.entry((module, fun, arity)).or_default();
+                    }
+                }
+            }
+            for &(caller, module, fun, arity) in &calls {
+                if let Some(callers) = usage.get_mut(&(module, fun, arity)) {
+                    callers.push(caller);
+                }
+            }
+            let mut usage: Vec<_> = usage.into_iter().collect();
+            usage.sort_by_key(|((module, fun, arity), _)| {
+                (module.resolve(interner), fun.resolve(interner), *arity)
+            });
+
+            reporter.export_usage(&usage, stdout)?;
+        }
+        Query::Api { app, json } => {
+            let app = analyzer.find_app(app)?;
+            let exports = api::surface(analyzer, app);
+            if *json {
+                api::write_json(analyzer, &exports, stdout)?;
+            } else {
+                api::write_human(analyzer, &exports, stdout)?;
+            }
+        }
+        Query::Calls(function) => {
+            let usage = "expected <mod>:<fun>/<arity>";
+            let (mfa, arity) = function.rsplit_once('/').context(usage)?;
+            let (module, fun) = mfa.split_once(':').context(usage)?;
+            let arity: u32 = arity.parse().context(usage)?;
+            let (module, fun) = analyzer
+                .atom(module)
+                .zip(analyzer.atom(fun))
+                .with_context(|| format!("unknown function: {}", function))?;
+            let calls = analyzer
+                .function_calls(module, fun, arity)
+                .with_context(|| format!("unknown function: {}", function))?;
+
+            for &(module, fun, arity) in calls {
+                writeln!(
+                    stdout,
+                    "{}:{}/{} ({})",
+                    module.resolve(interner).unwrap(),
+                    fun.resolve(interner).unwrap(),
+                    arity,
+                    analyzer
+                        .owner(module)
+                        .and_then(|app| app.resolve(interner))
+                        .unwrap_or("-")
+                )?;
+            }
+        }
     }
 
     Ok(())
 }
 
-fn parse_args() -> Result<Args> {
+fn write_tree(analyzer: &Analyzer, roots: &[String]) -> Result<()> {
+    let roots = roots
+        .iter()
+        .map(|app| analyzer.find_app(app))
+        .collect::<Result<Vec<_>>>()?;
+    tree::write_tree(analyzer, &roots, &mut io::stdout().lock())
+}
+
+fn write_why(analyzer: &Analyzer, from: &str, to: &str, all: bool) -> Result<()> {
+    let (from, to) = (analyzer.find_app(from)?, analyzer.find_app(to)?);
+    let paths = why::paths(analyzer, from, to, all);
+    if paths.is_empty() {
+        anyhow::bail!(
+            "{} doesn't depend on {}",
+            analyzer.name(from),
+            analyzer.name(to)
+        );
+    }
+    why::write_paths(analyzer, &paths, &mut io::stdout().lock())
+}
+
+fn write_closure(analyzer: &Analyzer, roots: &[String]) -> Result<()> {
+    if roots.is_empty() {
+        anyhow::bail!("usage: xref closure <app>...");
+    }
+    let roots = roots
+        .iter()
+        .map(|app| analyzer.find_app(app))
+        .collect::<Result<Vec<_>>>()?;
+    let needed = closure::needed_apps(analyzer, &roots);
+    closure::write(analyzer, &roots, &needed, &mut io::stdout().lock())
+}
+
+fn write_start_order(analyzer: &Analyzer, roots: &[String]) -> Result<()> {
+    let roots = roots
+        .iter()
+        .map(|app| analyzer.find_app(app))
+        .collect::<Result<Vec<_>>>()?;
+    match start::order(analyzer, &roots) {
+        Ok(order) => start::write(analyzer, &order, &mut io::stdout().lock()),
+        Err(cycles) => {
+            let cycles: Vec<_> = cycles
+                .iter()
+                .map(|edges| {
+                    let edges: Vec<_> = edges
+                        .iter()
+                        .map(|&(from, to)| {
+                            format!("{} -> {}", analyzer.name(from), analyzer.name(to))
+                        })
+                        .collect();
+                    format!("  {}", edges.join(", "))
+                })
+                .collect();
+            anyhow::bail!(
+                "the applications can't be started in any order, because of the dependency cycles:\n{}",
+                cycles.join("\n")
+            )
+        }
+    }
+}
+
+fn write_list(analyzer: &Analyzer, list: &List) -> Result<()> {
+    let interner = analyzer.interner();
+    let stdout = &mut io::stdout().lock();
+
+    match list {
+        List::Apps => {
+            let mut apps: Vec<_> = analyzer
+                .app_modules()
+                .iter()
+                .map(|(&app, modules)| {
+                    (
+                        app.resolve(interner).unwrap(),
+                        analyzer.app_version(app).unwrap_or("-"),
+                        modules.len(),
+                        analyzer.app_path(app).unwrap(),
+                    )
+                })
+                .collect();
+            apps.sort_unstable();
+            let width = apps.iter().map(|(app, ..)| app.len()).max().unwrap_or(0);
+            let vsn_width = apps.iter().map(|(_, vsn, ..)| vsn.len()).max().unwrap_or(0);
+            for (app, vsn, modules, path) in apps {
+                writeln!(
+                    stdout,
+                    "{:width$} {:vsn_width$} {:>4} {}",
+                    app,
+                    vsn,
+                    modules,
+                    path.display(),
+                    width = width,
+                    vsn_width = vsn_width
+                )?;
+            }
+        }
+        List::Modules { apps } => {
+            let apps = apps
+                .iter()
+                .map(|app| analyzer.find_app(app))
+                .collect::<Result<Vec<_>>>()?;
+            let mut modules: Vec<_> = analyzer
+                .modules_rev()
+                .iter()
+                .filter(|(_, app)| apps.is_empty() || apps.contains(app))
+                .map(|(module, app)| {
+                    (
+                        module.resolve(interner).unwrap(),
+                        app.resolve(interner).unwrap(),
+                    )
+                })
+                .collect();
+            modules.sort_unstable();
+            for (module, app) in modules {
+                writeln!(stdout, "{} {}", module, app)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Declared and used dependencies of the analyzed applications, either for
+/// each of them or for all of them together as a single unit
+fn write_manifest(
+    analyzer: &Analyzer,
+    analyze: &[String],
+    analyze_all: bool,
+    per_app: bool,
+) -> Result<()> {
+    let interner = analyzer.interner();
+    let mut apps: Vec<_> = if analyze_all {
+        analyzer.app_modules().keys().copied().collect()
+    } else {
+        analyze
+            .iter()
+            .map(|app| analyzer.find_app(app))
+            .collect::<Result<_>>()?
+    };
+    apps.sort_by_key(|app| app.resolve(interner));
+
+    let names = |apps: &BTreeSet<Atom>| -> Vec<&str> {
+        let mut names: Vec<_> = apps
+            .iter()
+            .map(|app| app.resolve(interner).unwrap())
+            .collect();
+        names.sort_unstable();
+        names
+    };
+    let manifest = |declared: BTreeSet<Atom>, used: BTreeSet<Atom>| {
+        json!({
+            "declared": names(&declared),
+            "used": names(&used),
+            "missing": names(&used.difference(&declared).copied().collect()),
+            "unused": names(&declared.difference(&used).copied().collect()),
+        })
+    };
+
+    let json = if per_app {
+        let manifests: serde_json::Map<_, _> = apps
+            .iter()
+            .map(|&app| {
+                let declared = analyzer
+                    .declared_deps(app)
+                    .iter()
+                    .chain(analyzer.included_apps(app))
+                    .copied()
+                    .collect();
+                let used = analyzer.used_apps(app).into_iter().collect();
+                (
+                    app.resolve(interner).unwrap().to_string(),
+                    manifest(declared, used),
+                )
+            })
+            .collect();
+        serde_json::Value::Object(manifests)
+    } else {
+        // Dependencies between the analyzed applications are internal
+        let external = |deps: Vec<Atom>| -> Vec<Atom> {
+            deps.into_iter().filter(|dep| !apps.contains(dep)).collect()
+        };
+        let declared = apps
+            .iter()
+            .flat_map(|&app| {
+                let declared = analyzer.declared_deps(app).iter();
+                external(
+                    declared
+                        .chain(analyzer.included_apps(app))
+                        .copied()
+                        .collect(),
+                )
+            })
+            .collect();
+        let used = apps
+            .iter()
+            .flat_map(|&app| external(analyzer.used_apps(app)))
+            .collect();
+        let mut manifest = manifest(declared, used);
+        manifest["apps"] = json!(apps
+            .iter()
+            .map(|app| app.resolve(interner).unwrap())
+            .collect::<Vec<_>>());
+        manifest
+    };
+
+    let stdout = &mut io::stdout().lock();
+    serde_json::to_writer_pretty(&mut *stdout, &json)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+fn write_stats(
+    analyzer: &Analyzer,
+    top_couplings: usize,
+    metrics: Option<usize>,
+    json: bool,
+    color: ColorChoice,
+) -> Result<()> {
+    let interner = analyzer.interner();
+    let reporter = Human::new(interner, color.enabled(io::stdout().is_terminal()));
+    let stdout = &mut io::stdout().lock();
+
+    if json {
+        let (apps, modules) = (metrics::apps(analyzer), metrics::modules(analyzer));
+        return metrics::write_json(analyzer, &apps, &modules, stdout);
+    }
+
+    reporter.totals(
+        &[
+            ("total apps", analyzer.app_modules().len()),
+            ("total app dependencies", analyzer.app_deps().edge_count()),
+            ("total modules", analyzer.modules_rev().len()),
+            ("total atoms", interner.len()),
+        ],
+        stdout,
+    )?;
+
+    let couplings = analyzer.app_couplings();
+    if top_couplings > 0 && !couplings.is_empty() {
+        let top = couplings.len().min(top_couplings);
+        reporter.couplings(&couplings[..top], stdout)?;
+    }
+
+    if let Some(top_modules) = metrics {
+        let (apps, modules) = (metrics::apps(analyzer), metrics::modules(analyzer));
+        metrics::write_human(analyzer, &apps, &modules, top_modules, stdout)?;
+    }
+
+    Ok(())
+}
+
+fn write_human(
+    args: &CheckArgs,
+    analyzer: &Analyzer,
+    analyze: &[Atom],
+    results: &[(Atom, AnalysisResult)],
+    color: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let interner = analyzer.interner();
+    let reporter = Human::new(interner, color);
+
+    reporter.app_deps(analyze, analyzer.app_deps(), out)?;
+    writeln!(out)?;
+
+    let mut results = results.to_vec();
+    let mut otp_counts = BTreeMap::new();
+    if args.collapse_otp {
+        results.retain(
+            |(_, result)| match otp::target_app(result, analyzer, interner) {
+                Some(app) => {
+                    *otp_counts.entry(app).or_default() += 1;
+                    false
+                }
+                None => true,
+            },
+        );
+    }
+
+    // Individual missing dependencies are rarely actionable on their own,
+    // the fix is the same for every module of the application
+    let suggestions = analyzer.dependency_suggestions(&results);
+    if !args.summary {
+        results.retain(|(_, result)| !matches!(result, AnalysisResult::MissingDependency { .. }));
+    }
+
+    if args.summary {
+        report::write_summary(&results, analyzer, interner, out)?;
+    } else {
+        let options = Options {
+            color,
+            show_paths: args.show_paths,
+        };
+        report::write(Format::Human, &results, analyzer, interner, options, out)?;
+    }
+
+    if !args.summary && !suggestions.is_empty() {
+        reporter.dependency_suggestions(&suggestions, out)?;
+    }
+
+    if !otp_counts.is_empty() {
+        reporter.otp_findings(&otp_counts, out)?;
+    }
+
+    Ok(())
+}
+
+/// Logs to stderr, with the time spent in every span as it closes
+fn init_tracing(verbosity: usize) {
+    let level = match verbosity {
+        0 => return,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+}
+
+/// Paths listed one per line in a file, or on stdin for `-`
+fn read_file_list(path: &str) -> Result<Vec<PathBuf>> {
+    let text = if path == "-" {
+        io::read_to_string(io::stdin()).context("reading file list from stdin")?
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("reading file list: {}", path))?
+    };
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn parse_args() -> Result<Option<Args>> {
     let mut args = pico_args::Arguments::from_env();
 
-    let parsed = Args {
-        lib_paths: args.values_from_str("--lib-path")?,
-        analyze: args.values_from_str("--analyze")?,
-        analyze_all: args.contains("--analyze-all"),
+    if args.contains(["-h", "--help"]) {
+        return Ok(None);
+    }
+
+    let mut subcommand = args.subcommand()?;
+    let mut positional = vec![];
+    // `xref <lib path>` runs the default command
+    if let Some(path) = subcommand.as_deref() {
+        if !COMMANDS.contains(&path) && Path::new(path).exists() {
+            positional.push(subcommand.take().unwrap());
+        }
+    }
+
+    let watch = args.contains("--watch");
+    let mut jobs: Option<usize> = args.opt_value_from_str(["-j", "--jobs"])?;
+    if args.contains("--no-parallel") {
+        jobs = Some(1);
+    }
+    if jobs == Some(0) {
+        anyhow::bail!("--jobs expects a positive number of threads");
+    }
+    let mut verbosity = 0;
+    while args.contains(["-v", "--verbose"]) {
+        verbosity += 1;
+    }
+    while args.contains("-vv") {
+        verbosity += 2;
+    }
+
+    let cli_lib_paths: Vec<PathBuf> = args.values_from_str("--lib-path")?;
+    let mut lib_paths = vec![];
+    let color = args
+        .opt_value_from_str("--color")?
+        .unwrap_or(ColorChoice::Auto);
+    let config_path: Option<PathBuf> = args.opt_value_from_str("--config")?;
+    let mut config = match &config_path {
+        Some(path) => Config::load(path)?,
+        None => Config::load_default()?,
+    };
+    let profile: Option<String> = args.opt_value_from_str("--profile")?;
+    if let Some(profile) = &profile {
+        config = config.with_profile(profile)?;
+    }
+    config
+        .exclude
+        .apps
+        .extend(args.values_from_str::<_, Pattern>("--exclude-app")?);
+    config
+        .exclude
+        .modules
+        .extend(args.values_from_str::<_, Pattern>("--exclude-module")?);
+    config
+        .entry_points
+        .extend(args.values_from_str::<_, FunctionPattern>("--entry-point")?);
+    if args.contains("--reachability") {
+        config.reachability = true;
+    }
+    let checks = list_values(&mut args, "--check")?;
+    if let Some(check) = checks
+        .iter()
+        .find(|check| !CHECKS.contains(&check.as_str()))
+    {
+        anyhow::bail!(
+            "unknown check: {} (expected one of: {})",
+            check,
+            CHECKS.join(", ")
+        );
+    }
+    if !checks.is_empty() {
+        config.checks = Some(checks);
+    }
+    let ignore_file: Option<PathBuf> = args.opt_value_from_str("--ignore-file")?;
+    match ignore_file {
+        Some(path) => config.ignore.extend(ignore::load_file(&path)?),
+        None if Path::new(ignore::FILE_NAME).exists() => config
+            .ignore
+            .extend(ignore::load_file(Path::new(ignore::FILE_NAME))?),
+        None => {}
+    }
+    // impact has its own --release, naming lib paths
+    let release = match subcommand.as_deref() {
+        Some("impact") => None,
+        _ => args.opt_value_from_str::<_, PathBuf>("--release")?,
+    };
+    let release = match release {
+        Some(path) => Some(Release::load(&path)?),
+        None => None,
+    };
+    let files = match args.opt_value_from_str::<_, String>("--files-from")? {
+        Some(path) => read_file_list(&path)?,
+        None => vec![],
+    };
+    let escript = match args.opt_value_from_str::<_, PathBuf>("--escript")? {
+        Some(path) => Some(Escript::load(&path)?),
+        None => None,
+    };
+    let erl_libs = args.contains("--erl-libs");
+
+    let command = match subcommand.as_deref() {
+        None | Some("check") => Command::Check(Box::new(parse_check_args(&mut args)?)),
+        Some("graph") => {
+            let roots = list_values(&mut args, "--analyze")?;
+            let outputs = parse_outputs(&mut args, "mermaid")?;
+            Command::Graph { roots, outputs }
+        }
+        Some("query") => Command::Query(parse_query(&mut args)?),
+        Some(list @ "list") | Some(list @ "list-apps") | Some(list @ "list-modules") => {
+            let usage = "usage: xref list apps|modules [--app <app>...]";
+            let apps = list_values(&mut args, "--app")?;
+            let what = match list.strip_prefix("list-") {
+                Some(what) => what.to_string(),
+                None => args.free_from_str()?.context(usage)?,
+            };
+            match what.as_str() {
+                "apps" => Command::List(List::Apps),
+                "modules" => Command::List(List::Modules { apps }),
+                _ => anyhow::bail!(usage),
+            }
+        }
+        Some("manifest") => {
+            let usage =
+                "usage: xref manifest deps [--analyze <app>... | --analyze-all] [--per-app]";
+            let analyze = list_values(&mut args, "--analyze")?;
+            let analyze_all = args.contains("--analyze-all");
+            let per_app = args.contains("--per-app");
+            match args.free_from_str::<String>()?.as_deref() {
+                Some("deps") => Command::Manifest {
+                    analyze,
+                    analyze_all,
+                    per_app,
+                },
+                _ => anyhow::bail!(usage),
+            }
+        }
+        Some("tree") => Command::Tree {
+            roots: positional_apps(&mut args, &mut positional)?,
+        },
+        Some("closure") => Command::Closure {
+            roots: positional_apps(&mut args, &mut positional)?,
+        },
+        Some("start-order") => Command::StartOrder {
+            roots: positional_apps(&mut args, &mut positional)?,
+        },
+        Some("why") => {
+            let usage = "usage: xref why <app> <dependency> [--all]";
+            let all = args.contains("--all");
+            Command::Why {
+                from: args.free_from_str()?.context(usage)?,
+                to: args.free_from_str()?.context(usage)?,
+                all,
+            }
+        }
+        Some("stats") => {
+            let top_couplings = args.opt_value_from_str("--top-couplings")?.unwrap_or(10);
+            let top_modules = args.opt_value_from_str("--top-modules")?;
+            let metrics = args.contains("--metrics") || top_modules.is_some();
+            let json = match args.opt_value_from_str::<_, String>("--format")?.as_deref() {
+                None | Some("human") => false,
+                Some("json") => true,
+                Some(other) => {
+                    anyhow::bail!("unknown format: {} (expected one of: human, json)", other)
+                }
+            };
+            Command::Stats {
+                top_couplings,
+                metrics: metrics.then(|| top_modules.unwrap_or(10)),
+                json,
+            }
+        }
+        Some("diff") => Command::Diff {
+            base_lib_paths: args.values_from_str("--base-lib-path")?,
+        },
+        Some("impact") => {
+            let usage = "usage: xref impact <app> --release [<name>=]<lib path>...";
+            let releases: Vec<String> = args.values_from_str("--release")?;
+            let json = match args.opt_value_from_str::<_, String>("--format")?.as_deref() {
+                None | Some("human") => false,
+                Some("json") => true,
+                Some(other) => {
+                    anyhow::bail!("unknown format: {} (expected one of: human, json)", other)
+                }
+            };
+            let app = args.free_from_str()?.context(usage)?;
+            if releases.is_empty() {
+                anyhow::bail!(usage);
+            }
+            Command::Impact {
+                app,
+                releases: releases
+                    .iter()
+                    .map(|release| parse_release(release))
+                    .collect(),
+                json,
+            }
+        }
+        Some("completions") => Command::Completions(
+            args.free_from_str()?
+                .context("usage: xref completions bash|zsh|fish")?,
+        ),
+        Some("help") => return Ok(None),
+        Some(other) => anyhow::bail!("unknown command: {}\n\n{}", other, USAGE),
+    };
+
+    // Anything left over is a lib path, `<app>@<dir>` names a single one
+    positional.extend(args.free()?);
+    let mut named_apps = vec![];
+    for path in positional
+        .into_iter()
+        .map(PathBuf::from)
+        .chain(cli_lib_paths)
+    {
+        match named_app(&path) {
+            Some(named) => named_apps.push(named),
+            None => lib_paths.push(path),
+        }
+    }
+
+    if let Some(release) = &release {
+        if lib_paths.is_empty() && config.lib_paths.is_empty() {
+            if let Some(root) = &release.root {
+                lib_paths.push(root.join("lib"));
+            }
+        }
+        if config.analyze.is_empty() {
+            config.analyze_all = true;
+        }
+    }
+    if !files.is_empty() && config.analyze.is_empty() {
+        config.analyze_all = true;
+    }
+    if let Some(escript) = &escript {
+        if config.analyze.is_empty() && !config.analyze_all {
+            config.analyze = escript.apps().into_iter().map(|app| app.name).collect();
+        }
+    }
+    let loads_files = !files.is_empty() || escript.is_some();
+    if lib_paths.is_empty() && config.lib_paths.is_empty() && !loads_files {
+        if let Some(project) = discover::project(Path::new("."), profile.as_deref()) {
+            config.lib_paths = project.lib_paths;
+            if config.analyze.is_empty() && !config.analyze_all {
+                config.analyze_all = project.apps.is_empty();
+                config.analyze = project.apps;
+            }
+        }
+    }
+    if lib_paths.is_empty() {
+        lib_paths = config.lib_paths.clone();
+    }
+    if erl_libs || (lib_paths.is_empty() && !loads_files) {
+        lib_paths.extend(discover::erl_libs());
+    }
+    for test_lib_path in &config.test_code.lib_paths {
+        if !lib_paths.contains(test_lib_path) {
+            lib_paths.push(test_lib_path.clone());
+        }
+    }
+
+    let mut command = command;
+    apply_analyze_defaults(&mut command, &config);
+
+    Ok(Some(Args {
+        command,
+        lib_paths,
+        named_apps,
+        color,
+        config,
+        release,
+        verbosity,
+        watch,
+        jobs,
+        files,
+        escript,
+    }))
+}
+
+/// Commands analyzing some applications default to the configured ones
+fn apply_analyze_defaults(command: &mut Command, config: &Config) {
+    match command {
+        Command::Check(check_args)
+            if check_args.analyze.is_empty()
+                && !check_args.analyze_all
+                && check_args.analyze_modules.is_empty() =>
+        {
+            check_args.analyze = config.analyze.clone();
+            check_args.analyze_all = config.analyze_all;
+        }
+        Command::Graph { roots, .. }
+        | Command::Tree { roots }
+        | Command::Closure { roots }
+        | Command::StartOrder { roots }
+            if roots.is_empty() =>
+        {
+            *roots = config.analyze.clone();
+        }
+        Command::Manifest {
+            analyze,
+            analyze_all,
+            ..
+        } if analyze.is_empty() && !*analyze_all => {
+            *analyze = config.analyze.clone();
+            *analyze_all = config.analyze_all;
+        }
+        _ => {}
+    }
+}
+
+/// The `--app` values and the positional arguments that aren't lib paths,
+/// which are added to `positional` instead
+fn positional_apps(
+    args: &mut pico_args::Arguments,
+    positional: &mut Vec<String>,
+) -> Result<Vec<String>> {
+    let mut apps = list_values(args, "--app")?;
+    while let Some(free) = args.free_from_str::<String>()? {
+        if Path::new(&free).exists() {
+            positional.push(free);
+        } else {
+            apps.push(free);
+        }
+    }
+    Ok(apps)
+}
+
+/// Values of a repeatable flag, each of which can also be a comma
+/// separated list
+fn list_values(args: &mut pico_args::Arguments, flag: &'static str) -> Result<Vec<String>> {
+    let values: Vec<String> = args.values_from_str(flag)?;
+    Ok(values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// `<app>@<dir>`, loading the directory as the named application
+fn named_app(path: &Path) -> Option<(String, PathBuf)> {
+    let (app, dir) = path.to_str()?.split_once('@')?;
+    let valid = app.starts_with(|c: char| c.is_ascii_lowercase())
+        && app.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid || dir.is_empty() {
+        return None;
+    }
+    Some((app.to_string(), PathBuf::from(dir)))
+}
+
+fn parse_check_args(args: &mut pico_args::Arguments) -> Result<CheckArgs> {
+    let analyze = list_values(args, "--analyze")?;
+    let analyze_all = args.contains("--analyze-all");
+    let analyze_modules = list_values(args, "--analyze-module")?;
+    let outputs = parse_outputs(args, "human")?;
+    let show_paths = args.contains("--show-paths");
+    let summary = args.contains("--summary");
+    let collapse_otp = args.contains("--collapse-otp");
+    let ignore_older_than = args.opt_value_from_str("--ignore-older-than")?;
+    let src_roots = args.values_from_str("--src-root")?;
+    let sample: Option<f64> = args.opt_value_from_str("--sample")?;
+    let seed = args.opt_value_from_str("--seed")?.unwrap_or(0);
+    let baseline = args.opt_value_from_str("--baseline")?;
+    let write_baseline = args.opt_value_from_str("--write-baseline")?;
+    let max_warnings = args.values_from_str("--max-warnings")?;
+    let min_cycle_size = args.opt_value_from_str("--min-cycle-size")?;
+    let otp_version = args.opt_value_from_str("--otp-version")?;
+    let otps = args
+        .values_from_str::<_, String>("--otp")?
+        .iter()
+        .map(|otp| parse_otp(otp))
+        .collect::<Result<_>>()?;
+
+    let sample = match sample {
+        Some(fraction) if fraction > 0.0 && fraction <= 1.0 => Some(Sample { fraction, seed }),
+        Some(fraction) => anyhow::bail!("--sample expects a fraction in (0, 1], got: {}", fraction),
+        None => None,
     };
 
-    args.finish()?;
+    Ok(CheckArgs {
+        analyze,
+        analyze_all,
+        analyze_modules,
+        outputs,
+        show_paths,
+        summary,
+        collapse_otp,
+        ignore_older_than,
+        src_roots,
+        sample,
+        baseline,
+        write_baseline,
+        max_warnings,
+        min_cycle_size,
+        otp_version,
+        otps,
+    })
+}
+
+/// `VERSION=PATH`, where the path is an OTP installation or its lib directory
+fn parse_otp(otp: &str) -> Result<(String, PathBuf)> {
+    let (version, path) = otp
+        .split_once('=')
+        .with_context(|| format!("expected --otp VERSION=PATH, got: {}", otp))?;
+    let path = PathBuf::from(path);
+    let lib = path.join("lib");
+    Ok((version.to_string(), if lib.is_dir() { lib } else { path }))
+}
+
+/// `--format` picks the format of the outputs that don't name one
+fn parse_outputs(args: &mut pico_args::Arguments, default_format: &str) -> Result<Vec<Output>> {
+    let format: OutputFormat = args
+        .opt_value_from_str::<_, String>("--format")?
+        .as_deref()
+        .unwrap_or(default_format)
+        .parse()?;
+    let outputs: Vec<String> = args.values_from_str("--output")?;
 
-    Ok(parsed)
+    if outputs.is_empty() {
+        Ok(vec![Output {
+            format,
+            target: Target::Stdout,
+        }])
+    } else {
+        outputs
+            .iter()
+            .map(|output| Output::parse(output, format))
+            .collect()
+    }
+}
+
+/// `NAME=PATH`, or just the lib directory of a release, `_rel/NAME/lib`
+fn parse_release(release: &str) -> (String, PathBuf) {
+    if let Some((name, path)) = release.split_once('=') {
+        return (name.to_string(), PathBuf::from(path));
+    }
+    let path = PathBuf::from(release);
+    let dir = if path.ends_with("lib") {
+        path.parent()
+    } else {
+        Some(path.as_path())
+    };
+    let name = dir.and_then(Path::file_name).map_or_else(
+        || release.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    (name, path)
+}
+
+fn parse_query(args: &mut pico_args::Arguments) -> Result<Query> {
+    let usage = "usage: xref query owner|implementors|dep|calls|api <name>";
+    let json = match args.opt_value_from_str::<_, String>("--format")?.as_deref() {
+        None | Some("human") => false,
+        Some("json") => true,
+        Some(other) => anyhow::bail!("unknown format: {} (expected one of: human, json)", other),
+    };
+    let query: String = args.free_from_str()?.context(usage)?;
+    let name: String = args.free_from_str()?.context(usage)?;
+
+    match query.as_str() {
+        "owner" => Ok(Query::Owner(name)),
+        "implementors" => match name.rsplit_once('/') {
+            Some((fun, arity)) => Ok(Query::Implementors {
+                fun: fun.to_string(),
+                arity: Some(arity.parse().context(usage)?),
+            }),
+            None => Ok(Query::Implementors {
+                fun: name,
+                arity: None,
+            }),
+        },
+        "dep" => Ok(Query::Dep(name)),
+        "calls" => Ok(Query::Calls(name)),
+        "api" => Ok(Query::Api { app: name, json }),
+        _ => anyhow::bail!(usage),
+    }
 }
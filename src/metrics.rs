@@ -0,0 +1,210 @@
+//! Coupling metrics of modules and applications, to find architectural hot
+//! spots: what many others depend on, and what depends on many others.
+
+use std::{cmp::Reverse, io::Write};
+
+use anyhow::Result;
+use fxhash::FxHashSet;
+use serde_json::json;
+
+use crate::{analyzer::Analyzer, types::Atom};
+
+pub struct Metrics {
+    /// The module or application
+    pub subject: Atom,
+    /// Distinct modules calling into it, from outside of it
+    pub fan_in: usize,
+    /// Distinct modules it calls, outside of it
+    pub fan_out: usize,
+    /// Distinct other applications calling into it
+    pub afferent: usize,
+    /// Distinct other applications it calls
+    pub efferent: usize,
+}
+
+impl Metrics {
+    /// Efferent over total coupling: 0 when only depended on, 1 when only
+    /// depending on others
+    pub fn instability(&self) -> f64 {
+        match self.afferent + self.efferent {
+            0 => 0.0,
+            total => self.efferent as f64 / total as f64,
+        }
+    }
+}
+
+/// Modules the module calls, directly or through literals
+fn called_modules(analyzer: &Analyzer, module: Atom) -> impl Iterator<Item = Atom> + '_ {
+    let loaded = analyzer.module(module).unwrap();
+    loaded
+        .imports
+        .keys()
+        .copied()
+        .chain(loaded.dynamic_calls.iter().map(|call| call.module))
+        .filter(move |&called| called != module)
+}
+
+/// Metrics of every loaded module, sorted by name
+pub fn modules(analyzer: &Analyzer) -> Vec<Metrics> {
+    let mut metrics: Vec<_> = analyzer
+        .modules_rev()
+        .iter()
+        .map(|(&module, &app)| {
+            let callers = analyzer.importers(module);
+            let callees: FxHashSet<_> = called_modules(analyzer, module).collect();
+            let other_apps = |modules: &mut dyn Iterator<Item = Atom>| {
+                modules
+                    .filter_map(|module| analyzer.owner(module))
+                    .filter(|&owner| owner != app)
+                    .collect::<FxHashSet<_>>()
+                    .len()
+            };
+            Metrics {
+                subject: module,
+                fan_in: callers.len(),
+                fan_out: callees.len(),
+                afferent: other_apps(&mut callers.iter().copied()),
+                efferent: other_apps(&mut callees.iter().copied()),
+            }
+        })
+        .collect();
+    metrics.sort_by_key(|metrics| analyzer.name(metrics.subject));
+    metrics
+}
+
+/// Metrics of every loaded application, counting only calls crossing its
+/// boundary, sorted by name
+pub fn apps(analyzer: &Analyzer) -> Vec<Metrics> {
+    let mut metrics: Vec<_> = analyzer
+        .app_modules()
+        .iter()
+        .map(|(&app, modules)| {
+            let mut callers = FxHashSet::default();
+            let mut callees = FxHashSet::default();
+            for &module in modules {
+                if analyzer.owner(module) != Some(app) {
+                    continue;
+                }
+                callers.extend(
+                    analyzer
+                        .importers(module)
+                        .iter()
+                        .filter(|&&caller| analyzer.owner(caller) != Some(app)),
+                );
+                callees.extend(
+                    called_modules(analyzer, module)
+                        .filter(|&called| analyzer.owner(called) != Some(app)),
+                );
+            }
+            let apps = |modules: &FxHashSet<Atom>| {
+                modules
+                    .iter()
+                    .filter_map(|&module| analyzer.owner(module))
+                    .collect::<FxHashSet<_>>()
+                    .len()
+            };
+            Metrics {
+                subject: app,
+                fan_in: callers.len(),
+                fan_out: callees.len(),
+                afferent: apps(&callers),
+                efferent: apps(&callees),
+            }
+        })
+        .collect();
+    metrics.sort_by_key(|metrics| analyzer.name(metrics.subject));
+    metrics
+}
+
+/// The applications, then the `top` modules with the highest fan-in and
+/// fan-out
+pub fn write_human(
+    analyzer: &Analyzer,
+    apps: &[Metrics],
+    modules: &[Metrics],
+    top: usize,
+    out: &mut dyn Write,
+) -> Result<()> {
+    writeln!(out, "\napplications:")?;
+    write_table(analyzer, apps.iter(), out)?;
+
+    let mut by_fan_in: Vec<_> = modules
+        .iter()
+        .filter(|metrics| metrics.fan_in > 0)
+        .collect();
+    by_fan_in.sort_by_key(|metrics| Reverse(metrics.fan_in));
+    writeln!(out, "\nmodules with the highest fan-in:")?;
+    write_table(analyzer, by_fan_in.into_iter().take(top), out)?;
+
+    let mut by_fan_out: Vec<_> = modules
+        .iter()
+        .filter(|metrics| metrics.fan_out > 0)
+        .collect();
+    by_fan_out.sort_by_key(|metrics| Reverse(metrics.fan_out));
+    writeln!(out, "\nmodules with the highest fan-out:")?;
+    write_table(analyzer, by_fan_out.into_iter().take(top), out)?;
+    Ok(())
+}
+
+fn write_table<'a>(
+    analyzer: &Analyzer,
+    rows: impl Iterator<Item = &'a Metrics>,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let rows: Vec<_> = rows.collect();
+    let width = rows
+        .iter()
+        .map(|metrics| analyzer.name(metrics.subject).len())
+        .max()
+        .unwrap_or(0);
+    writeln!(
+        out,
+        "  {:width$}  fan-in  fan-out  afferent  efferent  instability",
+        "",
+        width = width
+    )?;
+    for metrics in rows {
+        writeln!(
+            out,
+            "  {:width$}  {:>6}  {:>7}  {:>8}  {:>8}  {:>11.2}",
+            analyzer.name(metrics.subject),
+            metrics.fan_in,
+            metrics.fan_out,
+            metrics.afferent,
+            metrics.efferent,
+            metrics.instability(),
+            width = width
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_json(
+    analyzer: &Analyzer,
+    apps: &[Metrics],
+    modules: &[Metrics],
+    out: &mut dyn Write,
+) -> Result<()> {
+    let rows = |metrics: &[Metrics]| -> Vec<_> {
+        metrics
+            .iter()
+            .map(|metrics| {
+                json!({
+                    "name": analyzer.name(metrics.subject),
+                    "fan_in": metrics.fan_in,
+                    "fan_out": metrics.fan_out,
+                    "afferent": metrics.afferent,
+                    "efferent": metrics.efferent,
+                    "instability": metrics.instability(),
+                })
+            })
+            .collect()
+    };
+    let json = json!({
+        "apps": rows(apps),
+        "modules": rows(modules),
+    });
+    serde_json::to_writer_pretty(&mut *out, &json)?;
+    writeln!(out)?;
+    Ok(())
+}
@@ -0,0 +1,526 @@
+//! Knowledge about applications shipped with Erlang/OTP, useful when
+//! OTP itself is not among the analyzed lib paths.
+
+use crate::{
+    analyzer::{AnalysisResult, Analyzer},
+    types::Interner,
+};
+
+pub const OTP_APPS: &[&str] = &[
+    "asn1",
+    "common_test",
+    "compiler",
+    "crypto",
+    "debugger",
+    "dialyzer",
+    "diameter",
+    "edoc",
+    "eldap",
+    "erl_docgen",
+    "erl_interface",
+    "erts",
+    "et",
+    "eunit",
+    "ftp",
+    "hipe",
+    "inets",
+    "jinterface",
+    "kernel",
+    "megaco",
+    "mnesia",
+    "observer",
+    "odbc",
+    "os_mon",
+    "otp_mibs",
+    "parsetools",
+    "public_key",
+    "reltool",
+    "runtime_tools",
+    "sasl",
+    "snmp",
+    "ssh",
+    "ssl",
+    "stdlib",
+    "syntax_tools",
+    "tftp",
+    "tools",
+    "wx",
+    "xmerl",
+];
+
+const ERTS_MODULES: &[&str] = &[
+    "atomics",
+    "counters",
+    "erl_init",
+    "erl_prim_loader",
+    "erl_tracer",
+    "erlang",
+    "erts_code_purger",
+    "erts_internal",
+    "erts_literal_area_collector",
+    "init",
+    "persistent_term",
+    "prim_buffer",
+    "prim_eval",
+    "prim_file",
+    "prim_inet",
+    "prim_net",
+    "prim_socket",
+    "prim_zip",
+    "zlib",
+];
+
+const KERNEL_MODULES: &[&str] = &[
+    "application",
+    "application_controller",
+    "application_master",
+    "auth",
+    "code",
+    "code_server",
+    "disk_log",
+    "dist_util",
+    "erl_boot_server",
+    "erl_ddll",
+    "erl_distribution",
+    "erl_epmd",
+    "erpc",
+    "error_handler",
+    "error_logger",
+    "file",
+    "gen_sctp",
+    "gen_tcp",
+    "gen_udp",
+    "global",
+    "global_group",
+    "heart",
+    "inet",
+    "inet_db",
+    "inet_parse",
+    "inet_res",
+    "kernel",
+    "logger",
+    "logger_disk_log_h",
+    "logger_filters",
+    "logger_formatter",
+    "logger_std_h",
+    "net",
+    "net_adm",
+    "net_kernel",
+    "os",
+    "pg",
+    "pg2",
+    "rpc",
+    "seq_trace",
+    "socket",
+    "user_drv",
+    "wrap_log_reader",
+];
+
+const STDLIB_MODULES: &[&str] = &[
+    "argparse",
+    "array",
+    "base64",
+    "beam_lib",
+    "binary",
+    "c",
+    "calendar",
+    "dets",
+    "dict",
+    "digraph",
+    "digraph_utils",
+    "epp",
+    "erl_anno",
+    "erl_eval",
+    "erl_internal",
+    "erl_lint",
+    "erl_parse",
+    "erl_pp",
+    "erl_scan",
+    "erl_tar",
+    "escript",
+    "ets",
+    "file_sorter",
+    "filelib",
+    "filename",
+    "gb_sets",
+    "gb_trees",
+    "gen",
+    "gen_event",
+    "gen_fsm",
+    "gen_server",
+    "gen_statem",
+    "io",
+    "io_lib",
+    "json",
+    "lists",
+    "maps",
+    "math",
+    "ms_transform",
+    "orddict",
+    "ordsets",
+    "peer",
+    "pool",
+    "proc_lib",
+    "proplists",
+    "qlc",
+    "queue",
+    "rand",
+    "random",
+    "re",
+    "sets",
+    "shell",
+    "slave",
+    "sofs",
+    "string",
+    "supervisor",
+    "supervisor_bridge",
+    "sys",
+    "timer",
+    "unicode",
+    "uri_string",
+    "zip",
+];
+
+/// Behaviours defined by OTP applications, known even when OTP isn't loaded
+const OTP_BEHAVIOURS: &[&str] = &[
+    "application",
+    "erl_tracer",
+    "gen_event",
+    "gen_fsm",
+    "gen_server",
+    "gen_statem",
+    "ssh_channel",
+    "ssh_client_channel",
+    "ssh_client_key_api",
+    "ssh_daemon_channel",
+    "ssh_server_channel",
+    "ssh_server_key_api",
+    "supervisor",
+    "supervisor_bridge",
+    "tftp",
+];
+
+pub fn is_otp_behaviour(module: &str) -> bool {
+    OTP_BEHAVIOURS.contains(&module)
+}
+
+const APPLICATION_CALLBACKS: &[(&str, u32)] = &[
+    ("start", 2),
+    ("stop", 1),
+    ("prep_stop", 1),
+    ("config_change", 3),
+    ("start_phase", 3),
+];
+
+const GEN_SERVER_CALLBACKS: &[(&str, u32)] = &[
+    ("init", 1),
+    ("handle_call", 3),
+    ("handle_cast", 2),
+    ("handle_info", 2),
+    ("handle_continue", 2),
+    ("terminate", 2),
+    ("code_change", 3),
+    ("format_status", 1),
+    ("format_status", 2),
+    ("child_spec", 1),
+];
+
+const GEN_EVENT_CALLBACKS: &[(&str, u32)] = &[
+    ("init", 1),
+    ("handle_event", 2),
+    ("handle_call", 2),
+    ("handle_info", 2),
+    ("terminate", 2),
+    ("code_change", 3),
+    ("format_status", 1),
+    ("format_status", 2),
+];
+
+const SUPERVISOR_CALLBACKS: &[(&str, u32)] = &[("init", 1), ("child_spec", 1)];
+
+/// Functions the behaviour calls in the modules implementing it. `None`
+/// when not known, or not a fixed set, like the state functions of
+/// `gen_statem`.
+pub fn behaviour_callbacks(behaviour: &str) -> Option<&'static [(&'static str, u32)]> {
+    match behaviour {
+        "application" | "Elixir.Application" => Some(APPLICATION_CALLBACKS),
+        "gen_server" | "Elixir.GenServer" => Some(GEN_SERVER_CALLBACKS),
+        "gen_event" => Some(GEN_EVENT_CALLBACKS),
+        "supervisor" | "Elixir.Supervisor" => Some(SUPERVISOR_CALLBACKS),
+        "supervisor_bridge" => Some(&[("init", 1), ("terminate", 2)]),
+        _ => None,
+    }
+}
+
+/// `(module, function, arity, deprecated in, removed in, hint)`
+type ObsoleteEntry = (
+    &'static str,
+    &'static str,
+    Option<u32>,
+    Option<u32>,
+    Option<u32>,
+    &'static str,
+);
+
+/// Functions deprecated or removed in an OTP release. `_` and `None` stand
+/// for every function and arity of the module.
+const OBSOLETE: &[ObsoleteEntry] = &[
+    (
+        "erlang",
+        "hash",
+        Some(2),
+        Some(18),
+        Some(20),
+        "use erlang:phash2/2",
+    ),
+    (
+        "erlang",
+        "now",
+        Some(0),
+        Some(18),
+        None,
+        "use erlang:monotonic_time/0 or erlang:timestamp/0",
+    ),
+    (
+        "erlang",
+        "get_stacktrace",
+        Some(0),
+        Some(21),
+        Some(24),
+        "use the try/catch syntax",
+    ),
+    (
+        "erlang",
+        "phash",
+        Some(2),
+        Some(27),
+        None,
+        "use erlang:phash2/2",
+    ),
+    ("random", "_", None, Some(19), None, "use the rand module"),
+    ("pg2", "_", None, Some(23), Some(24), "use the pg module"),
+    (
+        "http_uri",
+        "_",
+        None,
+        Some(23),
+        None,
+        "use the uri_string module",
+    ),
+    ("slave", "_", None, Some(25), None, "use the peer module"),
+    ("ct_slave", "_", None, Some(25), None, "use the peer module"),
+    (
+        "crypto",
+        "hmac",
+        Some(3),
+        Some(23),
+        Some(24),
+        "use crypto:mac/4",
+    ),
+    (
+        "crypto",
+        "hmac",
+        Some(4),
+        Some(23),
+        Some(24),
+        "use crypto:macN/5",
+    ),
+    (
+        "crypto",
+        "hmac_init",
+        Some(2),
+        Some(23),
+        Some(24),
+        "use crypto:mac_init/3",
+    ),
+    (
+        "crypto",
+        "hmac_update",
+        Some(2),
+        Some(23),
+        Some(24),
+        "use crypto:mac_update/2",
+    ),
+    (
+        "crypto",
+        "hmac_final",
+        Some(1),
+        Some(23),
+        Some(24),
+        "use crypto:mac_final/1",
+    ),
+    (
+        "crypto",
+        "hmac_final_n",
+        Some(2),
+        Some(23),
+        Some(24),
+        "use crypto:mac_finalN/2",
+    ),
+    (
+        "crypto",
+        "cmac",
+        Some(3),
+        Some(23),
+        Some(24),
+        "use crypto:mac/4",
+    ),
+    (
+        "crypto",
+        "cmac",
+        Some(4),
+        Some(23),
+        Some(24),
+        "use crypto:macN/5",
+    ),
+    (
+        "crypto",
+        "block_encrypt",
+        None,
+        Some(23),
+        Some(24),
+        "use crypto:crypto_one_time/5",
+    ),
+    (
+        "crypto",
+        "block_decrypt",
+        None,
+        Some(23),
+        Some(24),
+        "use crypto:crypto_one_time/5",
+    ),
+    (
+        "crypto",
+        "stream_init",
+        None,
+        Some(23),
+        Some(24),
+        "use crypto:crypto_init/4",
+    ),
+    (
+        "crypto",
+        "stream_encrypt",
+        Some(2),
+        Some(23),
+        Some(24),
+        "use crypto:crypto_update/2",
+    ),
+    (
+        "crypto",
+        "stream_decrypt",
+        Some(2),
+        Some(23),
+        Some(24),
+        "use crypto:crypto_update/2",
+    ),
+];
+
+/// How a call fares on the target OTP release
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Obsolete {
+    Deprecated { release: u32, hint: &'static str },
+    Removed { release: u32, hint: &'static str },
+}
+
+/// Whether `module:function/arity` is removed in the `target` release of
+/// OTP or earlier, or otherwise deprecated by then
+pub fn obsolete(module: &str, function: &str, arity: u32, target: u32) -> Option<Obsolete> {
+    OBSOLETE
+        .iter()
+        .filter(|&&(m, f, a, ..)| {
+            m == module && (f == "_" || f == function) && a.is_none_or(|a| a == arity)
+        })
+        .find_map(
+            |&(.., deprecated, removed, hint)| match (deprecated, removed) {
+                (_, Some(release)) if release <= target => {
+                    Some(Obsolete::Removed { release, hint })
+                }
+                (Some(release), _) if release <= target => {
+                    Some(Obsolete::Deprecated { release, hint })
+                }
+                _ => None,
+            },
+        )
+}
+
+pub fn is_otp_app(app: &str) -> bool {
+    OTP_APPS.contains(&app)
+}
+
+/// The OTP application a well-known erts, kernel or stdlib module belongs to
+pub fn module_app(module: &str) -> Option<&'static str> {
+    [
+        ("erts", ERTS_MODULES),
+        ("kernel", KERNEL_MODULES),
+        ("stdlib", STDLIB_MODULES),
+    ]
+    .iter()
+    .find(|(_, modules)| modules.contains(&module))
+    .map(|(app, _)| *app)
+}
+
+/// Name of the OTP application a finding points into, if any. Modules that
+/// are not loaded at all are recognized only for the core OTP applications.
+pub fn target_app<'a>(
+    result: &AnalysisResult,
+    analyzer: &Analyzer,
+    interner: &'a Interner,
+) -> Option<&'a str> {
+    let app = match result {
+        AnalysisResult::MissingModule(module)
+        | AnalysisResult::MissingOptionalModule { module, .. }
+        | AnalysisResult::CheckedMissingModule { module, .. }
+        | AnalysisResult::UndefinedBehaviour { behaviour: module }
+        | AnalysisResult::UndefinedParseTransform { transform: module } => {
+            return module_app(module.resolve(interner)?)
+        }
+        AnalysisResult::MissingFunction(module, ..)
+        | AnalysisResult::ArityMismatch { module, .. }
+        | AnalysisResult::MacroCall { module, .. }
+        | AnalysisResult::UnexportedFunction { module, .. }
+        | AnalysisResult::DeprecatedCall { module, .. }
+        | AnalysisResult::ForbiddenCall { module, .. }
+        | AnalysisResult::RiskyCall { module, .. }
+        | AnalysisResult::GroupBoundary { module, .. }
+        | AnalysisResult::InternalModuleCall { module, .. }
+        | AnalysisResult::LayerViolation { module, .. } => analyzer.owner(*module)?,
+        AnalysisResult::UndefinedDynamicCall { module, .. }
+        | AnalysisResult::UndefinedCapture { module, .. } => match analyzer.owner(*module) {
+            Some(app) => app,
+            None => return module_app(module.resolve(interner)?),
+        },
+        AnalysisResult::ObsoleteCall { module, .. } => {
+            let name = module.resolve(interner)?;
+            return match analyzer.owner(*module) {
+                Some(app) => app.resolve(interner).filter(|app| is_otp_app(app)),
+                None => module_app(name),
+            };
+        }
+        AnalysisResult::MissingDependency { app_to, .. } => *app_to,
+        AnalysisResult::DependencyNotFound { dep, .. }
+        | AnalysisResult::UnusedDependency { dep, .. }
+        | AnalysisResult::RedundantDependency { dep, .. }
+        | AnalysisResult::SelfDependency { app: dep }
+        | AnalysisResult::UndeclaredRuntimeDependency { dep, .. }
+        | AnalysisResult::UnusedRuntimeDependency { dep, .. }
+        | AnalysisResult::RegisteredNameClash { other: dep, .. } => *dep,
+        AnalysisResult::MissingSource { .. }
+        | AnalysisResult::DifferentBuild { .. }
+        | AnalysisResult::MissingPrivFile { .. }
+        | AnalysisResult::MissingOnLoad { .. }
+        | AnalysisResult::NifWithoutLibrary { .. }
+        | AnalysisResult::UnusedExport(..)
+        | AnalysisResult::UnusedLocal(..)
+        | AnalysisResult::DeadModule(_)
+        | AnalysisResult::DuplicateModule { .. }
+        | AnalysisResult::OtpModuleClash { .. }
+        | AnalysisResult::UnlistedModule { .. }
+        | AnalysisResult::MissingBeam { .. }
+        | AnalysisResult::AppCallback { .. }
+        | AnalysisResult::TestCodeCall { .. }
+        | AnalysisResult::AppCycle { .. }
+        | AnalysisResult::ModuleCycle { .. } => return None,
+    };
+
+    app.resolve(interner).filter(|app| is_otp_app(app))
+}
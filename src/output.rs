@@ -0,0 +1,105 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{graph::GraphFormat, report::Format};
+
+/// Names of every `OutputFormat`, the reports first
+pub const FORMATS: &[&str] = &[
+    "human", "json", "gitlab", "erlang", "markdown", "mermaid", "dot",
+];
+
+/// Anything a single run can render: a findings report or the
+/// application graph
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum OutputFormat {
+    Report(Format),
+    Graph(GraphFormat),
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<OutputFormat> {
+        match (s.parse(), s.parse()) {
+            (Ok(format), _) => Ok(OutputFormat::Report(format)),
+            (_, Ok(format)) => Ok(OutputFormat::Graph(format)),
+            _ => anyhow::bail!(
+                "unknown output format: {} (expected one of: {})",
+                s,
+                FORMATS.join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Target {
+    Stdout,
+    Stderr,
+    File(PathBuf),
+}
+
+impl Target {
+    pub fn open(&self) -> Result<Box<dyn Write>> {
+        Ok(match self {
+            Target::Stdout => Box::new(io::stdout()),
+            Target::Stderr => Box::new(io::stderr()),
+            Target::File(path) => {
+                Box::new(BufWriter::new(File::create(path).with_context(|| {
+                    format!("creating output file: {}", path.display())
+                })?))
+            }
+        })
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Target::Stdout => io::stdout().is_terminal(),
+            Target::Stderr => io::stderr().is_terminal(),
+            Target::File(_) => false,
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Target> {
+        match s {
+            "" => anyhow::bail!("empty output path"),
+            "-" | "stdout" => Ok(Target::Stdout),
+            "stderr" => Ok(Target::Stderr),
+            path => Ok(Target::File(PathBuf::from(path))),
+        }
+    }
+}
+
+/// A single `--output` destination, either a bare path, rendered in the
+/// default format, or `FORMAT=PATH`. `-` or `stdout` and `stderr` name the
+/// standard streams, a file with one of those names needs a `./` prefix.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Output {
+    pub format: OutputFormat,
+    pub target: Target,
+}
+
+impl Output {
+    pub fn parse(s: &str, default: OutputFormat) -> Result<Output> {
+        match s.split_once('=') {
+            Some((format, target)) if format.parse::<OutputFormat>().is_ok() => Ok(Output {
+                format: format.parse()?,
+                target: target.parse()?,
+            }),
+            _ => Ok(Output {
+                format: default,
+                target: s.parse()?,
+            }),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Name patterns used to select applications and modules. A pattern is
+//! either a glob, where `*` matches any run of characters and `?` a single
+//! one, or a regex between slashes like `/^.*_pb$/`.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Pattern> {
+        let regex = match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(regex) => {
+                Regex::new(regex).with_context(|| format!("invalid regex pattern: {}", s))?
+            }
+            None => Regex::new(&glob_to_regex(s)).unwrap(),
+        };
+        Ok(Pattern {
+            source: s.to_string(),
+            regex,
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Pattern, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// Functions as `module:function/arity`, where the module and function are
+/// patterns. The arity can be `*` or left out, together with the slash, to
+/// match any. A module alone matches all of its functions.
+#[derive(Debug, Clone)]
+pub struct FunctionPattern {
+    source: String,
+    module: Pattern,
+    function: Pattern,
+    arity: Option<u32>,
+}
+
+impl FunctionPattern {
+    pub fn matches(&self, module: &str, function: &str, arity: u32) -> bool {
+        self.arity.is_none_or(|expected| expected == arity)
+            && self.module.matches(module)
+            && self.function.matches(function)
+    }
+
+    /// Whether some function of `module` could match
+    pub fn matches_module(&self, module: &str) -> bool {
+        self.module.matches(module)
+    }
+}
+
+impl FromStr for FunctionPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<FunctionPattern> {
+        let (module, rest) = s.split_once(':').unwrap_or((s, "*"));
+        let (function, arity) = match rest.rsplit_once('/') {
+            Some((function, "*")) => (function, None),
+            Some((function, arity)) => (
+                function,
+                Some(
+                    arity
+                        .parse()
+                        .with_context(|| format!("invalid arity in: {}", s))?,
+                ),
+            ),
+            None => (rest, None),
+        };
+        Ok(FunctionPattern {
+            source: s.to_string(),
+            module: module.parse()?,
+            function: function.parse()?,
+            arity,
+        })
+    }
+}
+
+impl fmt::Display for FunctionPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for FunctionPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FunctionPattern, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Applications and modules left out of loading and analysis
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Excludes {
+    pub apps: Vec<Pattern>,
+    pub modules: Vec<Pattern>,
+}
+
+impl Excludes {
+    pub fn app(&self, name: &str) -> bool {
+        self.apps.iter().any(|pattern| pattern.matches(name))
+    }
+
+    pub fn module(&self, name: &str) -> bool {
+        self.modules.iter().any(|pattern| pattern.matches(name))
+    }
+}
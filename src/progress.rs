@@ -0,0 +1,127 @@
+//! Progress reporting for embedders. Events are emitted from the worker
+//! threads as the work happens, so the callback has to be thread safe and
+//! should return quickly - forwarding into a channel works well.
+
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Application directories found on the lib paths, before any is read
+    AppsDiscovered {
+        count: usize,
+    },
+    BeamParsed {
+        path: PathBuf,
+    },
+    AppLoaded {
+        name: String,
+        modules: usize,
+    },
+    /// All checks of a single analyzed application finished
+    AppChecked {
+        name: String,
+        findings: usize,
+    },
+    AnalysisFinished {
+        findings: usize,
+    },
+}
+
+pub type Callback = Box<dyn Fn(Event) + Send + Sync>;
+
+/// A single line progress bar on stderr, for loading: applications loaded
+/// out of the discovered ones, beams parsed and the time elapsed
+pub struct Bar {
+    state: Arc<State>,
+}
+
+struct State {
+    start: Instant,
+    apps: AtomicUsize,
+    loaded: AtomicUsize,
+    beams: AtomicUsize,
+    drawn: Mutex<Option<Instant>>,
+}
+
+const REDRAW: Duration = Duration::from_millis(100);
+
+impl Bar {
+    /// A bar when stderr is a terminal, `None` otherwise
+    pub fn stderr() -> Option<Bar> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+        Some(Bar {
+            state: Arc::new(State {
+                start: Instant::now(),
+                apps: AtomicUsize::new(0),
+                loaded: AtomicUsize::new(0),
+                beams: AtomicUsize::new(0),
+                drawn: Mutex::new(None),
+            }),
+        })
+    }
+
+    pub fn callback(&self) -> Callback {
+        let state = self.state.clone();
+        Box::new(move |event| {
+            match event {
+                Event::AppsDiscovered { count } => state.apps.store(count, Ordering::Relaxed),
+                Event::BeamParsed { .. } => {
+                    state.beams.fetch_add(1, Ordering::Relaxed);
+                }
+                Event::AppLoaded { .. } => {
+                    state.loaded.fetch_add(1, Ordering::Relaxed);
+                }
+                _ => return,
+            }
+            state.draw();
+        })
+    }
+
+    /// Clears the line, so that output can follow
+    pub fn finish(&self) {
+        let _ = self.state.drawn.lock().map(|drawn| {
+            if drawn.is_some() {
+                eprint!("\r\x1b[K");
+            }
+        });
+    }
+}
+
+impl State {
+    fn draw(&self) {
+        // Workers report concurrently, whoever gets the lock draws
+        let mut drawn = match self.drawn.try_lock() {
+            Ok(drawn) => drawn,
+            Err(_) => return,
+        };
+        let now = Instant::now();
+        if drawn.is_some_and(|drawn| now - drawn < REDRAW) {
+            return;
+        }
+        *drawn = Some(now);
+
+        let apps = self.apps.load(Ordering::Relaxed);
+        let loaded = self.loaded.load(Ordering::Relaxed);
+        const WIDTH: usize = 30;
+        let filled = (loaded * WIDTH).checked_div(apps).unwrap_or(0).min(WIDTH);
+        eprint!(
+            "\r\x1b[K[{}{}] {}/{} apps, {} beams, {:.1}s",
+            "=".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            loaded,
+            apps,
+            self.beams.load(Ordering::Relaxed),
+            (now - self.start).as_secs_f64()
+        );
+    }
+}
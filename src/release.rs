@@ -0,0 +1,136 @@
+//! Restricting a run to the exact application versions of an OTP release,
+//! as listed in its `.rel` file.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{etf, term};
+
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub name: String,
+    pub version: String,
+    /// Application versions, by application name, without erts
+    pub apps: BTreeMap<String, String>,
+    pub erts: Option<String>,
+    /// The root of the release layout, when the `.rel` file is in
+    /// `releases/<vsn>`, whose `lib` directory has the applications
+    pub root: Option<PathBuf>,
+}
+
+impl Release {
+    /// Loads the release from a `.rel` file, a `releases/<vsn>` directory
+    /// with one, or the root of a release, using the version in
+    /// `releases/start_erl.data` if there is more than one.
+    pub fn load(path: &Path) -> Result<Release> {
+        if path.is_file() {
+            return Release::load_file(path);
+        }
+        let releases = path.join("releases");
+        if releases.is_dir() {
+            let vsn = match fs::read_to_string(releases.join("start_erl.data")) {
+                Ok(data) => data.split_whitespace().nth(1).map(String::from),
+                Err(_) => None,
+            };
+            let dir = match vsn {
+                Some(vsn) => releases.join(vsn),
+                None => single(&releases, |path| path.is_dir())
+                    .with_context(|| format!("no release version in {}", releases.display()))?,
+            };
+            return Release::load(&dir);
+        }
+        let file = single(path, |path| {
+            path.extension().is_some_and(|ext| ext == "rel")
+        })
+        .with_context(|| format!("no .rel file in {}", path.display()))?;
+        Release::load_file(&file)
+    }
+
+    fn load_file(path: &Path) -> Result<Release> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("reading release file: {}", path.display()))?;
+        let (name, version, erts, apps) =
+            parse(&text).with_context(|| format!("not a release file: {}", path.display()))?;
+
+        // releases/<vsn>/<name>.rel
+        let root = path
+            .parent()
+            .and_then(Path::parent)
+            .filter(|dir| dir.file_name().is_some_and(|name| name == "releases"))
+            .and_then(Path::parent)
+            .map(Path::to_path_buf);
+
+        Ok(Release {
+            name,
+            version,
+            apps,
+            erts,
+            root,
+        })
+    }
+
+    /// Whether the release ships this version of the application
+    pub fn contains(&self, app: &str, vsn: Option<&str>) -> bool {
+        let shipped = match app {
+            "erts" => self.erts.as_deref(),
+            _ => self.apps.get(app).map(String::as_str),
+        };
+        shipped.is_some() && shipped == vsn
+    }
+}
+
+type Parsed = (String, String, Option<String>, BTreeMap<String, String>);
+
+/// `{release, {Name, Vsn}, {erts, Vsn}, Apps}`, where every application is
+/// `{App, Vsn}`, optionally followed by its start type and included
+/// applications
+fn parse(text: &str) -> Result<Parsed> {
+    let terms = term::consult(text)?;
+    let (release, erts, apps) = match terms.as_slice() {
+        [release] => match etf::tuple(release) {
+            Some([tag, release, erts, apps]) if etf::atom(tag) == Some("release") => {
+                (release, erts, apps)
+            }
+            _ => anyhow::bail!("expected {{release, {{Name, Vsn}}, {{erts, Vsn}}, Apps}}"),
+        },
+        _ => anyhow::bail!("expected a single term, found {}", terms.len()),
+    };
+    let (name, version) = match etf::tuple(release) {
+        Some([name, version]) => etf::string(name).zip(etf::string(version)),
+        _ => None,
+    }
+    .context("expected the release {Name, Vsn}")?;
+    let erts = match etf::tuple(erts) {
+        Some([tag, version]) if etf::atom(tag) == Some("erts") => etf::string(version),
+        _ => None,
+    };
+    let apps = etf::list(apps)
+        .context("expected a list of applications")?
+        .iter()
+        .map(|app| match etf::tuple(app) {
+            Some([name, version, ..]) => etf::atom(name)
+                .map(String::from)
+                .zip(etf::string(version))
+                .context("expected {App, Vsn}"),
+            _ => anyhow::bail!("expected {{App, Vsn}}"),
+        })
+        .collect::<Result<_>>()?;
+    Ok((name, version, erts, apps))
+}
+
+/// The only entry of `dir` matching `filter`
+fn single(dir: &Path, filter: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+    let mut paths = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| filter(path));
+    match (paths.next(), paths.next()) {
+        (Some(path), None) => Some(path),
+        _ => None,
+    }
+}
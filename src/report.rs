@@ -0,0 +1,686 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    env,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::{
+    analyzer::{AnalysisResult, Analyzer, Severity},
+    types::{AppDeps, Atom, Interner, Mfa},
+};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Format {
+    Human,
+    Json,
+    GitLab,
+    Erlang,
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "human" => Ok(Format::Human),
+            "json" => Ok(Format::Json),
+            "gitlab" => Ok(Format::GitLab),
+            "erlang" => Ok(Format::Erlang),
+            "markdown" => Ok(Format::Markdown),
+            _ => anyhow::bail!(
+                "unknown format: {} (expected one of: human, json, gitlab, erlang, markdown)",
+                s
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// `auto` colors only when writing to a terminal and `NO_COLOR` is not set
+    /// (https://no-color.org)
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && terminal
+            }
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ColorChoice> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => anyhow::bail!(
+                "unknown color choice: {} (expected one of: auto, always, never)",
+                s
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Options {
+    pub color: bool,
+    /// Print where every module was loaded from in the human format
+    pub show_paths: bool,
+}
+
+pub fn write(
+    format: Format,
+    results: &[(Atom, AnalysisResult)],
+    analyzer: &Analyzer,
+    interner: &Interner,
+    options: Options,
+    out: &mut dyn Write,
+) -> Result<()> {
+    match format {
+        Format::Human => {
+            let mut human = Human::new(interner, options.color);
+            if options.show_paths {
+                human = human.with_paths();
+            }
+            human.findings(results, analyzer, out)
+        }
+        Format::Json => write_json(results, analyzer, interner, out),
+        Format::GitLab => write_gitlab(results, analyzer, interner, out),
+        Format::Erlang => write_erlang(results, interner, out),
+        Format::Markdown => write_markdown(results, analyzer, interner, out),
+    }
+}
+
+/// The default, terminal oriented reporter
+pub struct Human<'a> {
+    interner: &'a Interner,
+    color: bool,
+    paths: bool,
+}
+
+impl<'a> Human<'a> {
+    pub fn new(interner: &'a Interner, color: bool) -> Human<'a> {
+        Human {
+            interner,
+            color,
+            paths: false,
+        }
+    }
+
+    /// Show the .beam file each finding comes from
+    pub fn with_paths(self) -> Human<'a> {
+        Human {
+            paths: true,
+            ..self
+        }
+    }
+
+    pub fn totals(&self, totals: &[(&str, usize)], out: &mut dyn Write) -> Result<()> {
+        for (what, count) in totals {
+            writeln!(out, "{}: {}", what, self.paint(BOLD, &count.to_string()))?;
+        }
+        Ok(())
+    }
+
+    pub fn couplings(&self, couplings: &[(Atom, Atom, usize)], out: &mut dyn Write) -> Result<()> {
+        self.heading("most coupled apps (distinct module pairs)", out)?;
+        for (app_from, app_to, count) in couplings {
+            writeln!(
+                out,
+                "  {} -> {}: {}",
+                self.name(*app_from),
+                self.name(*app_to),
+                count
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn app_deps(&self, apps: &[Atom], app_deps: &AppDeps, out: &mut dyn Write) -> Result<()> {
+        self.heading("analyzed apps", out)?;
+        for &app in apps {
+            let deps: Vec<_> = app_deps
+                .neighbors_directed(app, petgraph::EdgeDirection::Outgoing)
+                .map(|dep| self.name(dep))
+                .collect();
+            writeln!(
+                out,
+                "  {}: {}",
+                self.paint(BOLD, self.name(app)),
+                deps.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn findings(
+        &self,
+        results: &[(Atom, AnalysisResult)],
+        analyzer: &Analyzer,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        for (module, result) in results {
+            let color = match analyzer.severity(result) {
+                Severity::Error => RED,
+                Severity::Warning => YELLOW,
+                Severity::Info => CYAN,
+            };
+            write!(
+                out,
+                "{}: {}",
+                self.paint(BOLD, self.name(*module)),
+                self.paint(color, &result.fmt(self.interner))
+            )?;
+            match analyzer.module(*module).filter(|_| self.paths) {
+                Some(loaded) => {
+                    let paths = match analyzer.location(*module, result) {
+                        // Modules read from source
+                        Some((source, line)) if source == loaded.path => {
+                            format!("({}:{})", source.display(), line)
+                        }
+                        Some((source, line)) => {
+                            format!("({}, {}:{})", loaded.path.display(), source.display(), line)
+                        }
+                        None => format!("({})", loaded.path.display()),
+                    };
+                    writeln!(out, " {}", self.paint(DIM, &paths))?
+                }
+                None => writeln!(out)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Exports of a single application together with the modules calling them
+    /// from other applications
+    pub fn export_usage(&self, usage: &[(Mfa, Vec<Atom>)], out: &mut dyn Write) -> Result<()> {
+        let used = usage
+            .iter()
+            .filter(|(_, callers)| !callers.is_empty())
+            .count();
+        self.heading(&format!("used exports ({} of {})", used, usage.len()), out)?;
+        for ((module, fun, arity), callers) in usage {
+            if callers.is_empty() {
+                continue;
+            }
+            let callers: Vec<_> = callers.iter().map(|caller| self.name(*caller)).collect();
+            writeln!(
+                out,
+                "  {}:{}/{}: {}",
+                self.name(*module),
+                self.name(*fun),
+                arity,
+                callers.join(", ")
+            )?;
+        }
+
+        self.heading("unused exports", out)?;
+        for ((module, fun, arity), _) in usage.iter().filter(|(_, callers)| callers.is_empty()) {
+            writeln!(
+                out,
+                "  {}:{}/{}",
+                self.name(*module),
+                self.name(*fun),
+                arity
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Missing dependencies collapsed into one line per pair of applications
+    pub fn dependency_suggestions(
+        &self,
+        suggestions: &[(Atom, Atom, Vec<Atom>)],
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        const SHOWN_MODULES: usize = 5;
+
+        self.heading("suggested dependencies", out)?;
+        for (app, dep, modules) in suggestions {
+            let mut shown: Vec<_> = modules
+                .iter()
+                .take(SHOWN_MODULES)
+                .map(|module| self.name(*module))
+                .collect();
+            if modules.len() > SHOWN_MODULES {
+                shown.push("...");
+            }
+            writeln!(
+                out,
+                "  add {} to {}'s applications list - needed by {} ({})",
+                self.paint(BOLD, self.name(*dep)),
+                self.paint(BOLD, self.name(*app)),
+                count(modules.len(), "module"),
+                shown.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Summarized findings pointing into OTP applications
+    pub fn otp_findings(&self, counts: &BTreeMap<&str, usize>, out: &mut dyn Write) -> Result<()> {
+        self.heading("findings in OTP applications", out)?;
+        for (app, count) in counts {
+            writeln!(out, "  {}: {}", self.paint(BOLD, app), count)?;
+        }
+        Ok(())
+    }
+
+    fn heading(&self, text: &str, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "\n{}:", self.paint(BOLD, text))?;
+        Ok(())
+    }
+
+    fn name(&self, atom: Atom) -> &'a str {
+        atom.resolve(self.interner).unwrap()
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Prints the number of findings of every check for each application,
+/// instead of the individual findings
+pub fn write_summary(
+    results: &[(Atom, AnalysisResult)],
+    analyzer: &Analyzer,
+    interner: &Interner,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut counts = BTreeMap::<&str, BTreeMap<&str, usize>>::new();
+
+    for (module, result) in results {
+        let app = analyzer
+            .result_app(*module, result)
+            .and_then(|app| app.resolve(interner))
+            .unwrap_or("unknown");
+        *counts
+            .entry(app)
+            .or_default()
+            .entry(result.check_name())
+            .or_default() += 1;
+    }
+
+    writeln!(out, "total findings: {}", results.len())?;
+    for (app, checks) in counts {
+        let checks: Vec<_> = checks
+            .into_iter()
+            .map(|(check, count)| format!("{} {}", count, pluralize(check, count)))
+            .collect();
+        writeln!(out, "{}: {}", app, checks.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Turns a check name like `missing_dependency` into `missing dependencies`
+fn pluralize(check_name: &str, count: usize) -> String {
+    let words = check_name.replace('_', " ");
+    if count == 1 {
+        words
+    } else if let Some(stem) = words.strip_suffix('y') {
+        format!("{}ies", stem)
+    } else {
+        format!("{}s", words)
+    }
+}
+
+fn write_json(
+    results: &[(Atom, AnalysisResult)],
+    analyzer: &Analyzer,
+    interner: &Interner,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let findings: Vec<_> = results
+        .iter()
+        .map(|(module, result)| {
+            let app = analyzer.result_app(*module, result);
+            let location = analyzer.location(*module, result);
+            json!({
+                "check": result.check_name(),
+                "severity": json_severity(analyzer.severity(result)),
+                "module": module.resolve(interner).unwrap(),
+                "app": app.map(|app| app.resolve(interner).unwrap()),
+                "message": result.fmt(interner),
+                "path": analyzer.module(*module).map(|module| &module.path),
+                "app_path": app.and_then(|app| analyzer.app_path(app)),
+                "source": location.as_ref().map(|(source, _)| source),
+                "line": location.as_ref().map(|(_, line)| line),
+            })
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(&mut *out, &findings)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn json_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+// https://docs.gitlab.com/ee/ci/testing/code_quality.html#implement-a-custom-tool
+fn write_gitlab(
+    results: &[(Atom, AnalysisResult)],
+    analyzer: &Analyzer,
+    interner: &Interner,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let cwd = env::current_dir()?;
+    let issues: Vec<_> = results
+        .iter()
+        .map(|(module, result)| {
+            // GitLab links locations relative to the repository root
+            let (path, line) = match (analyzer.location(*module, result), analyzer.module(*module))
+            {
+                (Some((source, line)), _) => (relative_path(&source, &cwd), line),
+                (None, Some(module)) => (relative_path(&module.path, &cwd), 1),
+                (None, None) => (PathBuf::from(module.resolve(interner).unwrap()), 1),
+            };
+            let path = path.display().to_string();
+            let module = module.resolve(interner).unwrap();
+            let description = result.fmt(interner);
+            json!({
+                "type": "issue",
+                "check_name": result.check_name(),
+                "description": description,
+                "categories": ["Bug Risk"],
+                "severity": gitlab_severity(analyzer.severity(result)),
+                "fingerprint": fingerprint(&[result.check_name(), module, &description]),
+                "location": {
+                    "path": path,
+                    "lines": { "begin": line },
+                },
+            })
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(&mut *out, &issues)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Findings grouped by application into collapsed tables, meant to be
+/// posted as a pull request comment
+fn write_markdown(
+    results: &[(Atom, AnalysisResult)],
+    analyzer: &Analyzer,
+    interner: &Interner,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut apps = BTreeMap::<&str, Vec<_>>::new();
+    for (module, result) in results {
+        let app = analyzer
+            .result_app(*module, result)
+            .and_then(|app| app.resolve(interner))
+            .unwrap_or("unknown");
+        apps.entry(app).or_default().push((module, result));
+    }
+
+    writeln!(out, "### xref: {}\n", count(results.len(), "finding"))?;
+    if results.is_empty() {
+        writeln!(out, "No findings :tada:")?;
+    }
+
+    for (app, mut findings) in apps {
+        findings.sort_by_key(|(module, result)| {
+            (Reverse(analyzer.severity(result)), module.resolve(interner))
+        });
+        let severity_count = |severity| {
+            findings
+                .iter()
+                .filter(|(_, result)| analyzer.severity(result) == severity)
+                .count()
+        };
+
+        writeln!(out, "<details>")?;
+        writeln!(
+            out,
+            "<summary><code>{}</code>: {} ({}, {}, {} info)</summary>\n",
+            app,
+            count(findings.len(), "finding"),
+            count(severity_count(Severity::Error), "error"),
+            count(severity_count(Severity::Warning), "warning"),
+            severity_count(Severity::Info)
+        )?;
+        writeln!(out, "| Severity | Check | Module | Message |")?;
+        writeln!(out, "|---|---|---|---|")?;
+        for (module, result) in findings {
+            writeln!(
+                out,
+                "| {} | {} | `{}` | {} |",
+                json_severity(analyzer.severity(result)),
+                result.check_name(),
+                module.resolve(interner).unwrap(),
+                result.fmt(interner).replace('|', "\\|")
+            )?;
+        }
+        writeln!(out, "\n</details>\n")?;
+    }
+    Ok(())
+}
+
+fn count(count: usize, what: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, what)
+    } else {
+        format!("{} {}s", count, what)
+    }
+}
+
+/// A list of `{Check, Target, Caller}` tuples terminated with a dot, so it
+/// can be read back with `file:consult/1`. Undefined functions use the same
+/// `{M, F, A}` shape as `xref:analyze/2`.
+fn write_erlang(
+    results: &[(Atom, AnalysisResult)],
+    interner: &Interner,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let atom = |atom: &Atom| quote_atom(atom.resolve(interner).unwrap());
+
+    writeln!(out, "[")?;
+    for (i, (module, result)) in results.iter().enumerate() {
+        let target = match result {
+            AnalysisResult::MissingModule(module)
+            | AnalysisResult::MissingOptionalModule { module, .. }
+            | AnalysisResult::CheckedMissingModule { module, .. }
+            | AnalysisResult::DeadModule(module)
+            | AnalysisResult::UndefinedBehaviour { behaviour: module }
+            | AnalysisResult::UndefinedParseTransform { transform: module }
+            | AnalysisResult::UnlistedModule { module, .. }
+            | AnalysisResult::MissingBeam { module, .. }
+            | AnalysisResult::TestCodeCall { module }
+            | AnalysisResult::AppCallback {
+                callback: module, ..
+            } => atom(module),
+            AnalysisResult::MissingFunction(module, fun, arity, _)
+            | AnalysisResult::ArityMismatch {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::MacroCall {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UnexportedFunction {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UnusedExport(module, fun, arity)
+            | AnalysisResult::UnusedLocal(module, fun, arity)
+            | AnalysisResult::NifWithoutLibrary {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UndefinedDynamicCall {
+                module,
+                function: fun,
+                arity,
+            }
+            | AnalysisResult::UndefinedCapture {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::DeprecatedCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::ObsoleteCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::ForbiddenCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::RiskyCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::GroupBoundary {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::InternalModuleCall {
+                module,
+                function: fun,
+                arity,
+                ..
+            }
+            | AnalysisResult::LayerViolation {
+                module,
+                function: fun,
+                arity,
+                ..
+            } => format!("{{{},{},{}}}", atom(module), atom(fun), arity),
+            AnalysisResult::MissingDependency {
+                module,
+                app_from,
+                app_to,
+            } => format!("{{{},{},{}}}", atom(app_from), atom(app_to), atom(module)),
+            AnalysisResult::DependencyNotFound { dep, .. }
+            | AnalysisResult::UnusedDependency { dep, .. }
+            | AnalysisResult::RedundantDependency { dep, .. }
+            | AnalysisResult::SelfDependency { app: dep }
+            | AnalysisResult::UndeclaredRuntimeDependency { dep, .. }
+            | AnalysisResult::UnusedRuntimeDependency { dep, .. }
+            | AnalysisResult::DuplicateModule { other: dep, .. } => atom(dep),
+            AnalysisResult::RegisteredNameClash { name, .. } => atom(name),
+            AnalysisResult::AppCycle { apps: atoms, .. }
+            | AnalysisResult::ModuleCycle { modules: atoms } => {
+                let atoms: Vec<_> = atoms.iter().map(atom).collect();
+                format!("[{}]", atoms.join(","))
+            }
+            AnalysisResult::OtpModuleClash { otp_app, .. } => quote_atom(otp_app),
+            AnalysisResult::MissingSource { .. }
+            | AnalysisResult::MissingPrivFile { .. }
+            | AnalysisResult::DifferentBuild { .. } => quote_string(&result.target(interner)),
+            AnalysisResult::MissingOnLoad { module, function } => {
+                format!("{{{},{},0}}", atom(module), atom(function))
+            }
+        };
+        let separator = if i + 1 < results.len() { "," } else { "" };
+        writeln!(
+            out,
+            " {{{},{},{}}}{}",
+            result.check_name(),
+            target,
+            atom(module),
+            separator
+        )?;
+    }
+    writeln!(out, "].")?;
+    Ok(())
+}
+
+const RESERVED_WORDS: &[&str] = &[
+    "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case",
+    "catch", "cond", "div", "else", "end", "fun", "if", "let", "maybe", "not", "of", "or",
+    "orelse", "receive", "rem", "try", "when", "xor",
+];
+
+fn quote_atom(name: &str) -> String {
+    let bare = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+        && !RESERVED_WORDS.contains(&name);
+
+    if bare {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+fn quote_string(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    path.strip_prefix(base).unwrap_or(path).to_path_buf()
+}
+
+fn gitlab_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Warning => "minor",
+        Severity::Error => "major",
+    }
+}
+
+/// Fingerprints have to be stable between runs, so that GitLab can track
+/// which findings were introduced or resolved by a merge request
+fn fingerprint(parts: &[&str]) -> String {
+    format!("{:016x}", fxhash::hash64(&parts))
+}
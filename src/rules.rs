@@ -0,0 +1,209 @@
+//! Call policies declared in `xref.toml`, checked like any other finding.
+//!
+//! ```toml
+//! [[forbidden_calls]]
+//! apps = ["core"]
+//! calls = ["io:format/2", "hackney"]
+//! reason = "core doesn't do IO"
+//!
+//! # From the top down, every layer calls only the ones below it
+//! [[layers]]
+//! name = "web"
+//! apps = ["web", "api_*"]
+//!
+//! [[layers]]
+//! name = "domain"
+//! apps = ["core"]
+//!
+//! [[layers]]
+//! name = "storage"
+//! apps = ["storage"]
+//!
+//! # Call only within themselves and into the groups they may call
+//! [[groups]]
+//! name = "accounts"
+//! apps = ["accounts", "accounts_*"]
+//! may_call = ["shared"]
+//!
+//! [[groups]]
+//! name = "shared"
+//! apps = ["shared_*"]
+//!
+//! # Reported by the risky_call check, on top of the built-in calls
+//! [[risky_calls]]
+//! calls = ["my_shell:run/1"]
+//! reason = "runs a shell command"
+//!
+//! # Code only production code must not call
+//! [test_code]
+//! apps = ["*_test_support"]
+//! modules = ["*_SUITE", "*_tests", "mock_*"]
+//! lib_paths = ["_build/test/lib"]
+//! ```
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::pattern::{FunctionPattern, Pattern};
+
+/// Functions the matching modules must not call. Callers are all modules
+/// of the matching applications, narrowed down by `modules` when given.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForbiddenCalls {
+    #[serde(default)]
+    pub apps: Vec<Pattern>,
+    #[serde(default)]
+    pub modules: Vec<Pattern>,
+    pub calls: Vec<FunctionPattern>,
+    pub reason: Option<String>,
+}
+
+impl ForbiddenCalls {
+    pub fn applies_to(&self, app: Option<&str>, module: &str) -> bool {
+        (self.apps.is_empty()
+            || app.is_some_and(|app| self.apps.iter().any(|pattern| pattern.matches(app))))
+            && (self.modules.is_empty()
+                || self.modules.iter().any(|pattern| pattern.matches(module)))
+    }
+
+    /// The first pattern matching the call
+    pub fn forbids(&self, module: &str, function: &str, arity: u32) -> Option<&FunctionPattern> {
+        self.calls
+            .iter()
+            .find(|pattern| pattern.matches(module, function, arity))
+    }
+}
+
+/// Calls the opt-in risky_call check reports every caller of, for a
+/// security audit
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskyCalls {
+    pub calls: Vec<FunctionPattern>,
+    pub reason: String,
+}
+
+/// Built-in risky calls, as patterns and the risk
+pub const RISKY_CALLS: &[(&str, &str)] = &[
+    ("os:cmd", "runs a shell command"),
+    ("erlang:open_port/2", "runs an external program"),
+    ("Elixir.System:cmd", "runs an external program"),
+    ("Elixir.Port:open/2", "runs an external program"),
+    (
+        "erlang:binary_to_term/1",
+        "decodes data without the safe option, creating atoms and funs",
+    ),
+    ("erlang:md5/1", "MD5 is a broken hash"),
+    ("crypto:md5", "MD5 is a broken hash"),
+    ("crypto:sha/1", "SHA-1 is a broken hash"),
+];
+
+/// Hashes and ciphers of `crypto` considered broken, reported when a
+/// function calling `crypto` names them
+pub const WEAK_ALGORITHMS: &[&str] = &[
+    "md4",
+    "md5",
+    "sha",
+    "des_cbc",
+    "des_cfb",
+    "des_ecb",
+    "des3_cbc",
+    "des_ede3",
+    "des_ede3_cbc",
+    "des_ede3_cfb",
+    "rc2_cbc",
+    "rc4",
+    "blowfish_cbc",
+    "blowfish_cfb64",
+    "blowfish_ecb",
+    "blowfish_ofb64",
+];
+
+/// Applications at one level of the architecture. Layers are declared from
+/// the top down, and call the layers below them, or only the ones listed
+/// in `may_call`. Applications outside of every layer are unconstrained.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    pub name: String,
+    pub apps: Vec<Pattern>,
+    pub may_call: Option<Vec<String>>,
+}
+
+impl Layer {
+    pub fn contains(&self, app: &str) -> bool {
+        self.apps.iter().any(|pattern| pattern.matches(app))
+    }
+}
+
+/// Whether modules of layer `from` may call into layer `to`, by index
+pub fn layer_allows(layers: &[Layer], from: usize, to: usize) -> bool {
+    if from == to {
+        return true;
+    }
+    match &layers[from].may_call {
+        Some(names) => names.contains(&layers[to].name),
+        None => to > from,
+    }
+}
+
+/// Applications tagged together, like the contexts of an umbrella. Groups
+/// call only into themselves and the groups listed in `may_call`. An
+/// application can be in several groups, and is unconstrained outside of
+/// every group.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    pub name: String,
+    pub apps: Vec<Pattern>,
+    #[serde(default)]
+    pub may_call: Vec<String>,
+}
+
+impl Group {
+    pub fn contains(&self, app: &str) -> bool {
+        self.apps.iter().any(|pattern| pattern.matches(app))
+    }
+}
+
+/// Whether modules of an application in groups `from` may call into one in
+/// groups `to`, by index: they share a group, or one of `from` may call one
+/// of `to`
+pub fn groups_allow(groups: &[Group], from: &[usize], to: &[usize]) -> bool {
+    to.iter().any(|to| {
+        from.contains(to)
+            || from
+                .iter()
+                .any(|&from| groups[from].may_call.contains(&groups[*to].name))
+    })
+}
+
+/// Test-only code. Applications loaded only from the `lib_paths` are test
+/// code too, like the test dependencies rebar3 puts in `_build/test/lib`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestCode {
+    pub apps: Vec<Pattern>,
+    /// Common Test suites and EUnit test modules when not given
+    pub modules: Vec<Pattern>,
+    pub lib_paths: Vec<PathBuf>,
+}
+
+impl Default for TestCode {
+    fn default() -> TestCode {
+        TestCode {
+            apps: vec![],
+            modules: vec!["*_SUITE".parse().unwrap(), "*_tests".parse().unwrap()],
+            lib_paths: vec![],
+        }
+    }
+}
+
+impl TestCode {
+    pub fn contains(&self, app: Option<&str>, module: &str) -> bool {
+        self.modules.iter().any(|pattern| pattern.matches(module))
+            || app.is_some_and(|app| self.apps.iter().any(|pattern| pattern.matches(app)))
+    }
+}
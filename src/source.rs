@@ -0,0 +1,521 @@
+//! Reading Erlang source files of applications that weren't built: just
+//! enough of the syntax to find the module's exports, behaviours and the
+//! remote calls and captures of its functions. Macros other than `?MODULE`
+//! aren't expanded, so calls through them are missed, and attributes like
+//! `-spec` aren't looked into.
+
+use anyhow::Result;
+
+/// What `parse` found in a module
+#[derive(Debug, Default)]
+pub struct SourceModule {
+    pub name: String,
+    pub exports: Vec<(String, u32)>,
+    /// Functions defined but not exported
+    pub locals: Vec<(String, u32)>,
+    pub behaviours: Vec<String>,
+    pub calls: Vec<Call>,
+}
+
+#[derive(Debug)]
+pub struct Call {
+    pub module: String,
+    pub function: String,
+    pub arity: u32,
+    pub line: u32,
+    /// A `fun Module:Function/Arity` capture rather than a call
+    pub capture: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Atom(String),
+    /// Unquoted reserved words, like `case` and `end`
+    Keyword(String),
+    Var,
+    Macro(String),
+    Int(u32),
+    /// `(`, `[`, `{` and `<<`
+    Open,
+    /// `)`, `]`, `}` and `>>`
+    Close,
+    Colon,
+    Comma,
+    Slash,
+    Minus,
+    /// The dot ending a form
+    Dot,
+    /// Strings, characters, floats and other punctuation
+    Other,
+}
+
+const KEYWORDS: &[&str] = &[
+    "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case",
+    "catch", "cond", "div", "else", "end", "fun", "if", "let", "maybe", "not", "of", "or",
+    "orelse", "receive", "rem", "try", "when", "xor",
+];
+
+/// Keywords starting an expression closed by `end`, besides funs
+const BLOCKS: &[&str] = &["begin", "case", "if", "maybe", "receive", "try"];
+
+pub fn parse(text: &str) -> Result<SourceModule> {
+    let tokens = tokenize(text)?;
+    let mut module = SourceModule::default();
+    let mut export_all = false;
+    let mut defined = vec![];
+    let mut calls = vec![];
+
+    for form in tokens.split(|(token, _)| *token == Token::Dot) {
+        let tokens: Vec<_> = form.iter().map(|(token, _)| token).collect();
+        match tokens.as_slice() {
+            [Token::Minus, Token::Atom(attribute), rest @ ..] => match attribute.as_str() {
+                "module" => {
+                    if let Some(name) = rest.iter().find_map(|token| atom(token)) {
+                        module.name = name.to_string();
+                    }
+                }
+                "export" => {
+                    module
+                        .exports
+                        .extend(rest.windows(3).filter_map(|window| match window {
+                            [Token::Atom(name), Token::Slash, Token::Int(arity)] => {
+                                Some((name.clone(), *arity))
+                            }
+                            _ => None,
+                        }))
+                }
+                "compile" => {
+                    export_all |= rest.iter().any(|token| atom(token) == Some("export_all"))
+                }
+                "behaviour" | "behavior" => {
+                    if let Some(name) = rest.iter().find_map(|token| atom(token)) {
+                        module.behaviours.push(name.to_string());
+                    }
+                }
+                _ => {}
+            },
+            [Token::Atom(name), Token::Open, ..] => {
+                defined.push((name.clone(), arguments(form, 1)));
+                find_calls(form, &mut calls);
+            }
+            _ => {}
+        }
+    }
+
+    if module.name.is_empty() {
+        anyhow::bail!("missing -module attribute");
+    }
+    defined.dedup();
+    if export_all {
+        module.exports = defined;
+    } else {
+        module.locals = defined
+            .into_iter()
+            .filter(|function| !module.exports.contains(function))
+            .collect();
+    }
+    module.calls = calls
+        .into_iter()
+        .map(|(module_name, function, arity, line, capture)| Call {
+            module: module_name.unwrap_or_else(|| module.name.clone()),
+            function,
+            arity,
+            line,
+            capture,
+        })
+        .collect();
+    Ok(module)
+}
+
+fn atom(token: &Token) -> Option<&str> {
+    match token {
+        Token::Atom(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// The module of a remote call or capture, `Some(None)` for `?MODULE`
+fn module(token: &Token) -> Option<Option<String>> {
+    match token {
+        Token::Atom(name) => Some(Some(name.clone())),
+        Token::Macro(name) if name == "MODULE" => Some(None),
+        _ => None,
+    }
+}
+
+type FoundCall = (Option<String>, String, u32, u32, bool);
+
+/// Remote calls, `m:f(...)`, and captures, `fun m:f/A`, of a function
+fn find_calls(form: &[(Token, u32)], out: &mut Vec<FoundCall>) {
+    for (i, window) in form.windows(4).enumerate() {
+        let line = window[0].1;
+        match window {
+            [(callee, _), (Token::Colon, _), (Token::Atom(function), _), (Token::Open, _)] => {
+                if let Some(callee) = module(callee) {
+                    let arity = arguments(form, i + 3);
+                    out.push((callee, function.clone(), arity, line, false));
+                }
+            }
+            [(Token::Keyword(fun), _), (callee, _), (Token::Colon, _), (Token::Atom(function), _)]
+                if fun == "fun" =>
+            {
+                let arity = match form.get(i + 4..i + 6) {
+                    Some([(Token::Slash, _), (Token::Int(arity), _)]) => *arity,
+                    _ => continue,
+                };
+                if let Some(callee) = module(callee) {
+                    out.push((callee, function.clone(), arity, line, true));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// How many arguments the parentheses opening at `open` enclose: the
+/// commas between them that aren't nested in brackets or blocks
+fn arguments(form: &[(Token, u32)], open: usize) -> u32 {
+    let mut depth = 0;
+    let mut commas = 0;
+    for (i, (token, _)) in form.iter().enumerate().skip(open) {
+        match token {
+            Token::Open => depth += 1,
+            Token::Keyword(keyword) if BLOCKS.contains(&keyword.as_str()) => depth += 1,
+            // Funs with a body, `fun(...) -> ... end` or
+            // `fun Name(...) -> ... end`, unlike `fun m:f/1`
+            Token::Keyword(keyword) if keyword == "fun" => {
+                let next = form.get(i + 1..i + 3).unwrap_or_default();
+                if let [(Token::Open, _), ..] | [(Token::Var, _), (Token::Open, _)] = next {
+                    depth += 1;
+                }
+            }
+            Token::Close | Token::Keyword(_) if is_close(token) => {
+                depth -= 1;
+                if depth == 0 {
+                    return if matches!(form.get(open + 1), Some((Token::Close, _))) {
+                        0
+                    } else {
+                        commas + 1
+                    };
+                }
+            }
+            Token::Comma if depth == 1 => commas += 1,
+            _ => {}
+        }
+    }
+    commas + 1
+}
+
+fn is_close(token: &Token) -> bool {
+    match token {
+        Token::Close => true,
+        Token::Keyword(keyword) => keyword == "end",
+        _ => false,
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, u32)>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    let name_end = |start: usize| {
+        start
+            + chars[start..]
+                .iter()
+                .take_while(|&&c| c.is_alphanumeric() || c == '_' || c == '@')
+                .count()
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start_line = line;
+        let token = match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '%' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            c if c.is_lowercase() => {
+                let end = name_end(i);
+                let name: String = chars[i..end].iter().collect();
+                i = end;
+                if KEYWORDS.contains(&name.as_str()) {
+                    Token::Keyword(name)
+                } else {
+                    Token::Atom(name)
+                }
+            }
+            c if c.is_uppercase() || c == '_' => {
+                i = name_end(i);
+                Token::Var
+            }
+            '\'' => {
+                let (name, end) = quoted(&chars, i, &mut line)?;
+                i = end;
+                Token::Atom(name)
+            }
+            '"' => {
+                // Triple-quoted strings end at the first `"""`
+                i = if chars[i..].starts_with(&['"', '"', '"']) {
+                    let mut end = i + 3;
+                    while end < chars.len() && !chars[end..].starts_with(&['"', '"', '"']) {
+                        if chars[end] == '\n' {
+                            line += 1;
+                        }
+                        end += 1;
+                    }
+                    end + 3
+                } else {
+                    quoted(&chars, i, &mut line)?.1
+                };
+                Token::Other
+            }
+            '$' => {
+                i += 1;
+                if chars.get(i) == Some(&'\\') {
+                    i += 1;
+                    match chars.get(i) {
+                        Some('x') if chars.get(i + 1) == Some(&'{') => {
+                            while i < chars.len() && chars[i] != '}' {
+                                i += 1;
+                            }
+                        }
+                        Some('^') => i += 1,
+                        Some(c) if c.is_digit(8) => {
+                            while chars.get(i + 1).is_some_and(|c| c.is_digit(8)) {
+                                i += 1;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if chars.get(i) == Some(&'\n') {
+                    line += 1;
+                }
+                i += 1;
+                Token::Other
+            }
+            '0'..='9' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '_') {
+                    i += 1;
+                }
+                let digits: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+                if chars.get(i) == Some(&'#') {
+                    i = name_end(i + 1);
+                    Token::Other
+                } else if chars.get(i) == Some(&'.')
+                    && chars.get(i + 1).is_some_and(char::is_ascii_digit)
+                {
+                    i = name_end(i + 1);
+                    if chars.get(i - 1).is_some_and(|&c| c == 'e' || c == 'E')
+                        && chars.get(i).is_some_and(|&c| c == '-' || c == '+')
+                    {
+                        i = name_end(i + 1);
+                    }
+                    Token::Other
+                } else {
+                    digits.parse().map_or(Token::Other, Token::Int)
+                }
+            }
+            '?' => {
+                i += 1;
+                if chars.get(i) == Some(&'?') {
+                    i += 1;
+                }
+                let start = i;
+                if chars.get(i) == Some(&'\'') {
+                    let (name, end) = quoted(&chars, i, &mut line)?;
+                    i = end;
+                    Token::Macro(name)
+                } else {
+                    i = name_end(i);
+                    Token::Macro(chars[start..i].iter().collect())
+                }
+            }
+            '.' => {
+                i += 1;
+                match chars.get(i) {
+                    None => Token::Dot,
+                    Some(&c) if c.is_whitespace() || c == '%' => Token::Dot,
+                    _ => Token::Other,
+                }
+            }
+            ':' => {
+                i += 1;
+                match chars.get(i) {
+                    Some(':') | Some('=') => {
+                        i += 1;
+                        Token::Other
+                    }
+                    _ => Token::Colon,
+                }
+            }
+            '<' if chars.get(i + 1) == Some(&'<') => {
+                i += 2;
+                Token::Open
+            }
+            '>' if chars.get(i + 1) == Some(&'>') => {
+                i += 2;
+                Token::Close
+            }
+            '(' | '[' | '{' => {
+                i += 1;
+                Token::Open
+            }
+            ')' | ']' | '}' => {
+                i += 1;
+                Token::Close
+            }
+            ',' => {
+                i += 1;
+                Token::Comma
+            }
+            '/' => {
+                i += 1;
+                Token::Slash
+            }
+            '-' => {
+                i += 1;
+                Token::Minus
+            }
+            _ => {
+                i += 1;
+                Token::Other
+            }
+        };
+        tokens.push((token, start_line));
+    }
+    Ok(tokens)
+}
+
+/// The text of the quoted atom or string starting at `start`, and the index
+/// right after its closing quote
+fn quoted(chars: &[char], start: usize, line: &mut u32) -> Result<(String, usize)> {
+    let quote = chars[start];
+    let mut text = String::new();
+    let mut i = start + 1;
+    loop {
+        match chars.get(i) {
+            Some(&c) if c == quote => return Ok((text, i + 1)),
+            // Escapes other than of the quote are rare in names, and kept
+            // as the escaped character
+            Some('\\') => {
+                text.extend(chars.get(i + 1));
+                i += 2;
+            }
+            Some(&c) => {
+                if c == '\n' {
+                    *line += 1;
+                }
+                text.push(c);
+                i += 1;
+            }
+            None => anyhow::bail!("line {}: missing closing {}", line, quote),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calls(module: &SourceModule) -> Vec<(&str, &str, u32, u32, bool)> {
+        module
+            .calls
+            .iter()
+            .map(|call| {
+                let Call {
+                    module,
+                    function,
+                    arity,
+                    line,
+                    capture,
+                } = call;
+                (module.as_str(), function.as_str(), *arity, *line, *capture)
+            })
+            .collect()
+    }
+
+    fn names(functions: &[(String, u32)]) -> Vec<(&str, u32)> {
+        functions
+            .iter()
+            .map(|(name, arity)| (name.as_str(), *arity))
+            .collect()
+    }
+
+    #[test]
+    fn exports_and_locals() {
+        let module = parse(
+            "-module(m).\n\
+             -behaviour(gen_server).\n\
+             -export([start/0, init/1]).\n\
+             start() -> ok.\n\
+             init(_) -> helper(1, 2).\n\
+             helper(A, B) -> {A, B}.\n",
+        )
+        .unwrap();
+        assert_eq!(module.name, "m");
+        assert_eq!(module.behaviours, ["gen_server"]);
+        assert_eq!(names(&module.exports), [("start", 0), ("init", 1)]);
+        assert_eq!(names(&module.locals), [("helper", 2)]);
+    }
+
+    #[test]
+    fn export_all() {
+        let module = parse(
+            "-module(m).\n\
+             -compile(export_all).\n\
+             f() -> ok.\n\
+             g(X) -> X;\n\
+             g(_) -> error.\n",
+        )
+        .unwrap();
+        assert_eq!(names(&module.exports), [("f", 0), ("g", 1)]);
+        assert!(module.locals.is_empty());
+    }
+
+    #[test]
+    fn arity_with_nested_expressions() {
+        let module = parse(
+            "-module(m).\n\
+             f(L, X) ->\n\
+             \x20   lists:foldl(fun(E, Acc) -> [E | Acc] end,\n\
+             \x20               case X of a -> [], _ -> [x, y] end,\n\
+             \x20               <<1, 2>>).\n",
+        )
+        .unwrap();
+        assert_eq!(calls(&module), [("lists", "foldl", 3, 3, false)]);
+    }
+
+    #[test]
+    fn module_macro_calls() {
+        let module = parse("-module(m).\nf() -> ?MODULE:g(1, 2).\ng(A, B) -> {A, B}.\n").unwrap();
+        assert_eq!(calls(&module), [("m", "g", 2, 2, false)]);
+    }
+
+    #[test]
+    fn captures() {
+        let module = parse("-module(m).\nf() -> fun other:g/2.\n").unwrap();
+        assert_eq!(calls(&module), [("other", "g", 2, 2, true)]);
+    }
+
+    #[test]
+    fn errors() {
+        let err = parse("-module(m).\nf() -> \"unterminated.\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 3: missing closing \"");
+        let err = parse("f() -> ok.\n").unwrap_err();
+        assert_eq!(err.to_string(), "missing -module attribute");
+    }
+}
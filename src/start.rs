@@ -0,0 +1,101 @@
+//! The order applications can be started in, like the boot script of a
+//! release starts them: every application after the ones listed in its
+//! `applications`. Included applications are started by their includer,
+//! not on their own.
+
+use std::io::Write;
+
+use anyhow::Result;
+use fxhash::FxHashSet;
+use petgraph::algo;
+
+use crate::{
+    analyzer::Analyzer,
+    types::{AppDeps, Atom},
+};
+
+/// Loaded applications `roots` need started, through their declared
+/// dependencies, and those dependencies between them. Without roots, all
+/// loaded applications.
+fn start_graph(analyzer: &Analyzer, roots: &[Atom]) -> AppDeps {
+    let mut graph = AppDeps::default();
+    let mut queue: Vec<_> = if roots.is_empty() {
+        analyzer.app_modules().keys().copied().collect()
+    } else {
+        roots.to_vec()
+    };
+    let mut seen = FxHashSet::default();
+    while let Some(app) = queue.pop() {
+        if !seen.insert(app) {
+            continue;
+        }
+        graph.add_node(app);
+        for &dep in analyzer.declared_deps(app) {
+            if analyzer.app_modules().contains_key(&dep) {
+                graph.add_edge(app, dep, ());
+                queue.push(dep);
+            }
+        }
+    }
+    graph
+}
+
+/// Applications in an order they can be started in, dependencies first,
+/// or the dependencies in a cycle of every set of applications that can't
+/// be ordered. Roots are started in the order given, all applications by
+/// name without roots; dependencies in the order of the `.app` file.
+pub fn order(analyzer: &Analyzer, roots: &[Atom]) -> Result<Vec<Atom>, Vec<Vec<(Atom, Atom)>>> {
+    let graph = start_graph(analyzer, roots);
+
+    let mut cycles: Vec<_> = algo::tarjan_scc(&graph)
+        .into_iter()
+        .filter(|apps| apps.len() > 1 || graph.contains_edge(apps[0], apps[0]))
+        .map(|apps| analyzer.cycle_edges(&graph, &apps))
+        .collect();
+    if !cycles.is_empty() {
+        cycles.sort_by_key(|edges| analyzer.name(edges[0].0));
+        return Err(cycles);
+    }
+
+    let mut roots = roots.to_vec();
+    if roots.is_empty() {
+        roots = graph.nodes().collect();
+        roots.sort_by_key(|&app| analyzer.name(app));
+    }
+    let mut order = vec![];
+    let mut started = FxHashSet::default();
+    for root in roots {
+        start(analyzer, &graph, root, &mut started, &mut order);
+    }
+    Ok(order)
+}
+
+fn start(
+    analyzer: &Analyzer,
+    graph: &AppDeps,
+    app: Atom,
+    started: &mut FxHashSet<Atom>,
+    order: &mut Vec<Atom>,
+) {
+    if !started.insert(app) {
+        return;
+    }
+    for &dep in analyzer.declared_deps(app) {
+        if graph.contains_node(dep) {
+            start(analyzer, graph, dep, started, order);
+        }
+    }
+    order.push(app);
+}
+
+pub fn write(analyzer: &Analyzer, order: &[Atom], out: &mut dyn Write) -> Result<()> {
+    let width = order.len().to_string().len();
+    for (idx, &app) in order.iter().enumerate() {
+        let label = match analyzer.app_version(app) {
+            Some(vsn) => format!("{} {}", analyzer.name(app), vsn),
+            None => analyzer.name(app).to_string(),
+        };
+        writeln!(out, "{:>width$}. {}", idx + 1, label, width = width)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,485 @@
+//! Parser for Erlang terms in text files, like `.app` files, read the way
+//! `file:consult/1` reads them: a sequence of terms, each ended by a dot,
+//! with `%` comments. The terms are the ones decoded from the External
+//! Term Format, so the `etf` helpers pick them apart too.
+
+use std::{collections::HashMap, convert::TryFrom, iter::Peekable, str::Chars};
+
+use anyhow::Result;
+use eetf::{
+    Atom, BigInteger, Binary, ByteList, FixInteger, Float, ImproperList, List, Map, Term, Tuple,
+};
+
+/// All the terms of `text`
+pub fn consult(text: &str) -> Result<Vec<Term>> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+        line: 1,
+    };
+    let mut terms = vec![];
+    while {
+        parser.skip_blank();
+        parser.chars.peek().is_some()
+    } {
+        terms.push(parser.term()?);
+        parser.expect('.')?;
+    }
+    Ok(terms)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn error<T>(&self, message: impl std::fmt::Display) -> Result<T> {
+        anyhow::bail!("line {}: {}", self.line, message)
+    }
+
+    /// Skips whitespace and comments
+    fn skip_blank(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == '%' {
+                while self.chars.peek().is_some_and(|&c| c != '\n') {
+                    self.next();
+                }
+            } else if c.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_blank();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => self.error(format!("expected `{}`, found `{}`", expected, c)),
+            None => self.error(format!(
+                "expected `{}`, found the end of the file",
+                expected
+            )),
+        }
+    }
+
+    /// Consumes `expected` if it comes next
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_blank();
+        if self.chars.peek() == Some(&expected) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn term(&mut self) -> Result<Term> {
+        self.skip_blank();
+        let c = match self.chars.peek() {
+            Some(&c) => c,
+            None => return self.error("expected a term, found the end of the file"),
+        };
+        match c {
+            '{' => {
+                self.next();
+                let elements = self.sequence('}')?;
+                Ok(Tuple::from(elements).into())
+            }
+            '[' => {
+                self.next();
+                self.list()
+            }
+            '#' => {
+                self.next();
+                self.expect('{')?;
+                self.map()
+            }
+            '<' => {
+                self.next();
+                self.expect('<')?;
+                self.binary()
+            }
+            '"' => Ok(string_term(self.strings()?)),
+            '\'' => {
+                self.next();
+                let name = self.quoted('\'')?;
+                Ok(Atom::from(name).into())
+            }
+            '$' => {
+                self.next();
+                let c = match self.next() {
+                    Some('\\') => self.escape()?,
+                    Some(c) => c,
+                    None => return self.error("expected a character after `$`"),
+                };
+                Ok(FixInteger::from(c as i32).into())
+            }
+            '-' | '0'..='9' => self.number(),
+            c if c.is_lowercase() => {
+                let name = self.name();
+                Ok(Atom::from(name).into())
+            }
+            c => self.error(format!("unexpected `{}`", c)),
+        }
+    }
+
+    /// Comma separated terms up to `close`
+    fn sequence(&mut self, close: char) -> Result<Vec<Term>> {
+        let mut elements = vec![];
+        if self.eat(close) {
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.term()?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                return Ok(elements);
+            }
+        }
+    }
+
+    fn list(&mut self) -> Result<Term> {
+        let mut elements = vec![];
+        if self.eat(']') {
+            return Ok(List::nil().into());
+        }
+        loop {
+            elements.push(self.term()?);
+            if self.eat('|') {
+                let last = self.term()?;
+                self.expect(']')?;
+                return Ok(match last {
+                    Term::List(tail) => {
+                        elements.extend(tail.elements);
+                        List::from(elements).into()
+                    }
+                    last => ImproperList::from((elements, last)).into(),
+                });
+            }
+            if !self.eat(',') {
+                self.expect(']')?;
+                return Ok(List::from(elements).into());
+            }
+        }
+    }
+
+    fn map(&mut self) -> Result<Term> {
+        let mut map = HashMap::new();
+        if self.eat('}') {
+            return Ok(Map::from(map).into());
+        }
+        loop {
+            let key = self.term()?;
+            self.expect('=')?;
+            self.expect('>')?;
+            map.insert(key, self.term()?);
+            if !self.eat(',') {
+                self.expect('}')?;
+                return Ok(Map::from(map).into());
+            }
+        }
+    }
+
+    /// `<<"text">>` and `<<1, 2, 3>>`, with no sizes or types
+    fn binary(&mut self) -> Result<Term> {
+        let mut bytes = vec![];
+        if !self.eat('>') {
+            loop {
+                self.skip_blank();
+                if self.chars.peek() == Some(&'"') {
+                    bytes.extend(self.strings()?.bytes());
+                } else {
+                    match self.number()? {
+                        Term::FixInteger(int) if (0..256).contains(&int.value) => {
+                            bytes.push(int.value as u8)
+                        }
+                        _ => return self.error("expected a byte in the binary"),
+                    }
+                }
+                if !self.eat(',') {
+                    self.expect('>')?;
+                    break;
+                }
+            }
+        }
+        self.expect('>')?;
+        Ok(Binary::from(bytes).into())
+    }
+
+    /// A string, and the ones right after it, as adjacent strings are
+    /// concatenated
+    fn strings(&mut self) -> Result<String> {
+        let mut string = String::new();
+        while {
+            self.skip_blank();
+            self.chars.peek() == Some(&'"')
+        } {
+            self.next();
+            string.push_str(&self.quoted('"')?);
+        }
+        Ok(string)
+    }
+
+    /// The rest of a quoted atom or string, after the opening quote
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some(c) if c == quote => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(c) => string.push(c),
+                None => return self.error(format!("missing closing {}", quote)),
+            }
+        }
+    }
+
+    /// The character of an escape sequence, after the backslash
+    fn escape(&mut self) -> Result<char> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return self.error("unfinished escape sequence"),
+        };
+        let c = match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\x0b',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'e' => '\x1b',
+            's' => ' ',
+            'd' => '\x7f',
+            '0'..='7' => {
+                let mut code = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match self.chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            self.next();
+                            code = code * 8 + digit;
+                        }
+                        None => break,
+                    }
+                }
+                self.char(code)?
+            }
+            'x' => {
+                let mut digits = String::new();
+                if self.chars.peek() == Some(&'{') {
+                    self.next();
+                    while let Some(c) = self.next().filter(|&c| c != '}') {
+                        digits.push(c);
+                    }
+                } else {
+                    digits.extend(self.next());
+                    digits.extend(self.next());
+                }
+                match u32::from_str_radix(&digits, 16) {
+                    Ok(code) => self.char(code)?,
+                    Err(_) => return self.error(format!("invalid escape: \\x{}", digits)),
+                }
+            }
+            '^' => match self.next() {
+                Some(c) if c.is_ascii_alphabetic() => self.char(c as u32 % 32)?,
+                _ => return self.error("invalid control character escape"),
+            },
+            c => c,
+        };
+        Ok(c)
+    }
+
+    fn char(&self, code: u32) -> Result<char> {
+        match char::from_u32(code) {
+            Some(c) => Ok(c),
+            None => self.error(format!("invalid character: {}", code)),
+        }
+    }
+
+    /// An unquoted atom
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '@' {
+                name.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    /// Integers, also as `Base#Digits`, and floats
+    fn number(&mut self) -> Result<Term> {
+        let mut text = String::new();
+        if self.chars.peek() == Some(&'-') {
+            text.push('-');
+            self.next();
+        }
+        let mut float = false;
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '0'..='9' | '_' => {}
+                // A dot is the end of the term unless a digit follows it
+                '.' if !float && !text.contains('#') => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    if !ahead.peek().is_some_and(char::is_ascii_digit) {
+                        break;
+                    }
+                    float = true;
+                }
+                'e' | 'E' if float => {
+                    text.push(c);
+                    self.next();
+                    if let Some(&sign @ ('-' | '+')) = self.chars.peek() {
+                        text.push(sign);
+                        self.next();
+                    }
+                    continue;
+                }
+                '#' if !float && !text.contains('#') => {}
+                c if c.is_ascii_alphanumeric() && text.contains('#') => {}
+                _ => break,
+            }
+            text.push(c);
+            self.next();
+        }
+        let text = text.replace('_', "");
+
+        if float {
+            return match text
+                .parse::<f64>()
+                .ok()
+                .and_then(|f| Float::try_from(f).ok())
+            {
+                Some(float) => Ok(float.into()),
+                None => self.error(format!("invalid float: {}", text)),
+            };
+        }
+        let int = match text.split_once('#') {
+            Some((base, digits)) => {
+                let (sign, base) = match base.strip_prefix('-') {
+                    Some(base) => (-1, base),
+                    None => (1, base),
+                };
+                base.parse()
+                    .ok()
+                    .filter(|base| (2..=36).contains(base))
+                    .and_then(|base| i64::from_str_radix(digits, base).ok())
+                    .map(|int| sign * int)
+            }
+            None => text.parse::<i64>().ok(),
+        };
+        match int {
+            Some(int) => Ok(match i32::try_from(int) {
+                Ok(int) => FixInteger::from(int).into(),
+                Err(_) => BigInteger::from(int).into(),
+            }),
+            None => self.error(format!("invalid integer: {}", text)),
+        }
+    }
+}
+
+/// Strings are lists of code points, compactly encoded as bytes when every
+/// one of them fits, like the External Term Format does
+fn string_term(string: String) -> Term {
+    if string.is_empty() {
+        List::nil().into()
+    } else if string.chars().all(|c| (c as u32) < 256) {
+        ByteList::from(string.chars().map(|c| c as u8).collect::<Vec<_>>()).into()
+    } else {
+        let chars = string
+            .chars()
+            .map(|c| FixInteger::from(c as i32).into())
+            .collect::<Vec<_>>();
+        List::from(chars).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::etf;
+
+    fn parse(text: &str) -> Term {
+        let mut terms = consult(text).unwrap();
+        assert_eq!(terms.len(), 1, "{:?}", terms);
+        terms.remove(0)
+    }
+
+    #[test]
+    fn atoms() {
+        assert_eq!(etf::atom(&parse("kernel.")), Some("kernel"));
+        assert_eq!(etf::atom(&parse("node@host.")), Some("node@host"));
+        assert_eq!(etf::atom(&parse("'Quoted Atom'.")), Some("Quoted Atom"));
+        assert_eq!(etf::atom(&parse(r"'it\'s'.")), Some("it's"));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(etf::string(&parse("\"text\".")).as_deref(), Some("text"));
+        assert_eq!(etf::string(&parse("\"a\" \"b\".")).as_deref(), Some("ab"));
+        assert_eq!(etf::string(&parse(r#""a\nb"."#)).as_deref(), Some("a\nb"));
+        assert_eq!(etf::list(&parse("\"\".")), Some(&[][..]));
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(etf::int(&parse("42.")), Some(42));
+        assert_eq!(etf::int(&parse("-7.")), Some(-7));
+        assert_eq!(etf::int(&parse("16#ff.")), Some(255));
+        assert_eq!(etf::int(&parse("$a.")), Some(97));
+    }
+
+    #[test]
+    fn tuples_and_lists() {
+        let term = parse("{application, app, [{vsn, \"1.0\"}, {modules, []}]}.");
+        let elements = etf::tuple(&term).unwrap();
+        assert_eq!(etf::atom(&elements[0]), Some("application"));
+        assert_eq!(etf::atom(&elements[1]), Some("app"));
+        let vsn = etf::proplist_get(&elements[2], "vsn").unwrap();
+        assert_eq!(etf::string(vsn).as_deref(), Some("1.0"));
+        let modules = etf::proplist_get(&elements[2], "modules").unwrap();
+        assert_eq!(etf::list(modules), Some(&[][..]));
+        assert_eq!(etf::tuple(&parse("{}.")), Some(&[][..]));
+    }
+
+    #[test]
+    fn maps() {
+        let term = parse("#{key => value, \"k\" => 1}.");
+        assert_eq!(
+            etf::map_get(&term, "key").and_then(etf::atom),
+            Some("value")
+        );
+        assert_eq!(etf::map_get(&parse("#{}."), "key"), None);
+    }
+
+    #[test]
+    fn several_terms_and_comments() {
+        let terms = consult("% comment\n{a, 1}.\n\n%% another\nb. % trailing\n").unwrap();
+        assert_eq!(terms.len(), 2);
+        assert_eq!(etf::atom(&terms[1]), Some("b"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let err = consult("{a, b.").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: expected `}`, found `.`");
+        let err = consult("\n[a, b]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: expected `.`, found the end of the file"
+        );
+        assert!(consult("'unterminated.").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{de, Deserialize, Deserializer};
+
+/// A UTC calendar timestamp, as recorded by the compiler in the CInf chunk
+#[derive(Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Copy, Clone)]
+pub struct Timestamp {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl Timestamp {
+    pub fn from_system_time(time: SystemTime) -> Timestamp {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        let secs = secs % 86400;
+
+        Timestamp {
+            year,
+            month,
+            day,
+            hour: (secs / 3600) as u32,
+            minute: (secs % 3600 / 60) as u32,
+            second: (secs % 60) as u32,
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)?;
+        if (self.hour, self.minute, self.second) != (0, 0, 0) {
+            write!(f, "T{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserialized from the same strings `FromStr` accepts
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = anyhow::Error;
+
+    /// Accepts `YYYY-MM-DD`, optionally followed by `THH:MM:SS`
+    fn from_str(s: &str) -> Result<Timestamp> {
+        let parse = || -> Option<Timestamp> {
+            let (date, time) = match s.find('T') {
+                Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+                None => (s, None),
+            };
+            let date: Vec<u32> = date
+                .split('-')
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .ok()?;
+            let time: Vec<u32> = match time {
+                Some(time) => time
+                    .split(':')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .ok()?,
+                None => vec![0, 0, 0],
+            };
+            match (date.as_slice(), time.as_slice()) {
+                (&[year, month, day], &[hour, minute, second])
+                    if (1..=12).contains(&month) && (1..=31).contains(&day) =>
+                {
+                    Some(Timestamp {
+                        year,
+                        month,
+                        day,
+                        hour,
+                        minute,
+                        second,
+                    })
+                }
+                _ => None,
+            }
+        };
+
+        parse().with_context(|| format!("invalid date: {} (expected YYYY-MM-DD)", s))
+    }
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (u32, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year as u32, month as u32, day as u32)
+}
@@ -0,0 +1,126 @@
+//! The application dependency tree, like `rebar3 tree`, from the
+//! dependencies declared in the `.app` files.
+
+use std::io::Write;
+
+use anyhow::Result;
+use fxhash::FxHashSet;
+
+use crate::{analyzer::Analyzer, types::Atom};
+
+/// Applications that no other loaded application depends on, sorted by
+/// name - the natural roots of the tree
+fn top_level(analyzer: &Analyzer) -> Vec<Atom> {
+    let deps: FxHashSet<_> = analyzer
+        .app_modules()
+        .keys()
+        .flat_map(|&app| children(analyzer, app))
+        .map(|(dep, _)| dep)
+        .collect();
+    let mut apps: Vec<_> = analyzer
+        .app_modules()
+        .keys()
+        .copied()
+        .filter(|app| !deps.contains(app))
+        .collect();
+    apps.sort_by_key(|&app| analyzer.name(app));
+    apps
+}
+
+/// Declared dependencies of `app`, then its included applications, with
+/// whether they are included
+fn children(analyzer: &Analyzer, app: Atom) -> Vec<(Atom, bool)> {
+    let deps = analyzer.declared_deps(app).iter().map(|&dep| (dep, false));
+    let included = analyzer.included_apps(app).iter().map(|&dep| (dep, true));
+    deps.chain(included).collect()
+}
+
+/// Writes the tree below every root. An application whose dependencies
+/// were already printed is marked with `(*)`, one depending on itself
+/// through its dependencies with `(cycle)`, an included one with
+/// `(included)`. Without roots, all loaded applications are written,
+/// starting from the top level ones.
+pub fn write_tree(analyzer: &Analyzer, roots: &[Atom], out: &mut dyn Write) -> Result<()> {
+    let mut printer = Printer {
+        analyzer,
+        expanded: FxHashSet::default(),
+        path: vec![],
+        out,
+    };
+    if !roots.is_empty() {
+        for &root in roots {
+            printer.write(root, "", None, false)?;
+        }
+        return Ok(());
+    }
+
+    for root in top_level(analyzer) {
+        printer.write(root, "", None, false)?;
+    }
+    // Applications in a dependency cycle aren't below any top level one
+    let mut rest: Vec<_> = analyzer.app_modules().keys().copied().collect();
+    rest.sort_by_key(|&app| analyzer.name(app));
+    for app in rest {
+        if !printer.expanded.contains(&app) {
+            printer.write(app, "", None, false)?;
+        }
+    }
+    Ok(())
+}
+
+struct Printer<'a> {
+    analyzer: &'a Analyzer,
+    expanded: FxHashSet<Atom>,
+    /// Applications from the root to the one being printed
+    path: Vec<Atom>,
+    out: &'a mut dyn Write,
+}
+
+impl Printer<'_> {
+    /// `last` is whether the application is the last dependency of its
+    /// parent, `None` for a root, `included` whether the parent includes it
+    fn write(&mut self, app: Atom, prefix: &str, last: Option<bool>, included: bool) -> Result<()> {
+        let branch = match last {
+            None => "",
+            Some(false) => "├─ ",
+            Some(true) => "└─ ",
+        };
+        let name = self.analyzer.name(app);
+        let mut label = match self.analyzer.app_version(app) {
+            Some(vsn) => format!("{} {}", name, vsn),
+            None => name.to_string(),
+        };
+        if included {
+            label.push_str(" (included)");
+        }
+        let children = children(self.analyzer, app);
+
+        let loaded = self.analyzer.app(name).is_some();
+        let marker = if !loaded {
+            " (not found)"
+        } else if self.path.contains(&app) {
+            " (cycle)"
+        } else if self.expanded.contains(&app) && !children.is_empty() {
+            " (*)"
+        } else {
+            ""
+        };
+        writeln!(self.out, "{}{}{}{}", prefix, branch, label, marker)?;
+        if !marker.is_empty() {
+            return Ok(());
+        }
+
+        self.expanded.insert(app);
+        self.path.push(app);
+        let prefix = match last {
+            None => String::new(),
+            Some(false) => format!("{}│  ", prefix),
+            Some(true) => format!("{}   ", prefix),
+        };
+        for (idx, &(dep, included)) in children.iter().enumerate() {
+            self.write(dep, &prefix, Some(idx + 1 == children.len()), included)?;
+        }
+        self.path.pop();
+        Ok(())
+    }
+}
@@ -1,15 +1,193 @@
-use fxhash::FxHashMap;
+use std::path::PathBuf;
+
+use eetf::Term;
+use fxhash::{FxHashMap, FxHashSet};
 use petgraph::graphmap::DiGraphMap;
 use string_interner::{symbol::SymbolU32, DefaultBackend, StringInterner};
 
+use crate::timestamp::Timestamp;
+
 pub type Imports = FxHashMap<Atom, Vec<(Atom, u32)>>;
 pub type Exports = Vec<(Atom, u32)>;
-pub type Modules = FxHashMap<Atom, (Imports, Exports)>;
+/// Module, function and arity
+pub type Mfa = (Atom, Atom, u32);
+/// Functions called by every function of a module, by name and arity
+pub type FunctionCalls = FxHashMap<(Atom, u32), Vec<Mfa>>;
+/// Values of the module's attributes, by name, in the order of the Attr
+/// chunk. An attribute given more than once has a value for each.
+pub type Attributes = FxHashMap<Atom, Vec<Term>>;
+/// Modules every function checks are loaded before calling them, with the
+/// function checking, like `code:ensure_loaded/1`
+pub type LoadChecks = FxHashMap<(Atom, u32), Vec<(Atom, Mfa)>>;
+pub type Modules = FxHashMap<Atom, Module>;
 pub type AppModules = FxHashMap<Atom, Vec<Atom>>;
 pub type AppDeps = DiGraphMap<Atom, ()>;
+pub type AppPaths = FxHashMap<Atom, PathBuf>;
+pub type AppVersions = FxHashMap<Atom, String>;
+/// Application callback modules, from `mod` in the .app files
+pub type AppCallbacks = FxHashMap<Atom, Atom>;
+/// Beam files of modules that another application on the lib paths also
+/// has, and which lose to it
+pub type ShadowedModules = FxHashMap<Atom, Vec<PathBuf>>;
+pub type DeclaredDeps = FxHashMap<Atom, Vec<Atom>>;
+pub type RegisteredNames = FxHashMap<Atom, Vec<Atom>>;
 
 pub type Interner = StringInterner<SymbolU32, DefaultBackend<SymbolU32>, fxhash::FxBuildHasher>;
 
+/// Everything the loader learned from the lib paths
+#[derive(Debug)]
+pub struct Workspace {
+    pub interner: Interner,
+    pub modules: Modules,
+    pub app_modules: AppModules,
+    /// Dependencies between loaded applications
+    pub app_deps: AppDeps,
+    /// Dependencies as listed in the .app files, including ones that
+    /// weren't found on the lib paths
+    pub declared_deps: DeclaredDeps,
+    /// `included_applications` from the .app files, started by the
+    /// including application rather than on their own
+    pub included_apps: DeclaredDeps,
+    /// `optional_applications` from the .app files, which may be missing
+    /// at runtime
+    pub optional_deps: DeclaredDeps,
+    /// Applications in `runtime_dependencies` of the .app files, for the
+    /// applications that have the key
+    pub runtime_deps: DeclaredDeps,
+    /// Process names in `registered` of the .app files
+    pub registered: RegisteredNames,
+    /// Application directory (the parent of `ebin`) each app was loaded from
+    pub app_paths: AppPaths,
+    /// `vsn` from the .app files
+    pub app_versions: AppVersions,
+    pub app_callbacks: AppCallbacks,
+    /// Modules with the same name as one loaded from an earlier
+    /// application, see `ShadowedModules`
+    pub shadowed: ShadowedModules,
+    /// `Module::build` of the shadowed beam files
+    pub shadowed_builds: FxHashMap<PathBuf, u64>,
+    /// `modules` from the .app files
+    pub listed_modules: AppModules,
+    /// Applications loaded only from test lib paths
+    pub test_apps: FxHashSet<Atom>,
+    /// Applications and modules found on the lib paths, but not loaded
+    /// because they were excluded
+    pub excluded: FxHashSet<Atom>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// The .beam file the module was loaded from
+    pub path: PathBuf,
+    pub imports: Imports,
+    pub exports: Exports,
+    /// Every attribute kept in the Attr chunk, including custom ones. The
+    /// ones checks rely on are also parsed into the fields below.
+    pub attributes: Attributes,
+    /// Functions defined but not exported, from the LocT chunk, including
+    /// the ones the compiler generates, like `-fun-0-` for funs
+    pub locals: Exports,
+    /// Taken from the compile info chunk, or the modification time
+    /// of the .beam file if the compiler didn't record it
+    pub compiled: Option<Timestamp>,
+    /// Source file the module was compiled from, as recorded by the compiler
+    pub source: Option<PathBuf>,
+    /// Strings found in the literal table, anywhere inside the literals
+    pub literal_strings: Vec<String>,
+    /// Modules of the `parse_transform` compile options, needed to compile
+    /// the module but not to run it
+    pub parse_transforms: Vec<Atom>,
+    /// From `-deprecated` attributes, and the `deprecated` metadata of the
+    /// Docs chunk
+    pub deprecated: Vec<Deprecation>,
+    /// Modules named in `-behaviour` attributes
+    pub behaviours: Vec<Atom>,
+    /// Whether the module is an Elixir protocol, dispatching to its
+    /// implementations at runtime
+    pub protocol: bool,
+    /// The Elixir protocol the module implements
+    pub protocol_impl: Option<Atom>,
+    /// The function of the `-on_load` attribute, run when the module is
+    /// loaded
+    pub on_load: Option<Atom>,
+    /// From `-ignore_xref` attributes, see `IgnoreXref`
+    pub ignore_xref: Vec<IgnoreXref>,
+    /// Calls found in the literal table, see `DynamicCall`
+    pub dynamic_calls: Vec<DynamicCall>,
+    /// Calls made by every function, including local ones and the
+    /// functions of funs it creates. Taken from the debug info if the code
+    /// couldn't be disassembled, e.g. compiled by a newer OTP release, and
+    /// `None` without it.
+    pub function_calls: Option<FunctionCalls>,
+    /// Line of the first call or capture of every remote function, from the
+    /// debug info. Empty if the module was compiled without it.
+    pub call_lines: FxHashMap<Mfa, u32>,
+    /// See `LoadChecks`, empty if the code couldn't be disassembled
+    pub load_checks: LoadChecks,
+    /// Atoms the functions calling `crypto` refer to, by `crypto` function
+    /// called, to tell the algorithms used
+    pub crypto_atoms: FxHashMap<Mfa, Vec<Atom>>,
+    /// Hash of the chunks `beam_lib:md5/1` covers: atoms, imports, exports,
+    /// code and literals. Equal for builds of the same code, whatever the
+    /// compile time or path.
+    pub build: u64,
+    /// Documented as hidden in the Docs chunk, with `@moduledoc false` or
+    /// `-moduledoc false`
+    pub hidden: bool,
+    /// Docs chunk entries of the functions with any `FunctionDoc` metadata
+    pub docs: FxHashMap<(Atom, u32), FunctionDoc>,
+}
+
+/// What the Docs chunk says about a function
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionDoc {
+    /// Documented as hidden, with `@doc false` or `-doc false`
+    pub hidden: bool,
+    /// The `deprecated` metadata, as set by Elixir's `@deprecated`
+    pub deprecated: Option<String>,
+    /// The `since` metadata: the version that added the function
+    pub since: Option<String>,
+}
+
+/// A function referenced by a literal instead of called directly: a
+/// `{Module, Function, Args}` tuple or a `fun Module:Function/Arity`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DynamicCall {
+    pub module: Atom,
+    pub function: Atom,
+    pub arity: u32,
+    /// Whether it's a fun reference, certainly a call rather than some
+    /// tuple that looks like one
+    pub fun_ref: bool,
+}
+
+/// A module or function whose findings the module's author suppressed
+/// with `-ignore_xref([M, {F, A}, {M, F, A}])`, or `-xref_ignore`. `{F, A}`
+/// is a function of the module itself.
+#[derive(Debug, Clone)]
+pub struct IgnoreXref {
+    pub module: Atom,
+    pub function: Option<(Atom, u32)>,
+}
+
+/// A `-deprecated` declaration. The function and arity are `None` for
+/// `'_'`, both of them for a deprecated module.
+#[derive(Debug, Clone)]
+pub struct Deprecation {
+    pub function: Option<Atom>,
+    pub arity: Option<u32>,
+    /// What to use instead, or when the function goes away
+    pub hint: Option<String>,
+}
+
+impl Deprecation {
+    pub fn matches(&self, function: Atom, arity: u32) -> bool {
+        self.function
+            .is_none_or(|deprecated| deprecated == function)
+            && self.arity.is_none_or(|deprecated| deprecated == arity)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Copy, Clone)]
 pub struct Atom(pub SymbolU32);
 
@@ -18,6 +196,11 @@ impl Atom {
         Atom(interner.get_or_intern(value))
     }
 
+    /// The atom of an already interned string
+    pub fn get(interner: &Interner, value: &str) -> Option<Atom> {
+        interner.get(value).map(Atom)
+    }
+
     pub fn resolve<'a>(&self, interner: &'a Interner) -> Option<&'a str> {
         interner.resolve(self.0)
     }
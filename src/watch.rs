@@ -0,0 +1,75 @@
+//! Waiting for changes to the lib paths, for `--watch`. The directories are
+//! polled rather than subscribed to, which also works on network storage.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::loader::expand_globs;
+
+/// Modification times of all `.beam` and `.app` files in the lib paths, and
+/// of the `.erl` and `.app.src` files of the applications read from source
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Snapshot(BTreeMap<PathBuf, SystemTime>);
+
+impl Snapshot {
+    /// Unreadable or missing directories are skipped, they may come back
+    pub fn take(lib_paths: &[PathBuf]) -> Snapshot {
+        let mut files = BTreeMap::new();
+        let paths = expand_globs(lib_paths).unwrap_or_default();
+        let dirs = paths.into_iter().flat_map(|path| {
+            if path.file_name() == Some(OsStr::new("ebin")) {
+                vec![path]
+            } else {
+                fs::read_dir(&path)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .flat_map(|app_path| [app_path.join("ebin"), app_path.join("src")])
+                    .collect()
+            }
+        });
+        for dir in dirs {
+            for entry in fs::read_dir(&dir).into_iter().flatten() {
+                let path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(_) => continue,
+                };
+                let extension = path.extension().and_then(OsStr::to_str);
+                if !matches!(extension, Some("beam" | "app" | "erl" | "src")) {
+                    continue;
+                }
+                if let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) {
+                    files.insert(path, modified);
+                }
+            }
+        }
+        Snapshot(files)
+    }
+
+    /// Blocks until the files differ from the snapshot, and then until they
+    /// stay the same for one more interval, so that a build that's still
+    /// writing beams triggers only once. Returns the new snapshot.
+    pub fn wait_for_change(&self, lib_paths: &[PathBuf], interval: Duration) -> Snapshot {
+        let mut current = loop {
+            thread::sleep(interval);
+            let current = Snapshot::take(lib_paths);
+            if current != *self {
+                break current;
+            }
+        };
+        loop {
+            thread::sleep(interval);
+            let next = Snapshot::take(lib_paths);
+            if next == current {
+                return current;
+            }
+            current = next;
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Explaining how one application depends on another: paths through the
+//! application graph, with the calls backing every step.
+
+use std::{collections::BTreeMap, io::Write};
+
+use anyhow::Result;
+use fxhash::FxHashMap;
+use petgraph::algo;
+
+use crate::{analyzer::Analyzer, types::Atom};
+
+/// A shortest path from `from` to `to`, or all of them without cycles when
+/// `all` is set, shortest first
+pub fn paths(analyzer: &Analyzer, from: Atom, to: Atom, all: bool) -> Vec<Vec<Atom>> {
+    let graph = analyzer.app_deps();
+    if !graph.contains_node(from) || !graph.contains_node(to) || from == to {
+        return vec![];
+    }
+
+    if all {
+        let mut paths: Vec<Vec<Atom>> = algo::all_simple_paths(graph, from, to, 0, None).collect();
+        paths.sort_by(|a, b| {
+            let names = |path: &[Atom]| -> Vec<&str> {
+                path.iter().map(|&app| analyzer.name(app)).collect()
+            };
+            (a.len(), names(a)).cmp(&(b.len(), names(b)))
+        });
+        return paths;
+    }
+
+    // Breadth first, visiting dependencies by name for a stable answer
+    let mut parents = FxHashMap::default();
+    let mut queue = vec![from];
+    while !queue.is_empty() {
+        let mut next = vec![];
+        for app in queue {
+            let mut deps: Vec<_> = graph.neighbors(app).collect();
+            deps.sort_by_key(|&dep| analyzer.name(dep));
+            for dep in deps {
+                if dep == from || parents.contains_key(&dep) {
+                    continue;
+                }
+                parents.insert(dep, app);
+                if dep == to {
+                    let mut path = vec![to];
+                    while let Some(&parent) = parents.get(path.last().unwrap()) {
+                        path.push(parent);
+                        if parent == from {
+                            break;
+                        }
+                    }
+                    path.reverse();
+                    return vec![path];
+                }
+                next.push(dep);
+            }
+        }
+        queue = next;
+    }
+    vec![]
+}
+
+/// Calls from modules of `from` into modules of `to`, as functions called
+/// for every (caller, callee) module pair
+pub fn evidence(
+    analyzer: &Analyzer,
+    from: Atom,
+    to: Atom,
+) -> BTreeMap<(String, String), Vec<String>> {
+    let mut calls = BTreeMap::<_, Vec<_>>::new();
+    for (caller, module, fun, arity) in analyzer.calls_into(to) {
+        if analyzer.owner(caller) == Some(from) {
+            calls
+                .entry((
+                    analyzer.name(caller).to_string(),
+                    analyzer.name(module).to_string(),
+                ))
+                .or_default()
+                .push(format!("{}/{}", analyzer.name(fun), arity));
+        }
+    }
+    calls
+}
+
+pub fn write_paths(analyzer: &Analyzer, paths: &[Vec<Atom>], out: &mut dyn Write) -> Result<()> {
+    for (idx, path) in paths.iter().enumerate() {
+        if idx > 0 {
+            writeln!(out)?;
+        }
+        let names: Vec<_> = path.iter().map(|&app| analyzer.name(app)).collect();
+        writeln!(out, "{}", names.join(" -> "))?;
+
+        for step in path.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            writeln!(out, "  {} -> {}", analyzer.name(from), analyzer.name(to))?;
+            let calls = evidence(analyzer, from, to);
+            if calls.is_empty() {
+                let reason = if analyzer.declared_deps(from).contains(&to) {
+                    "declared in the .app file, but not called"
+                } else if analyzer.included_apps(from).contains(&to) {
+                    "included by the .app file, but not called"
+                } else {
+                    "implicit dependency"
+                };
+                writeln!(out, "    ({})", reason)?;
+            }
+            for ((caller, module), functions) in calls {
+                writeln!(
+                    out,
+                    "    {} -> {}: {}",
+                    caller,
+                    module,
+                    functions.join(", ")
+                )?;
+            }
+        }
+    }
+    Ok(())
+}